@@ -0,0 +1,19 @@
+-- =====================================================
+-- Migration: Full-text search over nav content and note titles
+-- =====================================================
+
+-- The 'simple' configuration does no stemming or stop-word removal, so it
+-- behaves the same for every language stored in the outline.
+ALTER TABLE hulunote_navs
+ADD COLUMN IF NOT EXISTS content_tsv tsvector
+    GENERATED ALWAYS AS (to_tsvector('simple', content)) STORED;
+
+ALTER TABLE hulunote_notes
+ADD COLUMN IF NOT EXISTS title_tsv tsvector
+    GENERATED ALWAYS AS (to_tsvector('simple', title)) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_navs_content_tsv ON hulunote_navs USING GIN (content_tsv);
+CREATE INDEX IF NOT EXISTS idx_hulunote_notes_title_tsv ON hulunote_notes USING GIN (title_tsv);
+
+COMMENT ON COLUMN hulunote_navs.content_tsv IS 'Search vector over content, maintained by PostgreSQL';
+COMMENT ON COLUMN hulunote_notes.title_tsv IS 'Search vector over title, maintained by PostgreSQL';
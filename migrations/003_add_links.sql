@@ -0,0 +1,40 @@
+-- =====================================================
+-- Migration: Links and backlinks between navs and notes
+-- =====================================================
+
+-- One row per [[Page]] or ((nav-uuid)) reference found in a nav's content.
+-- Page links are stored by title and resolved when queried, so they also
+-- point at notes created after the reference was written.
+CREATE TABLE IF NOT EXISTS hulunote_links (
+    id BIGSERIAL PRIMARY KEY,
+    source_nav_id UUID NOT NULL,
+    source_note_id VARCHAR(36) NOT NULL,
+    database_id VARCHAR(36) NOT NULL,
+    link_type TEXT NOT NULL,  -- 'page' for [[Title]], 'block' for ((nav-uuid))
+    target_title TEXT,
+    target_nav_id UUID,
+    created_at TIMESTAMP(6) WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_links_source_nav_id ON hulunote_links(source_nav_id);
+CREATE INDEX IF NOT EXISTS idx_hulunote_links_target_title ON hulunote_links(database_id, target_title);
+CREATE INDEX IF NOT EXISTS idx_hulunote_links_target_nav_id ON hulunote_links(target_nav_id);
+
+-- Index the references already present in existing navs
+INSERT INTO hulunote_links (source_nav_id, source_note_id, database_id, link_type, target_title, target_nav_id)
+SELECT source_nav_id, source_note_id, database_id, link_type, target_title, target_nav_id
+FROM (
+    SELECT v.id AS source_nav_id, v.note_id AS source_note_id, v.database_id,
+           'page' AS link_type, m[1] AS target_title, NULL::uuid AS target_nav_id
+    FROM hulunote_navs v,
+         regexp_matches(v.content, '\[\[([^\[\]]+)\]\]', 'g') AS m
+    UNION ALL
+    SELECT v.id, v.note_id, v.database_id, 'block', NULL, m[1]::uuid
+    FROM hulunote_navs v,
+         regexp_matches(v.content, '\(\(([0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})\)\)', 'g') AS m
+) refs
+WHERE NOT EXISTS (SELECT 1 FROM hulunote_links l WHERE l.source_nav_id = refs.source_nav_id);
+
+COMMENT ON TABLE hulunote_links IS 'References from nav content to notes ([[Title]]) and navs ((uuid))';
+COMMENT ON COLUMN hulunote_links.target_title IS 'Referenced note title for page links';
+COMMENT ON COLUMN hulunote_links.target_nav_id IS 'Referenced nav id for block references';
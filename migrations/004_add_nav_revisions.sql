@@ -0,0 +1,33 @@
+-- =====================================================
+-- Migration: Version history for navs
+-- =====================================================
+
+-- One row per change to a nav, holding the nav's state after the change.
+-- The state of a note at a point in time is the latest revision of each
+-- of its navs at or before that time.
+CREATE TABLE IF NOT EXISTS hulunote_nav_revisions (
+    id BIGSERIAL PRIMARY KEY,
+    nav_id UUID NOT NULL,
+    note_id VARCHAR(36) NOT NULL,
+    database_id VARCHAR(36) NOT NULL,
+    account_id BIGINT NOT NULL,
+    op TEXT NOT NULL,  -- 'create', 'import', 'update', 'delete' or 'restore'
+    parid VARCHAR(36) NOT NULL,
+    same_deep_order REAL NOT NULL,
+    content TEXT NOT NULL,
+    properties TEXT NOT NULL,
+    is_display BOOLEAN NOT NULL,
+    is_delete BOOLEAN NOT NULL,
+    created_at TIMESTAMP(6) WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_nav_revisions_nav_id ON hulunote_nav_revisions(nav_id, id);
+CREATE INDEX IF NOT EXISTS idx_hulunote_nav_revisions_note_id ON hulunote_nav_revisions(note_id, created_at);
+
+-- Existing navs start their history with their current state
+INSERT INTO hulunote_nav_revisions (nav_id, note_id, database_id, account_id, op, parid,
+                                    same_deep_order, content, properties, is_display, is_delete, created_at)
+SELECT v.id, v.note_id, v.database_id, v.account_id, 'create', v.parid,
+       v.same_deep_order, v.content, v.properties, v.is_display, v.is_delete, v.updated_at
+FROM hulunote_navs v
+WHERE NOT EXISTS (SELECT 1 FROM hulunote_nav_revisions r WHERE r.nav_id = v.id);
@@ -0,0 +1,18 @@
+-- =====================================================
+-- Migration: Trash for soft-deleted databases, notes and navs
+-- =====================================================
+
+-- When an item was moved to the trash. Items deleted together (a database
+-- and the notes and navs it cascaded to) share the same timestamp, which is
+-- how a restore knows what to bring back with them.
+ALTER TABLE hulunote_databases ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP(6) WITH TIME ZONE;
+ALTER TABLE hulunote_notes ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP(6) WITH TIME ZONE;
+ALTER TABLE hulunote_navs ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP(6) WITH TIME ZONE;
+
+UPDATE hulunote_databases SET deleted_at = updated_at WHERE is_delete = true AND deleted_at IS NULL;
+UPDATE hulunote_notes SET deleted_at = updated_at WHERE is_delete = true AND deleted_at IS NULL;
+UPDATE hulunote_navs SET deleted_at = updated_at WHERE is_delete = true AND deleted_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_databases_deleted_at ON hulunote_databases(deleted_at) WHERE is_delete = true;
+CREATE INDEX IF NOT EXISTS idx_hulunote_notes_deleted_at ON hulunote_notes(deleted_at) WHERE is_delete = true;
+CREATE INDEX IF NOT EXISTS idx_hulunote_navs_deleted_at ON hulunote_navs(deleted_at) WHERE is_delete = true;
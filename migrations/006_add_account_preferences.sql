@@ -0,0 +1,8 @@
+-- =====================================================
+-- Migration: Timezone and date format preferences
+-- =====================================================
+
+-- IANA timezone name (e.g. `Asia/Shanghai`) that decides which day is "today"
+-- for daily notes, and the strftime format used for daily-note titles.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS timezone TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS date_format TEXT NOT NULL DEFAULT '%Y-%m-%d';
@@ -0,0 +1,9 @@
+-- =====================================================
+-- Migration: Note templates
+-- =====================================================
+
+-- Template notes are copied into new notes or under a nav by
+-- /hulunote/instantiate-template.
+ALTER TABLE hulunote_notes ADD COLUMN IF NOT EXISTS is_template BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_notes_is_template ON hulunote_notes(database_id) WHERE is_template = true;
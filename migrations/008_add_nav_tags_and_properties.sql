@@ -0,0 +1,48 @@
+-- =====================================================
+-- Migration: Tags and properties of navs
+-- =====================================================
+
+-- One row per #tag or #[[multi word tag]] in a nav's content, and per entry
+-- of a `tags::` property. Rows follow the nav, so moving it to another note
+-- or database needs no re-indexing; queries join hulunote_navs for that.
+CREATE TABLE IF NOT EXISTS hulunote_nav_tags (
+    nav_id UUID NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (nav_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_nav_tags_tag ON hulunote_nav_tags(lower(tag));
+
+-- One row per `key:: value` line (or JSON object entry) of a nav's
+-- properties. Keys are lowercased; number_value is set when the value is a
+-- number, for range queries.
+CREATE TABLE IF NOT EXISTS hulunote_nav_properties (
+    nav_id UUID NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    number_value DOUBLE PRECISION,
+    PRIMARY KEY (nav_id, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_nav_properties_value ON hulunote_nav_properties(key, value);
+CREATE INDEX IF NOT EXISTS idx_hulunote_nav_properties_number ON hulunote_nav_properties(key, number_value);
+
+-- Index the tags and `key:: value` properties already present in existing
+-- navs. Navs with JSON properties are indexed the next time they are saved.
+INSERT INTO hulunote_nav_tags (nav_id, tag)
+SELECT DISTINCT v.id, COALESCE(m[1], m[2])
+FROM hulunote_navs v,
+     regexp_matches(v.content, '(?:^|\s)#(?:\[\[([^\[\]]+)\]\]|([[:alnum:]_/-]+))', 'g') AS m
+ON CONFLICT DO NOTHING;
+
+INSERT INTO hulunote_nav_properties (nav_id, key, value, number_value)
+SELECT DISTINCT ON (v.id, lower(m[1]))
+       v.id, lower(m[1]), m[2],
+       CASE WHEN m[2] ~ '^-?[0-9]+(\.[0-9]+)?$' THEN m[2]::double precision END
+FROM hulunote_navs v,
+     regexp_matches(v.properties, '^\s*([[:alnum:]_-]+)::(?: (.*?))?\s*$', 'gn') AS m
+WHERE m[2] IS NOT NULL
+ON CONFLICT DO NOTHING;
+
+COMMENT ON TABLE hulunote_nav_tags IS '#tags found in nav content and tags:: properties';
+COMMENT ON TABLE hulunote_nav_properties IS 'Parsed key:: value properties of navs';
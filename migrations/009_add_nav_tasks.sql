@@ -0,0 +1,33 @@
+-- =====================================================
+-- Migration: Tasks
+-- =====================================================
+
+-- One row per nav whose content starts with a task marker (TODO, DOING,
+-- DONE, CANCELED and their Logseq/Roam spellings). Dates come from
+-- DEADLINE:/SCHEDULED: in the content or due::/scheduled:: properties.
+CREATE TABLE IF NOT EXISTS hulunote_nav_tasks (
+    nav_id UUID PRIMARY KEY,
+    status TEXT NOT NULL,      -- 'todo', 'doing', 'done' or 'canceled'
+    priority TEXT,             -- 'A', 'B' or 'C' from [#A]
+    due_date DATE,
+    scheduled_date DATE
+);
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_nav_tasks_due ON hulunote_nav_tasks(status, due_date);
+
+-- Index the task markers already present in existing navs. Dates and
+-- priorities are indexed the next time a nav is saved.
+INSERT INTO hulunote_nav_tasks (nav_id, status)
+SELECT v.id,
+       CASE m[1]
+           WHEN 'TODO' THEN 'todo' WHEN 'LATER' THEN 'todo'
+           WHEN 'DOING' THEN 'doing' WHEN 'NOW' THEN 'doing'
+           WHEN 'DONE' THEN 'done'
+           ELSE 'canceled'
+       END
+FROM hulunote_navs v,
+     regexp_match(v.content, '^\s*(?:\{\{\[\[)?(TODO|LATER|DOING|NOW|DONE|CANCELED|CANCELLED)(?:\]\]\}\})?(?:\s|$)') AS m
+WHERE m IS NOT NULL
+ON CONFLICT DO NOTHING;
+
+COMMENT ON TABLE hulunote_nav_tasks IS 'Task markers, priorities and dates parsed from nav content';
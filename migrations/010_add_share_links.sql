@@ -0,0 +1,23 @@
+-- =====================================================
+-- Migration: Share links
+-- =====================================================
+
+-- Unguessable links that let anyone holding the token read a note, a nav's
+-- subtree or a whole database without logging in. Links are revoked rather
+-- than deleted so their view counts stay listed.
+CREATE TABLE IF NOT EXISTS hulunote_share_links (
+    id BIGSERIAL PRIMARY KEY,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    account_id BIGINT NOT NULL,
+    target_type TEXT NOT NULL,  -- 'note', 'nav' or 'database'
+    target_id VARCHAR(36) NOT NULL,
+    password_hash TEXT,
+    expires_at TIMESTAMP(6) WITH TIME ZONE,
+    view_count BIGINT NOT NULL DEFAULT 0,
+    last_viewed_at TIMESTAMP(6) WITH TIME ZONE,
+    revoked_at TIMESTAMP(6) WITH TIME ZONE,
+    created_at TIMESTAMP(6) WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_hulunote_share_links_account_id ON hulunote_share_links(account_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_hulunote_share_links_target ON hulunote_share_links(target_type, target_id);
@@ -0,0 +1,22 @@
+-- =====================================================
+-- Migration: Database members and last editors
+-- =====================================================
+
+-- Accounts a database is shared with, and their role in it. The account
+-- that created a database (hulunote_databases.account_id) is always an
+-- owner and has no row here.
+CREATE TABLE IF NOT EXISTS database_members (
+    database_id UUID NOT NULL,
+    account_id BIGINT NOT NULL,
+    role TEXT NOT NULL,  -- 'owner', 'editor', 'commenter' or 'viewer'
+    invited_by BIGINT NOT NULL,
+    created_at TIMESTAMP(6) WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP(6) WITH TIME ZONE NOT NULL DEFAULT now(),
+    PRIMARY KEY (database_id, account_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_database_members_account_id ON database_members(account_id);
+
+-- The account that last changed a nav. NULL until the nav is first edited,
+-- in which case its author (account_id) is the last editor.
+ALTER TABLE hulunote_navs ADD COLUMN IF NOT EXISTS last_account_id BIGINT;
@@ -0,0 +1,21 @@
+-- =====================================================
+-- Migration: Per-field clocks for collaborative editing
+-- =====================================================
+
+-- backend-ts (milliseconds) of the last write to each field of a nav, keyed
+-- by column name. Edits sent over the WebSocket only overwrite a field when
+-- they are at least as new as its clock (last writer wins per field).
+-- Navs that predate this migration start with every field at their
+-- updated_at.
+ALTER TABLE hulunote_navs ADD COLUMN IF NOT EXISTS field_clocks JSONB;
+
+UPDATE hulunote_navs
+SET field_clocks = (
+    SELECT jsonb_object_agg(field, (EXTRACT(EPOCH FROM updated_at) * 1000)::bigint)
+    FROM unnest(ARRAY['parid', 'same_deep_order', 'content', 'properties',
+                      'is_display', 'is_delete', 'is_public']) AS field
+)
+WHERE field_clocks IS NULL;
+
+ALTER TABLE hulunote_navs ALTER COLUMN field_clocks SET DEFAULT '{}';
+ALTER TABLE hulunote_navs ALTER COLUMN field_clocks SET NOT NULL;
@@ -0,0 +1,19 @@
+-- =====================================================
+-- Migration: WebSocket event log
+-- =====================================================
+
+-- Every event pushed over the WebSocket, so clients that were disconnected
+-- or fell behind can replay what they missed. seq orders the events.
+-- Events of a database have database_id set; events for one account only
+-- (profile changes) have account_id set instead.
+CREATE TABLE IF NOT EXISTS ws_events (
+    seq BIGSERIAL PRIMARY KEY,
+    database_id TEXT,
+    note_id TEXT,
+    account_id BIGINT,
+    backend_ts BIGINT NOT NULL,
+    payload JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ws_events_database_id ON ws_events(database_id, seq);
+CREATE INDEX IF NOT EXISTS idx_ws_events_backend_ts ON ws_events(backend_ts);
@@ -0,0 +1,9 @@
+-- =====================================================
+-- Migration: Indexes for walking the nav tree
+-- =====================================================
+
+-- The nav tree is walked down by parid and a note's navs are read by
+-- note_id. Both columns hold uuids as TEXT, so walks compare them against
+-- id::text, and walks up cast parid to uuid to use the primary key.
+CREATE INDEX IF NOT EXISTS idx_hulunote_navs_parid ON hulunote_navs(parid);
+CREATE INDEX IF NOT EXISTS idx_hulunote_navs_note_id ON hulunote_navs(note_id);
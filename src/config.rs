@@ -5,6 +5,16 @@ pub struct Config {
     pub database_url: String,
     pub jwt_secret: String,
     pub jwt_expiry_hours: i64,
+    /// Days deleted items stay in the trash before they are purged (0 keeps them forever)
+    pub trash_retention_days: i64,
+    /// Seconds between two runs of the trash purge job
+    pub trash_purge_interval_secs: u64,
+    /// Days WebSocket events are kept for clients to resume from
+    pub ws_event_retention_days: i64,
+    /// Seconds between two runs of the WebSocket event purge job
+    pub ws_event_purge_interval_secs: u64,
+    /// Accounts allowed to use the admin endpoints
+    pub admin_account_ids: Vec<i64>,
 }
 
 impl Config {
@@ -18,6 +28,29 @@ impl Config {
                 .unwrap_or_else(|_| "720".to_string()) // 30 days
                 .parse()
                 .expect("JWT_EXPIRY_HOURS must be a number"),
+            trash_retention_days: env::var("TRASH_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("TRASH_RETENTION_DAYS must be a number"),
+            trash_purge_interval_secs: env::var("TRASH_PURGE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .expect("TRASH_PURGE_INTERVAL_SECS must be a number"),
+            ws_event_retention_days: env::var("WS_EVENT_RETENTION_DAYS")
+                .unwrap_or_else(|_| "7".to_string())
+                .parse()
+                .expect("WS_EVENT_RETENTION_DAYS must be a number"),
+            ws_event_purge_interval_secs: env::var("WS_EVENT_PURGE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .expect("WS_EVENT_PURGE_INTERVAL_SECS must be a number"),
+            admin_account_ids: env::var("ADMIN_ACCOUNT_IDS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(|id| id.parse().expect("ADMIN_ACCOUNT_IDS must be a comma-separated list of account ids"))
+                .collect(),
         }
     }
 }
@@ -33,9 +33,11 @@ pub enum AppError {
     Bcrypt(#[from] bcrypt::BcryptError),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match &self {
+impl AppError {
+    /// HTTP status and client-facing message for this error. Internal
+    /// details are logged here rather than sent to the client.
+    pub fn status_and_message(&self) -> (StatusCode, String) {
+        match self {
             AppError::Database(e) => {
                 tracing::error!("Database error: {:?}", e);
                 // Extract useful message for unique constraint violations (PG error code 23505)
@@ -43,9 +45,7 @@ impl IntoResponse for AppError {
                     if db_err.code().as_deref() == Some("23505") {
                         // Duplicate key - return 409 Conflict with detail
                         let detail = db_err.message().to_string();
-                        return (StatusCode::CONFLICT, Json(json!({
-                            "error": format!("Duplicate entry: {}", detail)
-                        }))).into_response();
+                        return (StatusCode::CONFLICT, format!("Duplicate entry: {}", detail));
                     }
                 }
                 (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
@@ -63,7 +63,13 @@ impl IntoResponse for AppError {
                 tracing::error!("Bcrypt error: {:?}", e);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Password error".to_string())
             }
-        };
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = self.status_and_message();
 
         let body = Json(json!({
             "error": error_message
@@ -0,0 +1,195 @@
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::MemberRole;
+
+/// Kind of access a handler needs on a database, note or nav
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Change the database itself or who it is shared with
+    Manage,
+}
+
+impl Access {
+    /// Least role that grants this access
+    fn required_role(self) -> MemberRole {
+        match self {
+            Access::Read => MemberRole::Viewer,
+            Access::Write => MemberRole::Editor,
+            Access::Manage => MemberRole::Owner,
+        }
+    }
+}
+
+impl std::str::FromStr for MemberRole {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "viewer" => Ok(Self::Viewer),
+            "commenter" => Ok(Self::Commenter),
+            "editor" => Ok(Self::Editor),
+            "owner" => Ok(Self::Owner),
+            other => Err(AppError::Internal(format!("Unknown member role '{}'", other))),
+        }
+    }
+}
+
+/// Apply the shared access rule: members can do what their role allows,
+/// and everyone else can only read public content.
+fn check(role: Option<&str>, is_public: bool, access: Access, what: &str) -> Result<()> {
+    let role: Option<MemberRole> = role.map(str::parse).transpose()?;
+    if role.is_some_and(|role| role >= access.required_role()) {
+        return Ok(());
+    }
+    match access {
+        Access::Read if is_public => Ok(()),
+        Access::Read => Err(AppError::PermissionDenied(format!("Cannot read other's {}", what))),
+        Access::Write => Err(AppError::PermissionDenied(format!("Cannot modify other's {}", what))),
+        Access::Manage => Err(AppError::PermissionDenied(format!("Only owners can manage this {}", what))),
+    }
+}
+
+/// The caller's role in a database, or `None` when it is not shared with
+/// them. The account that created a database is always an owner.
+pub(crate) async fn database_role(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    database_id: Uuid,
+) -> Result<Option<MemberRole>> {
+    let row: Option<(Option<String>,)> = sqlx::query_as(
+        r#"
+        SELECT CASE WHEN d.account_id = $2 THEN 'owner' ELSE m.role END
+        FROM hulunote_databases d
+        LEFT JOIN database_members m ON m.database_id = d.id AND m.account_id = $2
+        WHERE d.id = $1
+        "#,
+    )
+    .bind(database_id)
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.and_then(|(role,)| role).map(|role| role.parse()).transpose()
+}
+
+/// Ids of the live databases the caller owns or is a member of
+pub(crate) async fn member_database_ids(pool: &sqlx::PgPool, account_id: i64) -> Result<Vec<String>> {
+    Ok(sqlx::query_as::<_, (Uuid,)>(
+        r#"
+        SELECT d.id FROM hulunote_databases d
+        WHERE d.is_delete = false
+        AND (d.account_id = $1
+             OR EXISTS (SELECT 1 FROM database_members m WHERE m.database_id = d.id AND m.account_id = $1))
+        "#,
+    )
+    .bind(account_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.0.to_string())
+    .collect())
+}
+
+/// Accounts a database is shared with, its creator included
+pub(crate) async fn database_account_ids(pool: &sqlx::PgPool, database_id: &str) -> Result<Vec<i64>> {
+    Ok(sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT d.account_id FROM hulunote_databases d WHERE d.id::text = $1
+        UNION
+        SELECT m.account_id FROM database_members m WHERE m.database_id::text = $1
+        "#,
+    )
+    .bind(database_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.0)
+    .collect())
+}
+
+/// Check access to a database
+pub async fn authorize_database(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    database_id: Uuid,
+    access: Access,
+) -> Result<()> {
+    let row: Option<(bool,)> = sqlx::query_as("SELECT is_public FROM hulunote_databases WHERE id = $1")
+        .bind(database_id)
+        .fetch_optional(pool)
+        .await?;
+    let (is_public,) = row.ok_or_else(|| AppError::NotFound("Database not found".to_string()))?;
+
+    let role = database_role(pool, account_id, database_id).await?;
+    check(role.map(MemberRole::as_str), is_public, access, "database")
+}
+
+/// Check access to a note and return its database id.
+///
+/// A note is governed by the members of its database and is visible when
+/// either the note or its database is public.
+pub async fn authorize_note(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    note_id: Uuid,
+    access: Access,
+) -> Result<String> {
+    let row: Option<(String, Option<String>, bool)> = sqlx::query_as(
+        r#"
+        SELECT n.database_id,
+               CASE WHEN COALESCE(d.account_id, n.account_id) = $2 THEN 'owner' ELSE m.role END,
+               n.is_public OR COALESCE(d.is_public, false)
+        FROM hulunote_notes n
+        LEFT JOIN hulunote_databases d ON d.id::text = n.database_id
+        LEFT JOIN database_members m ON m.database_id = d.id AND m.account_id = $2
+        WHERE n.id = $1
+        "#,
+    )
+    .bind(note_id)
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let (database_id, role, is_public) = row
+        .ok_or_else(|| AppError::NotFound("Note not found".to_string()))?;
+
+    check(role.as_deref(), is_public, access, "note")?;
+    Ok(database_id)
+}
+
+/// Check access to a nav and return its (note id, database id).
+///
+/// A nav is visible when the nav, its note or its database is public.
+pub async fn authorize_nav(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    nav_id: Uuid,
+    access: Access,
+) -> Result<(String, String)> {
+    let row: Option<(String, String, Option<String>, bool)> = sqlx::query_as(
+        r#"
+        SELECT v.note_id,
+               v.database_id,
+               CASE WHEN COALESCE(d.account_id, n.account_id, v.account_id) = $2 THEN 'owner' ELSE m.role END,
+               v.is_public OR COALESCE(n.is_public, false) OR COALESCE(d.is_public, false)
+        FROM hulunote_navs v
+        LEFT JOIN hulunote_notes n ON n.id::text = v.note_id
+        LEFT JOIN hulunote_databases d ON d.id::text = v.database_id
+        LEFT JOIN database_members m ON m.database_id = d.id AND m.account_id = $2
+        WHERE v.id = $1
+        "#,
+    )
+    .bind(nav_id)
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let (note_id, database_id, role, is_public) = row
+        .ok_or_else(|| AppError::NotFound("Nav not found".to_string()))?;
+
+    check(role.as_deref(), is_public, access, "nav")?;
+    Ok((note_id, database_id))
+}
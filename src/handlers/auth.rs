@@ -27,7 +27,8 @@ pub async fn web_login(
         r#"
         SELECT id, username, nickname, password, mail, avatar, introduction,
                invitation_code, cell_number, oauth_key, need_update_password,
-               is_new_user, expires_at, registration_code, created_at, updated_at
+               is_new_user, expires_at, registration_code, timezone, date_format,
+               created_at, updated_at
         FROM accounts
         WHERE username = $1 OR mail = $1
         "#,
@@ -136,7 +137,8 @@ pub async fn web_signup(
         VALUES ($1, $2, $3, $4, $5, $6, true, $7, $8)
         RETURNING id, username, nickname, password, mail, avatar, introduction,
                   invitation_code, cell_number, oauth_key, need_update_password,
-                  is_new_user, expires_at, registration_code, created_at, updated_at
+                  is_new_user, expires_at, registration_code, timezone, date_format,
+                  created_at, updated_at
         "#,
     )
     .bind(&username)
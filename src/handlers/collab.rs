@@ -0,0 +1,186 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_note, broadcast_reordered, check_nav_parent, insert_nav, nav_changed_event, rebalance_siblings, update_nav,
+    ws::WsEvent, Access, AppState, NavChanges,
+};
+
+const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Name of a nav column in requests and acknowledgements
+fn request_field(column: &str) -> &str {
+    match column {
+        "same_deep_order" => "order",
+        "is_display" => "is-display",
+        "is_delete" => "is-delete",
+        "is_public" => "is-public",
+        other => other,
+    }
+}
+
+/// Drop a field from a set of changes
+fn drop_field(changes: &mut NavChanges<'_>, column: &str) {
+    match column {
+        "parid" => changes.parid = None,
+        "same_deep_order" => changes.order = None,
+        "content" => changes.content = None,
+        "properties" => changes.properties = None,
+        "is_display" => changes.is_display = None,
+        "is_delete" => changes.is_delete = None,
+        "is_public" => changes.is_public = None,
+        _ => {}
+    }
+}
+
+/// Apply a nav operation sent over the WebSocket with last-writer-wins per
+/// field, then send every collaborator of the database the `nav_created`,
+/// `nav_updated` or `nav_deleted` event for it.
+///
+/// A field is only written when the operation's `backend-ts` is at least
+/// the clock of the last write to that field, so edits that arrive late do
+/// not undo newer ones. Timestamps from the future count as now. `create`
+/// of a nav that already exists is applied as an update, which makes
+/// retried operations harmless.
+pub async fn apply_nav_op(state: &AppState, account_id: i64, req: &WsNavOp) -> Result<Value> {
+    let pool = state.pool.as_ref();
+    let note_uuid = Uuid::parse_str(&req.note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+    let database_id = authorize_note(pool, account_id, note_uuid, Access::Write).await?;
+
+    let op = &req.op;
+    let now = Utc::now().timestamp_millis();
+    let clock = req.backend_ts.map_or(now, |ts| ts.min(now));
+
+    let nav_id = op
+        .id
+        .as_deref()
+        .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid nav ID".to_string())))
+        .transpose()?;
+
+    let mut changes = match op.op {
+        BatchNavOpKind::Create | BatchNavOpKind::Update => NavChanges {
+            parid: op.parid.as_deref(),
+            order: op.order,
+            content: op.content.as_deref(),
+            properties: op.properties.as_deref(),
+            is_display: op.is_display,
+            ..NavChanges::default()
+        },
+        BatchNavOpKind::Move => NavChanges {
+            parid: Some(
+                op.parid
+                    .as_deref()
+                    .ok_or_else(|| AppError::BadRequest("Missing parid".to_string()))?,
+            ),
+            order: op.order,
+            ..NavChanges::default()
+        },
+        BatchNavOpKind::Delete => NavChanges {
+            is_delete: Some(true),
+            ..NavChanges::default()
+        },
+    };
+    changes.clock = Some(clock);
+
+    let mut tx = state.pool.begin().await?;
+
+    let existing: Option<(String, Value)> = match nav_id {
+        Some(nav_id) => {
+            sqlx::query_as("SELECT note_id, field_clocks FROM hulunote_navs WHERE id = $1 FOR UPDATE")
+                .bind(nav_id)
+                .fetch_optional(&mut *tx)
+                .await?
+        }
+        None => None,
+    };
+
+    let mut ignored = Vec::new();
+    let created = existing.is_none();
+    let (mut nav, applied) = match (nav_id, existing) {
+        (Some(nav_id), Some((nav_note_id, clocks))) => {
+            if nav_note_id != req.note_id {
+                return Err(AppError::BadRequest("Nav does not belong to this note".to_string()));
+            }
+
+            for column in changes.fields() {
+                let last_write = clocks.get(column).and_then(Value::as_i64).unwrap_or(0);
+                if clock < last_write {
+                    drop_field(&mut changes, column);
+                    ignored.push(column);
+                }
+            }
+
+            if let Some(parid) = changes.parid {
+                check_nav_parent(&mut tx, &req.note_id, Some(nav_id), parid).await?;
+            }
+
+            let applied = changes.fields();
+            let nav = if applied.is_empty() {
+                sqlx::query_as(
+                    r#"
+                    SELECT id, parid, same_deep_order, content, account_id, last_account_id, note_id, database_id,
+                           is_display, is_public, is_delete, properties, extra_id, created_at, updated_at
+                    FROM hulunote_navs WHERE id = $1
+                    "#,
+                )
+                .bind(nav_id)
+                .fetch_one(&mut *tx)
+                .await?
+            } else {
+                let revision_op = if changes.is_delete == Some(true) { "delete" } else { "update" };
+                update_nav(&mut tx, account_id, nav_id, &req.note_id, &database_id, &changes, revision_op).await?
+            };
+            (nav, applied)
+        }
+        (_, None) if op.op == BatchNavOpKind::Create => {
+            let parid = changes.parid.unwrap_or(ROOT_NAV_ID);
+            check_nav_parent(&mut tx, &req.note_id, None, parid).await?;
+            let nav_id = nav_id.unwrap_or_else(Uuid::new_v4);
+            let nav = insert_nav(&mut tx, account_id, nav_id, &req.note_id, &database_id, &changes).await?;
+            (nav, changes.fields())
+        }
+        (None, _) => return Err(AppError::BadRequest("Missing nav ID".to_string())),
+        (Some(_), None) => return Err(AppError::NotFound("Nav not found".to_string())),
+    };
+
+    let reordered = if op.op == BatchNavOpKind::Create || changes.parid.is_some() || changes.order.is_some() {
+        rebalance_siblings(&mut tx, account_id, &nav.parid).await?
+    } else {
+        Vec::new()
+    };
+    tx.commit().await?;
+
+    if let Some(order) = reordered.iter().find(|o| o.id == nav.id.to_string()) {
+        nav.same_deep_order = order.same_deep_order;
+    }
+
+    let backend_ts = Utc::now().timestamp_millis();
+    if created {
+        let event = WsEvent::NavCreated {
+            note_id: req.note_id.clone(),
+            database_id: database_id.clone(),
+            backend_ts,
+            nav: NavInfo::from(nav.clone()),
+        };
+        state.ws_broadcaster.broadcast_to_collaborators(pool, event).await;
+    } else if !applied.is_empty() {
+        let event = nav_changed_event(&nav, &changes, backend_ts);
+        state.ws_broadcaster.broadcast_to_collaborators(pool, event).await;
+    }
+    broadcast_reordered(state, &req.note_id, &database_id, backend_ts, &reordered).await;
+
+    Ok(json!({
+        "success": true,
+        "id": nav.id.to_string(),
+        "nav": NavInfo::from(nav),
+        "applied": applied.into_iter().map(request_field).collect::<Vec<_>>(),
+        "ignored": ignored.into_iter().map(request_field).collect::<Vec<_>>(),
+        "reordered": reordered,
+        "backend-ts": backend_ts
+    }))
+}
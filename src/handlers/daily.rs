@@ -0,0 +1,321 @@
+use axum::{extract::State, Extension, Json};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, NaiveDate, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{get_database_id, insert_note, ws::WsEvent, Access, AppState};
+
+/// Title format of daily notes
+pub const DAILY_TITLE_FORMAT: &str = "%Y-%m-%d";
+
+/// Most days `get-daily-notes` lists at once, a leap year
+const MAX_DAILY_RANGE_DAYS: i64 = 366;
+
+/// Parse a daily-note title written by us or by another outliner:
+/// `2026-10-17`, `2026_10_17`, `2026/10/17`, `October 17th, 2026` (Roam)
+/// or `Oct 17th, 2026` (Logseq)
+pub fn parse_daily_title(title: &str) -> Option<NaiveDate> {
+    let title = title.trim();
+    for format in [DAILY_TITLE_FORMAT, "%Y_%m_%d", "%Y/%m/%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(title, format) {
+            return Some(date);
+        }
+    }
+
+    let (month_day, year) = title.split_once(", ")?;
+    let (month, day) = month_day.split_once(' ')?;
+    let day = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| day.strip_suffix(suffix))
+        .unwrap_or(day);
+    let date = format!("{} {} {}", month, day, year);
+    ["%B %d %Y", "%b %d %Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(&date, format).ok())
+}
+
+/// Rewrite a daily-note title to `YYYY-MM-DD`; other titles are returned unchanged
+pub fn normalize_daily_title(title: &str) -> String {
+    match parse_daily_title(title) {
+        Some(date) => date.format(DAILY_TITLE_FORMAT).to_string(),
+        None => title.to_string(),
+    }
+}
+
+/// Whether `format` is a strftime format that dates survive a round trip through
+pub fn is_valid_date_format(format: &str) -> bool {
+    if format.trim().is_empty() || StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return false;
+    }
+    // Day and month differ so that formats dropping or mixing them up are caught
+    let sample = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+    NaiveDate::parse_from_str(&sample.format(format).to_string(), format).ok() == Some(sample)
+}
+
+/// Whether Postgres knows `timezone` as a timezone name
+pub(crate) async fn is_valid_timezone(pool: &PgPool, timezone: &str) -> Result<bool> {
+    let (valid,): (bool,) =
+        sqlx::query_as("SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1)")
+            .bind(timezone)
+            .fetch_one(pool)
+            .await?;
+    Ok(valid)
+}
+
+/// Parse a daily-note title in the account's date format or any format
+/// `parse_daily_title` recognizes
+fn parse_daily_title_as(title: &str, date_format: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(title.trim(), date_format)
+        .ok()
+        .or_else(|| parse_daily_title(title))
+}
+
+pub(crate) fn parse_date(value: &str, field: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, DAILY_TITLE_FORMAT).map_err(|_| {
+        AppError::BadRequest(format!("Invalid {} '{}', expected YYYY-MM-DD", field, value))
+    })
+}
+
+/// The account's timezone and daily-note title format
+pub(crate) async fn daily_preferences(pool: &PgPool, account_id: i64) -> Result<(String, String)> {
+    let preferences: (String, String) =
+        sqlx::query_as("SELECT timezone, date_format FROM accounts WHERE id = $1")
+            .bind(account_id)
+            .fetch_one(pool)
+            .await?;
+    Ok(preferences)
+}
+
+/// The current date in `timezone`
+pub(crate) async fn today_in(pool: &PgPool, timezone: &str) -> Result<NaiveDate> {
+    let (today,): (NaiveDate,) = sqlx::query_as("SELECT (NOW() AT TIME ZONE $1)::date")
+        .bind(timezone)
+        .fetch_one(pool)
+        .await?;
+    Ok(today)
+}
+
+/// The titles a daily note of `date` is usually given: the account's format,
+/// ours and the ones Roam and Logseq write
+fn daily_title_variants(date: NaiveDate, date_format: &str) -> Vec<String> {
+    let suffix = match date.day() {
+        1 | 21 | 31 => "st",
+        2 | 22 => "nd",
+        3 | 23 => "rd",
+        _ => "th",
+    };
+    let mut titles: Vec<String> = [date_format, DAILY_TITLE_FORMAT, "%Y_%m_%d", "%Y/%m/%d"]
+        .iter()
+        .map(|format| date.format(format).to_string())
+        .collect();
+    for month in ["%B", "%b"] {
+        let month = date.format(month);
+        titles.push(format!("{} {}{}, {}", month, date.day(), suffix, date.year()));
+        titles.push(format!("{} {}, {}", month, date.day(), date.year()));
+    }
+    titles
+}
+
+/// Live daily notes of a database dated within `from..=to`, ordered by date.
+/// When a day has several, the one titled in the account's format comes first.
+async fn find_daily_notes(
+    pool: &PgPool,
+    database_id: &str,
+    date_format: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<(NaiveDate, HulunoteNote)>> {
+    // Narrow down the candidates in SQL before parsing titles. A single date
+    // is looked up by its exact titles; a range by any title holding one of
+    // its years, plus the exact titles in the account's format when that
+    // leaves the year out
+    let (titles, year_pattern) = if from == to {
+        (daily_title_variants(from, date_format), None)
+    } else {
+        let titles: Vec<String> = if date_format.contains("%Y") || date_format.contains("%F") {
+            Vec::new()
+        } else {
+            from.iter_days()
+                .take_while(|date| *date <= to)
+                .map(|date| date.format(date_format).to_string())
+                .collect()
+        };
+        let years: Vec<String> = (from.year()..=to.year()).map(|year| year.to_string()).collect();
+        (titles, Some(format!("(^|[^0-9])({})([^0-9]|$)", years.join("|"))))
+    };
+
+    let notes: Vec<HulunoteNote> = sqlx::query_as(
+        r#"
+        SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
+        FROM hulunote_notes
+        WHERE database_id = $1 AND is_delete = false
+        AND (title = ANY($2) OR title ~ $3)
+        "#,
+    )
+    .bind(database_id)
+    .bind(&titles)
+    .bind(&year_pattern)
+    .fetch_all(pool)
+    .await?;
+
+    let mut daily_notes: Vec<(NaiveDate, HulunoteNote)> = notes
+        .into_iter()
+        .filter_map(|note| {
+            let date = parse_daily_title_as(&note.title, date_format)?;
+            (date >= from && date <= to).then_some((date, note))
+        })
+        .collect();
+    daily_notes.sort_by_key(|(date, note)| {
+        let preferred = note.title == date.format(date_format).to_string();
+        (*date, !preferred, note.created_at)
+    });
+    Ok(daily_notes)
+}
+
+/// Get the daily note of a date (today in the account's timezone by default),
+/// creating it with its root nav if it does not exist yet
+pub async fn get_or_create_daily_note(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<DailyNoteRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let database_id = get_database_id(
+        pool,
+        account_id,
+        req.database_id.as_deref(),
+        req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Write,
+    )
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?
+    .to_string();
+
+    let (mut timezone, date_format) = daily_preferences(pool, account_id).await?;
+    if let Some(tz) = req.timezone {
+        if !is_valid_timezone(pool, &tz).await? {
+            return Err(AppError::BadRequest(format!("Unknown timezone '{}'", tz)));
+        }
+        timezone = tz;
+    }
+    let date = match req.date.as_deref() {
+        Some(date) => parse_date(date, "date")?,
+        None => today_in(pool, &timezone).await?,
+    };
+
+    if let Some((_, note)) = find_daily_notes(pool, &database_id, &date_format, date, date)
+        .await?
+        .into_iter()
+        .next()
+    {
+        return Ok(Json(json!({
+            "note": NoteInfo::from(note),
+            "date": date.format(DAILY_TITLE_FORMAT).to_string(),
+            "created": false,
+        })));
+    }
+
+    let title = date.format(&date_format).to_string();
+    let mut tx = pool.begin().await?;
+    let Some(note) = insert_note(&mut tx, account_id, Uuid::new_v4(), Uuid::new_v4(), &database_id, &title).await? else {
+        // Either created concurrently or waiting in the trash
+        let existing: HulunoteNote = sqlx::query_as(
+            r#"
+            SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+                   is_shortcut, is_template, account_id, pv, created_at, updated_at
+            FROM hulunote_notes
+            WHERE database_id = $1 AND title = $2
+            "#,
+        )
+        .bind(&database_id)
+        .bind(&title)
+        .fetch_one(&mut *tx)
+        .await?;
+        if existing.is_delete {
+            return Err(AppError::BadRequest(format!(
+                "The daily note '{}' is in the trash; restore it first",
+                title
+            )));
+        }
+        return Ok(Json(json!({
+            "note": NoteInfo::from(existing),
+            "date": date.format(DAILY_TITLE_FORMAT).to_string(),
+            "created": false,
+        })));
+    };
+    tx.commit().await?;
+
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(
+            state.pool.as_ref(),
+            WsEvent::NoteCreated {
+                note_id: note.id.to_string(),
+                database_id: database_id.clone(),
+                backend_ts: Utc::now().timestamp_millis(),
+                title: note.title.clone(),
+                root_nav_id: note.root_nav_id.clone(),
+            },
+        )
+        .await;
+
+    Ok(Json(json!({
+        "note": NoteInfo::from(note),
+        "date": date.format(DAILY_TITLE_FORMAT).to_string(),
+        "created": true,
+    })))
+}
+
+/// List the daily notes of a date range, for calendar views
+pub async fn get_daily_notes(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetDailyNotesRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let database_id = get_database_id(
+        pool,
+        account_id,
+        req.database_id.as_deref(),
+        req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
+    )
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?
+    .to_string();
+
+    let from = parse_date(&req.from, "from")?;
+    let to = parse_date(&req.to, "to")?;
+    if to < from {
+        return Err(AppError::BadRequest("'to' must not be before 'from'".to_string()));
+    }
+    if (to - from).num_days() >= MAX_DAILY_RANGE_DAYS {
+        return Err(AppError::BadRequest(format!("A range spans at most {} days", MAX_DAILY_RANGE_DAYS)));
+    }
+
+    let (timezone, date_format) = daily_preferences(pool, account_id).await?;
+    let today = today_in(pool, &timezone).await?;
+    let daily_notes: Vec<Value> = find_daily_notes(pool, &database_id, &date_format, from, to)
+        .await?
+        .into_iter()
+        .map(|(date, note)| {
+            json!({
+                "date": date.format(DAILY_TITLE_FORMAT).to_string(),
+                "note": NoteInfo::from(note),
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "daily-notes": daily_notes,
+        "today": today.format(DAILY_TITLE_FORMAT).to_string(),
+        "timezone": timezone,
+        "date-format": date_format,
+    })))
+}
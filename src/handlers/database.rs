@@ -1,4 +1,5 @@
 use axum::{extract::State, Extension, Json};
+use chrono::Utc;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
@@ -6,32 +7,46 @@ use uuid::Uuid;
 use crate::error::{AppError, Result};
 use crate::models::*;
 
-use super::AppState;
+use super::{authorize_database, record_nav_revisions, ws::WsEvent, Access, AppState};
 
-/// Get database ID by various identifiers
+/// Get database ID by various identifiers, checking that the caller
+/// has the requested access to it
 pub async fn get_database_id(
     pool: &sqlx::PgPool,
     account_id: i64,
     database_id: Option<&str>,
     database_name: Option<&str>,
+    access: Access,
 ) -> Result<Option<Uuid>> {
     // Try database_id first
     if let Some(id) = database_id {
         if let Ok(uuid) = Uuid::parse_str(id) {
+            authorize_database(pool, account_id, uuid, access).await?;
             return Ok(Some(uuid));
         }
     }
 
-    // Try database_name
+    // Try database_name, preferring the caller's own database over one
+    // shared with them under the same name
     if let Some(name) = database_name {
         let result: Option<(Uuid,)> = sqlx::query_as(
-            "SELECT id FROM hulunote_databases WHERE name = $1 AND account_id = $2 AND is_delete = false"
+            r#"
+            SELECT d.id FROM hulunote_databases d
+            LEFT JOIN database_members m ON m.database_id = d.id AND m.account_id = $2
+            WHERE d.name = $1 AND d.is_delete = false
+            AND (d.account_id = $2 OR m.account_id IS NOT NULL)
+            ORDER BY (d.account_id = $2) DESC, d.created_at
+            LIMIT 1
+            "#
         )
         .bind(name)
         .bind(account_id)
         .fetch_optional(pool)
         .await?;
 
+        if let Some((uuid,)) = result {
+            authorize_database(pool, account_id, uuid, access).await?;
+        }
         return Ok(result.map(|r| r.0));
     }
 
@@ -45,7 +60,7 @@ pub async fn create_database(
     Json(req): Json<CreateDatabaseRequest>,
 ) -> Result<Json<Value>> {
     // Check existing database count
-    let count: (i64,) = sqlx::query_as(
+    let _count: (i64,) = sqlx::query_as(
         "SELECT COUNT(*) FROM hulunote_databases WHERE account_id = $1 AND is_delete = false"
     )
     .bind(account_id)
@@ -82,9 +97,22 @@ pub async fn create_database(
     .fetch_one(state.pool.as_ref())
     .await?;
 
+    let database = DatabaseInfo::from(db);
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(
+            state.pool.as_ref(),
+            WsEvent::DatabaseCreated {
+                database_id: db_id.to_string(),
+                backend_ts: Utc::now().timestamp_millis(),
+                database: database.clone(),
+            },
+        )
+        .await;
+
     // Return with "database" key to match frontend expectation
     Ok(Json(json!({
-        "database": DatabaseInfo::from(db),
+        "database": database,
         "success": true
     })))
 }
@@ -109,89 +137,100 @@ pub async fn delete_database(
         Uuid::parse_str(id)
             .map_err(|_| AppError::BadRequest("Invalid database ID".to_string()))?
     } else if let Some(ref name) = req.database_name {
-        let result: Option<(Uuid,)> = sqlx::query_as(
-            "SELECT id FROM hulunote_databases WHERE name = $1 AND account_id = $2 AND is_delete = false"
-        )
-        .bind(name)
-        .bind(account_id)
-        .fetch_optional(state.pool.as_ref())
-        .await?;
-
-        result
-            .map(|r| r.0)
+        get_database_id(state.pool.as_ref(), account_id, None, Some(name), Access::Manage)
+            .await?
             .ok_or_else(|| AppError::NotFound("Database not found".to_string()))?
     } else {
         return Err(AppError::BadRequest("Database ID or name required".to_string()));
     };
 
-    // Check ownership
-    let exists: Option<(i64,)> = sqlx::query_as(
-        "SELECT account_id FROM hulunote_databases WHERE id = $1 AND is_delete = false"
-    )
-    .bind(db_uuid)
-    .fetch_optional(state.pool.as_ref())
-    .await?;
+    // Only owners can delete a database
+    authorize_database(state.pool.as_ref(), account_id, db_uuid, Access::Manage).await?;
 
-    match exists {
-        Some((owner_id,)) if owner_id != account_id => {
-            return Err(AppError::PermissionDenied("Cannot delete other's database".to_string()));
-        }
-        None => {
-            return Err(AppError::NotFound("Database not found".to_string()));
-        }
-        _ => {}
-    }
+    // Move the database and everything in it to the trash. One transaction
+    // gives them the same deleted_at, so restoring the database brings back
+    // exactly what was deleted with it.
+    let mut tx = state.pool.begin().await?;
 
-    // Soft delete the database
     sqlx::query(
-        "UPDATE hulunote_databases SET is_delete = true, updated_at = NOW() WHERE id = $1"
+        "UPDATE hulunote_databases SET is_delete = true, deleted_at = NOW(), updated_at = NOW() WHERE id = $1"
     )
     .bind(db_uuid)
-    .execute(state.pool.as_ref())
+    .execute(&mut *tx)
     .await?;
 
-    // Optionally: soft delete all notes in this database
     sqlx::query(
-        "UPDATE hulunote_notes SET is_delete = true, updated_at = NOW() WHERE database_id = $1"
+        "UPDATE hulunote_notes SET is_delete = true, deleted_at = NOW(), updated_at = NOW() WHERE database_id = $1 AND is_delete = false"
     )
     .bind(db_uuid.to_string())
-    .execute(state.pool.as_ref())
+    .execute(&mut *tx)
     .await?;
 
-    // Optionally: soft delete all navs in this database
-    sqlx::query(
-        "UPDATE hulunote_navs SET is_delete = true, updated_at = NOW() WHERE database_id = $1"
+    let navs: Vec<(Uuid,)> = sqlx::query_as(
+        "UPDATE hulunote_navs SET is_delete = true, deleted_at = NOW(), updated_at = NOW() WHERE database_id = $1 AND is_delete = false RETURNING id"
     )
     .bind(db_uuid.to_string())
-    .execute(state.pool.as_ref())
+    .fetch_all(&mut *tx)
     .await?;
 
+    let nav_ids: Vec<Uuid> = navs.into_iter().map(|(id,)| id).collect();
+    record_nav_revisions(&mut tx, &nav_ids, account_id, "delete").await?;
+
+    tx.commit().await?;
+
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(
+            state.pool.as_ref(),
+            WsEvent::DatabaseDeleted {
+                database_id: db_uuid.to_string(),
+                backend_ts: Utc::now().timestamp_millis(),
+            },
+        )
+        .await;
+
     Ok(Json(json!({
         "success": true,
         "message": "Database deleted successfully"
     })))
 }
 
-/// Get database list for current user
+/// A database together with the caller's role in it
+#[derive(sqlx::FromRow)]
+struct DatabaseWithRole {
+    #[sqlx(flatten)]
+    database: HulunoteDatabase,
+    role: String,
+}
+
+/// Get database list for current user, including databases shared with them
 pub async fn get_database_list(
     State(state): State<AppState>,
     Extension(account_id): Extension<i64>,
     Json(_req): Json<Value>,
 ) -> Result<Json<Value>> {
-    let databases: Vec<HulunoteDatabase> = sqlx::query_as(
+    let databases: Vec<DatabaseWithRole> = sqlx::query_as(
         r#"
-        SELECT id, name, description, is_delete, is_public, is_offline, is_default,
-               account_id, setting, created_at, updated_at
-        FROM hulunote_databases
-        WHERE account_id = $1 AND is_delete = false
-        ORDER BY created_at DESC
+        SELECT d.id, d.name, d.description, d.is_delete, d.is_public, d.is_offline, d.is_default,
+               d.account_id, d.setting, d.created_at, d.updated_at,
+               CASE WHEN d.account_id = $1 THEN 'owner' ELSE m.role END AS role
+        FROM hulunote_databases d
+        LEFT JOIN database_members m ON m.database_id = d.id AND m.account_id = $1
+        WHERE d.is_delete = false AND (d.account_id = $1 OR m.account_id IS NOT NULL)
+        ORDER BY (d.account_id = $1) DESC, d.created_at DESC
         "#,
     )
     .bind(account_id)
     .fetch_all(state.pool.as_ref())
     .await?;
 
-    let database_list: Vec<DatabaseInfo> = databases.into_iter().map(DatabaseInfo::from).collect();
+    let database_list = databases
+        .into_iter()
+        .map(|row| {
+            let role = row.role.parse()?;
+            Ok(DatabaseInfo { role: Some(role), ..DatabaseInfo::from(row.database) })
+        })
+        .collect::<Result<Vec<DatabaseInfo>>>()?;
 
     // Get user settings (placeholder)
     let settings = json!({});
@@ -214,23 +253,8 @@ pub async fn update_database(
     let db_uuid = Uuid::parse_str(&database_id)
         .map_err(|_| AppError::BadRequest("Invalid database ID".to_string()))?;
 
-    // Check ownership
-    let exists: Option<(i64,)> = sqlx::query_as(
-        "SELECT account_id FROM hulunote_databases WHERE id = $1"
-    )
-    .bind(db_uuid)
-    .fetch_optional(state.pool.as_ref())
-    .await?;
-
-    match exists {
-        Some((owner_id,)) if owner_id != account_id => {
-            return Err(AppError::PermissionDenied("Cannot update other's database".to_string()));
-        }
-        None => {
-            return Err(AppError::NotFound("Database not found".to_string()));
-        }
-        _ => {}
-    }
+    // Only owners can change the database itself
+    authorize_database(state.pool.as_ref(), account_id, db_uuid, Access::Manage).await?;
 
     // Build update query dynamically
     let mut updates = vec![];
@@ -279,5 +303,21 @@ pub async fn update_database(
 
     query_builder.execute(state.pool.as_ref()).await?;
 
+    let database_id = db_uuid.to_string();
+    let backend_ts = Utc::now().timestamp_millis();
+    let event = if req.is_delete == Some(true) {
+        WsEvent::DatabaseDeleted { database_id, backend_ts }
+    } else {
+        WsEvent::DatabaseUpdated {
+            database_id,
+            backend_ts,
+            name: req.db_name,
+            is_public: req.is_public,
+            is_default: req.is_default,
+            is_delete: req.is_delete,
+        }
+    };
+    state.ws_broadcaster.broadcast_to_collaborators(state.pool.as_ref(), event).await;
+
     Ok(Json(json!({"success": true})))
 }
@@ -0,0 +1,443 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures_util::{stream, TryStreamExt};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom, Write};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{authorize_note, get_database_id, note_to_markdown, note_to_opml, Access, AppState};
+
+/// Group navs by parent id, each group sorted by `same-deep-order`
+pub fn children_by_parent(navs: &[ImportNavData]) -> HashMap<&str, Vec<&ImportNavData>> {
+    let mut children: HashMap<&str, Vec<&ImportNavData>> = HashMap::new();
+    for nav in navs {
+        children.entry(nav.parid.as_str()).or_default().push(nav);
+    }
+    for group in children.values_mut() {
+        group.sort_by(|a, b| a.same_deep_order.total_cmp(&b.same_deep_order));
+    }
+    children
+}
+
+/// Export file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Json,
+    Markdown,
+    Opml,
+}
+
+impl ExportFormat {
+    fn parse(format: Option<&str>) -> Result<Self> {
+        match format.unwrap_or("json") {
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "opml" => Ok(Self::Opml),
+            other => Err(AppError::BadRequest(format!("Unsupported export format: {}", other))),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Markdown => "md",
+            Self::Opml => "opml",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Opml => "text/x-opml; charset=utf-8",
+        }
+    }
+
+    fn render(self, note: &ImportNoteJson) -> Result<Vec<u8>> {
+        match self {
+            Self::Json => serde_json::to_vec_pretty(note)
+                .map_err(|e| AppError::Internal(format!("Failed to serialize note: {}", e))),
+            Self::Markdown => Ok(note_to_markdown(note).into_bytes()),
+            Self::Opml => Ok(note_to_opml(note).into_bytes()),
+        }
+    }
+}
+
+/// Columns of `HulunoteNav`, for the export queries
+const NAV_COLUMNS: &str = "v.id, v.parid, v.same_deep_order, v.content, v.account_id, v.last_account_id, \
+     v.note_id, v.database_id, v.is_display, v.is_public, v.is_delete, v.properties, v.extra_id, \
+     v.created_at, v.updated_at";
+
+/// A note and its navs in the `ImportNoteJson` format
+fn note_export(note: HulunoteNote, navs: Vec<HulunoteNav>) -> ImportNoteJson {
+    ImportNoteJson {
+        note: ImportNoteData {
+            id: note.id.to_string(),
+            title: note.title,
+            root_nav_id: note.root_nav_id,
+            is_delete: Some(note.is_delete),
+            is_public: Some(note.is_public),
+            is_shortcut: Some(note.is_shortcut),
+            is_template: Some(note.is_template),
+        },
+        navs: navs.into_iter().map(ImportNavData::from).collect(),
+    }
+}
+
+/// Load a note and its navs in the `ImportNoteJson` format.
+/// The root nav is left out because import recreates it from `root-nav-id`.
+pub async fn load_note_export(pool: &sqlx::PgPool, note: HulunoteNote) -> Result<ImportNoteJson> {
+    let navs: Vec<HulunoteNav> = sqlx::query_as(&format!(
+        r#"
+        SELECT {} FROM hulunote_navs v
+        WHERE v.note_id = $1 AND v.is_delete = false AND v.id::text <> $2
+        ORDER BY v.same_deep_order ASC
+        "#,
+        NAV_COLUMNS
+    ))
+    .bind(note.id.to_string())
+    .bind(&note.root_nav_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(note_export(note, navs))
+}
+
+/// Turn a note title into a file name that is safe inside a ZIP archive
+/// and unique among the names already used
+pub fn export_file_name(title: &str, id: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let mut base: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(100)
+        .collect();
+    base = base.trim().trim_start_matches('.').to_string();
+    if base.is_empty() {
+        base = "untitled".to_string();
+    }
+
+    let mut name = format!("{}.{}", base, extension);
+    if used.contains(&name.to_lowercase()) {
+        name = format!("{}-{}.{}", base, id, extension);
+    }
+    used.insert(name.to_lowercase());
+    name
+}
+
+/// Pack (file name, content) pairs into a ZIP archive
+pub fn build_zip(files: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for (name, data) in files {
+        zip.start_file(name, options)
+            .map_err(|e| AppError::Internal(format!("Failed to write ZIP entry: {}", e)))?;
+        zip.write_all(&data)
+            .map_err(|e| AppError::Internal(format!("Failed to write ZIP entry: {}", e)))?;
+    }
+
+    let cursor = zip
+        .finish()
+        .map_err(|e| AppError::Internal(format!("Failed to finish ZIP: {}", e)))?;
+    Ok(cursor.into_inner())
+}
+
+/// A `Write + Seek` target for `zip::ZipWriter` that sends out the archive
+/// as it is written. `ZipWriter` only seeks back into the entry it is
+/// writing, so everything before it is sent whenever an entry is finished
+/// and flushed; seeking further back fails.
+struct ZipChunks {
+    sender: mpsc::Sender<std::io::Result<Bytes>>,
+    /// Bytes already sent
+    sent: u64,
+    /// Bytes written after `sent`
+    buffer: Vec<u8>,
+    position: u64,
+}
+
+impl ZipChunks {
+    fn new(sender: mpsc::Sender<std::io::Result<Bytes>>) -> Self {
+        Self { sender, sent: 0, buffer: Vec::new(), position: 0 }
+    }
+}
+
+impl Write for ZipChunks {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let start = (self.position - self.sent) as usize;
+        let overlap = data.len().min(self.buffer.len().saturating_sub(start));
+        self.buffer[start..start + overlap].copy_from_slice(&data[..overlap]);
+        self.buffer.extend_from_slice(&data[overlap..]);
+        self.position += data.len() as u64;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        self.sent += chunk.len() as u64;
+        self.sender
+            .blocking_send(Ok(chunk))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Download cancelled"))
+    }
+}
+
+/// Only there because `ZipWriter` flushes after each entry only for
+/// readable targets; it never reads from this one
+impl Read for ZipChunks {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "ZIP download is write-only"))
+    }
+}
+
+impl Seek for ZipChunks {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let end = self.sent + self.buffer.len() as u64;
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => end.checked_add_signed(offset),
+        };
+        match position {
+            Some(position) if position >= self.sent && position <= end => {
+                self.position = position;
+                Ok(position)
+            }
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Cannot seek into data already sent")),
+        }
+    }
+}
+
+/// Pack (file name, content) pairs received from `files` into a ZIP
+/// archive, sending it out in chunks
+fn stream_zip(mut files: mpsc::Receiver<(String, Vec<u8>)>, chunks: mpsc::Sender<std::io::Result<Bytes>>) -> Result<()> {
+    let mut zip = zip::ZipWriter::new(ZipChunks::new(chunks));
+    zip.set_flush_on_finish_file(true);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    while let Some((name, data)) = files.blocking_recv() {
+        zip.start_file(name, options)
+            .map_err(|e| AppError::Internal(format!("Failed to write ZIP entry: {}", e)))?;
+        zip.write_all(&data)
+            .map_err(|e| AppError::Internal(format!("Failed to write ZIP entry: {}", e)))?;
+    }
+
+    zip.finish()
+        .and_then(|mut chunks| Ok(chunks.flush()?))
+        .map_err(|e| AppError::Internal(format!("Failed to finish ZIP: {}", e)))
+}
+
+/// The `Content-Type` and `Content-Disposition` of a file download.
+/// Non-ASCII file names are sent percent-encoded in `filename*` with an
+/// ASCII fallback in `filename`.
+fn attachment_headers(content_type: &'static str, filename: &str) -> [(header::HeaderName, String); 2] {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+
+    [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded),
+        ),
+    ]
+}
+
+/// Send bytes as a file download
+pub fn attachment(content_type: &'static str, filename: &str, data: Vec<u8>) -> Response {
+    (attachment_headers(content_type, filename), data).into_response()
+}
+
+/// Package notes as a ZIP with one file per note
+fn notes_zip(notes: Vec<ImportNoteJson>, format: ExportFormat) -> Result<Vec<u8>> {
+    let mut used = HashSet::new();
+    let mut files = Vec::with_capacity(notes.len());
+    for note in notes {
+        let name = export_file_name(&note.note.title, &note.note.id, format.extension(), &mut used);
+        files.push((name, format.render(&note)?));
+    }
+    build_zip(files)
+}
+
+/// Fetch a note the caller can read
+pub async fn get_readable_note(pool: &sqlx::PgPool, account_id: i64, note_id: &str) -> Result<HulunoteNote> {
+    let note_uuid = Uuid::parse_str(note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+
+    authorize_note(pool, account_id, note_uuid, Access::Read).await?;
+
+    let note: HulunoteNote = sqlx::query_as(
+        r#"
+        SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
+        FROM hulunote_notes
+        WHERE id = $1
+        "#,
+    )
+    .bind(note_uuid)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(note)
+}
+
+/// Get every note of a database the caller can read
+pub async fn get_readable_database_notes(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    req: &ExportDatabaseRequest,
+) -> Result<(Uuid, Vec<HulunoteNote>)> {
+    let database_id = get_database_id(
+        pool,
+        account_id,
+        req.database_id.as_deref(),
+        req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
+    )
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
+
+    let notes: Vec<HulunoteNote> = sqlx::query_as(
+        r#"
+        SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
+        FROM hulunote_notes
+        WHERE database_id = $1 AND is_delete = false
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(database_id.to_string())
+    .fetch_all(pool)
+    .await?;
+
+    Ok((database_id, notes))
+}
+
+/// Export a note as a ZIP containing one `ImportNoteJson` file,
+/// or as a single Markdown or OPML file
+pub async fn export_note(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<ExportNoteRequest>,
+) -> Result<Response> {
+    let format = ExportFormat::parse(req.format.as_deref())?;
+    let note = get_readable_note(state.pool.as_ref(), account_id, &req.note_id).await?;
+    let note_id = note.id;
+
+    let export = load_note_export(state.pool.as_ref(), note).await?;
+
+    if format != ExportFormat::Json {
+        let name = export_file_name(&export.note.title, &export.note.id, format.extension(), &mut HashSet::new());
+        return Ok(attachment(format.content_type(), &name, format.render(&export)?));
+    }
+
+    let data = notes_zip(vec![export], format)?;
+    Ok(attachment("application/zip", &format!("note-{}.zip", note_id), data))
+}
+
+/// Export every note of a database as a ZIP with one file per note. The
+/// archive is streamed while the navs of the notes are read, in one query.
+pub async fn export_database(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<ExportDatabaseRequest>,
+) -> Result<Response> {
+    let format = ExportFormat::parse(req.format.as_deref())?;
+    let (database_id, notes) =
+        get_readable_database_notes(state.pool.as_ref(), account_id, &req).await?;
+
+    let (files_tx, files_rx) = mpsc::channel(4);
+    let (chunks_tx, chunks_rx) = mpsc::channel(4);
+    let errors = chunks_tx.clone();
+    let zip = tokio::task::spawn_blocking(move || stream_zip(files_rx, chunks_tx));
+
+    tokio::spawn(async move {
+        let rendered = render_database_notes(state.pool.as_ref(), &database_id, notes, format, files_tx).await;
+        let zipped = zip
+            .await
+            .unwrap_or_else(|e| Err(AppError::Internal(format!("ZIP task failed: {}", e))));
+        if let Err(e) = rendered.and(zipped) {
+            tracing::error!("Export of database {} failed: {}", database_id, e);
+            // Cut the download short rather than end it like a whole archive
+            let _ = errors.send(Err(std::io::Error::other(e.to_string()))).await;
+        }
+    });
+
+    let body = Body::from_stream(stream::unfold(chunks_rx, |mut chunks| async move {
+        chunks.recv().await.map(|chunk| (chunk, chunks))
+    }));
+    Ok((attachment_headers("application/zip", &format!("database-{}.zip", database_id)), body).into_response())
+}
+
+/// Read the navs of `notes`, which are ordered like the notes of
+/// `get_readable_database_notes`, and send each note as a file to `files`
+async fn render_database_notes(
+    pool: &sqlx::PgPool,
+    database_id: &Uuid,
+    notes: Vec<HulunoteNote>,
+    format: ExportFormat,
+    files: mpsc::Sender<(String, Vec<u8>)>,
+) -> Result<()> {
+    let query = format!(
+        r#"
+        SELECT {} FROM hulunote_navs v
+        JOIN hulunote_notes n ON n.id::text = v.note_id
+        WHERE n.database_id = $1 AND n.id = ANY($2)
+        AND v.is_delete = false AND v.id::text <> n.root_nav_id
+        ORDER BY n.created_at ASC, n.id ASC, v.same_deep_order ASC
+        "#,
+        NAV_COLUMNS
+    );
+    // Only the listed notes, so a note created or restored since the list
+    // was read cannot put navs in the stream that no note claims
+    let note_ids: Vec<Uuid> = notes.iter().map(|note| note.id).collect();
+    let mut rows = sqlx::query_as::<_, HulunoteNav>(&query)
+        .bind(database_id.to_string())
+        .bind(&note_ids)
+        .fetch(pool);
+
+    let mut used = HashSet::new();
+    let mut next: Option<HulunoteNav> = rows.try_next().await?;
+    for note in notes {
+        let note_id = note.id.to_string();
+        let mut navs = Vec::new();
+        while let Some(nav) = next.take_if(|nav| nav.note_id == note_id) {
+            navs.push(nav);
+            next = rows.try_next().await?;
+        }
+
+        let export = note_export(note, navs);
+        let name = export_file_name(&export.note.title, &export.note.id, format.extension(), &mut used);
+        let data = format.render(&export)?;
+        if files.send((name, data)).await.is_err() {
+            // The ZIP task stopped; it reports why
+            break;
+        }
+    }
+    Ok(())
+}
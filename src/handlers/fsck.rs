@@ -0,0 +1,585 @@
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use serde_json::{json, Value};
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{fetch_navs, insert_note, nav_batches, record_nav_revisions, sync_nav_links, ws::WsEvent, AppState};
+
+const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Title of the note that `recover` repairs move misplaced navs into
+const RECOVERED_NOTE_TITLE: &str = "Recovered";
+
+#[derive(Debug, FromRow)]
+struct FsckNote {
+    id: Uuid,
+    database_id: String,
+    root_nav_id: String,
+    is_delete: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct FsckNav {
+    id: Uuid,
+    parid: String,
+    note_id: String,
+    database_id: String,
+    is_delete: bool,
+}
+
+impl std::str::FromStr for FsckRepair {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "reattach" => Ok(Self::Reattach),
+            "recover" => Ok(Self::Recover),
+            other => Err(AppError::BadRequest(format!(
+                "Unknown repair mode '{}', expected reattach or recover",
+                other
+            ))),
+        }
+    }
+}
+
+/// Check a database for broken outline trees, and optionally repair them
+/// (admin only)
+pub async fn fsck(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<FsckRequest>,
+) -> Result<Json<Value>> {
+    if !state.config.admin_account_ids.contains(&account_id) {
+        return Err(AppError::PermissionDenied("Admin access required".to_string()));
+    }
+
+    let database_id = Uuid::parse_str(&req.database_id)
+        .map_err(|_| AppError::BadRequest("Invalid database ID format".to_string()))?;
+
+    let exists: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM hulunote_databases WHERE id = $1")
+        .bind(database_id)
+        .fetch_optional(state.pool.as_ref())
+        .await?;
+    if exists.is_none() {
+        return Err(AppError::NotFound("Database not found".to_string()));
+    }
+
+    let (report, events) = check_database(state.pool.as_ref(), database_id, req.repair).await?;
+    for event in events {
+        state.ws_broadcaster.broadcast_to_collaborators(state.pool.as_ref(), event).await;
+    }
+
+    Ok(Json(json!(report)))
+}
+
+/// Scan the notes and navs of a database for missing and duplicate roots,
+/// orphans, cycles and mismatched note or database ids. With `repair`, fix
+/// what was found in the same transaction.
+pub async fn fsck_database(pool: &PgPool, database_id: Uuid, repair: Option<FsckRepair>) -> Result<FsckReport> {
+    let (report, _) = check_database(pool, database_id, repair).await?;
+    Ok(report)
+}
+
+/// `fsck_database`, also returning the events that announce its repairs
+async fn check_database(
+    pool: &PgPool,
+    database_id: Uuid,
+    repair: Option<FsckRepair>,
+) -> Result<(FsckReport, Vec<WsEvent>)> {
+    let database_id = database_id.to_string();
+    let mut tx = pool.begin().await?;
+
+    let (notes, navs) = load_tree(&mut tx, &database_id).await?;
+    let issues = find_issues(&database_id, &notes, &navs);
+
+    let mut report = FsckReport {
+        database_id: database_id.clone(),
+        notes_checked: notes.values().filter(|n| n.database_id == database_id).count(),
+        navs_checked: navs.values().filter(|n| in_database(&database_id, n, &notes)).count(),
+        issues,
+        repaired: 0,
+        recovered_note_id: None,
+    };
+
+    let mut events = Vec::new();
+    if let Some(mode) = repair {
+        if !report.issues.is_empty() {
+            events = repair_issues(&mut tx, mode, &notes, &navs, &mut report).await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok((report, events))
+}
+
+/// Load the navs of a database (by their own database id or their note's)
+/// and the parents they point to, plus every note those navs name
+async fn load_tree(
+    conn: &mut PgConnection,
+    database_id: &str,
+) -> Result<(HashMap<String, FsckNote>, HashMap<Uuid, FsckNav>)> {
+    let mut navs: HashMap<Uuid, FsckNav> = sqlx::query_as::<_, FsckNav>(
+        r#"
+        SELECT id, parid, note_id, database_id, is_delete
+        FROM hulunote_navs
+        WHERE database_id = $1
+        OR note_id IN (SELECT id::text FROM hulunote_notes WHERE database_id = $1)
+        "#,
+    )
+    .bind(database_id)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|nav| (nav.id, nav))
+    .collect();
+
+    // Parents in other databases, so a nav whose parent is elsewhere is
+    // reported as such rather than as an orphan
+    let outside: Vec<Uuid> = navs
+        .values()
+        .filter(|nav| nav.parid != ROOT_NAV_ID)
+        .filter_map(|nav| Uuid::parse_str(&nav.parid).ok())
+        .filter(|parid| !navs.contains_key(parid))
+        .collect();
+    if !outside.is_empty() {
+        let parents: Vec<FsckNav> = sqlx::query_as(
+            "SELECT id, parid, note_id, database_id, is_delete FROM hulunote_navs WHERE id = ANY($1)",
+        )
+        .bind(&outside)
+        .fetch_all(&mut *conn)
+        .await?;
+        navs.extend(parents.into_iter().map(|nav| (nav.id, nav)));
+    }
+
+    let note_ids: Vec<String> = navs.values().map(|nav| nav.note_id.clone()).collect();
+    let notes: HashMap<String, FsckNote> = sqlx::query_as::<_, FsckNote>(
+        r#"
+        SELECT id, database_id, root_nav_id, is_delete
+        FROM hulunote_notes
+        WHERE database_id = $1 OR id::text = ANY($2)
+        "#,
+    )
+    .bind(database_id)
+    .bind(&note_ids)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|note| (note.id.to_string(), note))
+    .collect();
+
+    Ok((notes, navs))
+}
+
+fn in_database(database_id: &str, nav: &FsckNav, notes: &HashMap<String, FsckNote>) -> bool {
+    nav.database_id == database_id || notes.get(&nav.note_id).is_some_and(|note| note.database_id == database_id)
+}
+
+fn find_issues(database_id: &str, notes: &HashMap<String, FsckNote>, navs: &HashMap<Uuid, FsckNav>) -> Vec<FsckIssue> {
+    let mut issues = Vec::new();
+    let issue = |kind, note_id: &str, nav_id: String, detail: String| FsckIssue {
+        kind,
+        note_id: note_id.to_string(),
+        nav_id,
+        detail,
+    };
+
+    let mut live_notes: Vec<(&String, &FsckNote)> = notes
+        .iter()
+        .filter(|(_, note)| note.database_id == database_id && !note.is_delete)
+        .collect();
+    live_notes.sort_by_key(|(id, _)| *id);
+
+    for (note_id, note) in live_notes {
+        let root_ok = Uuid::parse_str(&note.root_nav_id)
+            .ok()
+            .and_then(|id| navs.get(&id))
+            .is_some_and(|root| !root.is_delete && root.parid == ROOT_NAV_ID && &root.note_id == note_id);
+        if !root_ok {
+            issues.push(issue(
+                FsckIssueKind::MissingRoot,
+                note_id,
+                note.root_nav_id.clone(),
+                "Root nav is missing or deleted".to_string(),
+            ));
+        }
+    }
+
+    let mut ids: Vec<&Uuid> = navs
+        .iter()
+        .filter(|(_, nav)| !nav.is_delete && in_database(database_id, nav, notes))
+        .map(|(id, _)| id)
+        .collect();
+    ids.sort();
+
+    for id in ids {
+        let nav = &navs[id];
+        let note = match notes.get(&nav.note_id) {
+            None => {
+                issues.push(issue(
+                    FsckIssueKind::Orphan,
+                    &nav.note_id,
+                    id.to_string(),
+                    format!("Note {} not found", nav.note_id),
+                ));
+                continue;
+            }
+            // Navs of deleted notes come back with the note
+            Some(note) if note.is_delete => continue,
+            Some(note) => note,
+        };
+
+        if nav.database_id != note.database_id {
+            issues.push(issue(
+                FsckIssueKind::MismatchedDatabase,
+                &nav.note_id,
+                id.to_string(),
+                format!("Nav is in database {} but its note is in {}", nav.database_id, note.database_id),
+            ));
+        }
+
+        if nav.parid == ROOT_NAV_ID {
+            if id.to_string() != note.root_nav_id {
+                issues.push(issue(
+                    FsckIssueKind::DuplicateRoot,
+                    &nav.note_id,
+                    id.to_string(),
+                    format!("Top-level nav besides the root nav {}", note.root_nav_id),
+                ));
+            }
+            continue;
+        }
+
+        // Children of a deleted nav are not orphans: they come back when
+        // it is restored
+        match Uuid::parse_str(&nav.parid).ok().and_then(|parid| navs.get(&parid)) {
+            None => issues.push(issue(
+                FsckIssueKind::Orphan,
+                &nav.note_id,
+                id.to_string(),
+                format!("Parent {} not found", nav.parid),
+            )),
+            Some(parent) if parent.note_id != nav.note_id => issues.push(issue(
+                FsckIssueKind::MismatchedNote,
+                &nav.note_id,
+                id.to_string(),
+                format!("Parent {} is in note {}", parent.id, parent.note_id),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    for cycle in find_cycles(navs) {
+        // Cut each cycle at its smallest id, so repeated runs agree
+        let cut = *cycle.iter().min().unwrap();
+        let nav = &navs[&cut];
+        if in_database(database_id, nav, notes) {
+            issues.push(issue(
+                FsckIssueKind::Cycle,
+                &nav.note_id,
+                cut.to_string(),
+                format!("Cycle through {} navs", cycle.len()),
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Every cycle in the parent links between navs
+fn find_cycles(navs: &HashMap<Uuid, FsckNav>) -> Vec<Vec<Uuid>> {
+    enum Visit {
+        InPath,
+        Done,
+    }
+
+    let mut ids: Vec<&Uuid> = navs.keys().collect();
+    ids.sort();
+
+    let mut visits: HashMap<Uuid, Visit> = HashMap::new();
+    let mut cycles = Vec::new();
+
+    for &start in ids {
+        let mut path = Vec::new();
+        let mut current = start;
+        loop {
+            match visits.get(&current) {
+                Some(Visit::Done) => break,
+                Some(Visit::InPath) => {
+                    let from = path.iter().position(|id| *id == current).unwrap();
+                    cycles.push(path[from..].to_vec());
+                    break;
+                }
+                None => {}
+            }
+            visits.insert(current, Visit::InPath);
+            path.push(current);
+
+            match Uuid::parse_str(&navs[&current].parid).ok().filter(|parid| navs.contains_key(parid)) {
+                Some(parid) => current = parid,
+                None => break,
+            }
+        }
+        for id in path {
+            visits.insert(id, Visit::Done);
+        }
+    }
+
+    cycles
+}
+
+async fn repair_issues(
+    conn: &mut PgConnection,
+    mode: FsckRepair,
+    notes: &HashMap<String, FsckNote>,
+    navs: &HashMap<Uuid, FsckNav>,
+    report: &mut FsckReport,
+) -> Result<Vec<WsEvent>> {
+    let backend_ts = Utc::now().timestamp_millis();
+    let mut events = Vec::new();
+    let (owner,): (i64,) = sqlx::query_as("SELECT account_id FROM hulunote_databases WHERE id::text = $1")
+        .bind(&report.database_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let mut touched: Vec<Uuid> = Vec::new();
+    // Navs whose note or database changed, so their links need re-indexing
+    let mut relinked: HashSet<Uuid> = HashSet::new();
+
+    // Root navs first, so reattached navs have somewhere to go
+    let mut roots: HashMap<String, String> = notes
+        .iter()
+        .filter(|(_, note)| note.database_id == report.database_id && !note.is_delete)
+        .map(|(id, note)| (id.clone(), note.root_nav_id.clone()))
+        .collect();
+
+    for issue in report.issues.iter().filter(|i| i.kind == FsckIssueKind::MissingRoot) {
+        let root = repair_root(conn, owner, &issue.note_id, &report.database_id, &issue.nav_id).await?;
+        roots.insert(issue.note_id.clone(), root.to_string());
+        touched.push(root);
+        report.repaired += 1;
+    }
+
+    for issue in report.issues.iter().filter(|i| i.kind == FsckIssueKind::MismatchedDatabase) {
+        let nav_id = Uuid::parse_str(&issue.nav_id).map_err(|_| AppError::Internal("Invalid nav ID".to_string()))?;
+        sqlx::query("UPDATE hulunote_navs SET database_id = $2, updated_at = NOW() WHERE id = $1")
+            .bind(nav_id)
+            .bind(&notes[&issue.note_id].database_id)
+            .execute(&mut *conn)
+            .await?;
+        touched.push(nav_id);
+        relinked.insert(nav_id);
+        report.repaired += 1;
+    }
+
+    let mut children: HashMap<&str, Vec<Uuid>> = HashMap::new();
+    for nav in navs.values() {
+        children.entry(nav.parid.as_str()).or_default().push(nav.id);
+    }
+
+    let misplaced = report.issues.iter().filter(|i| {
+        matches!(
+            i.kind,
+            FsckIssueKind::Orphan | FsckIssueKind::DuplicateRoot | FsckIssueKind::MismatchedNote | FsckIssueKind::Cycle
+        )
+    });
+    let mut recovered: Option<(String, String)> = None;
+    let mut repaired = 0;
+
+    for issue in misplaced {
+        let nav_id = Uuid::parse_str(&issue.nav_id).map_err(|_| AppError::Internal("Invalid nav ID".to_string()))?;
+        let nav = &navs[&nav_id];
+
+        let reattach_to = roots.get(&nav.note_id).filter(|root| **root != issue.nav_id);
+        let (note_id, parid) = match (mode, reattach_to) {
+            (FsckRepair::Reattach, Some(root)) => (nav.note_id.clone(), root.clone()),
+            // Navs without a note of their own always go to the Recovered note
+            _ => {
+                if recovered.is_none() {
+                    let note = recovered_note(conn, owner, &report.database_id, backend_ts, &mut events).await?;
+                    report.recovered_note_id = Some(note.0.clone());
+                    recovered = Some(note);
+                }
+                recovered.clone().unwrap()
+            }
+        };
+
+        let (order,): (f32,) = sqlx::query_as(
+            "SELECT COALESCE(MAX(same_deep_order) + 1, 0)::real FROM hulunote_navs WHERE parid = $1 AND is_delete = false",
+        )
+        .bind(&parid)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query("UPDATE hulunote_navs SET parid = $2, same_deep_order = $3, updated_at = NOW() WHERE id = $1")
+            .bind(nav_id)
+            .bind(&parid)
+            .bind(order)
+            .execute(&mut *conn)
+            .await?;
+        touched.push(nav_id);
+
+        if note_id != nav.note_id || nav.database_id != report.database_id {
+            let subtree = descendants(nav_id, &children);
+            sqlx::query(
+                "UPDATE hulunote_navs SET note_id = $2, database_id = $3, updated_at = NOW() WHERE id = ANY($1)",
+            )
+            .bind(&subtree)
+            .bind(&note_id)
+            .bind(&report.database_id)
+            .execute(&mut *conn)
+            .await?;
+            relinked.extend(subtree.iter().copied());
+            touched.extend(subtree);
+        }
+
+        repaired += 1;
+    }
+    report.repaired += repaired;
+
+    let relinked: Vec<Uuid> = relinked.into_iter().collect();
+    let moved: Vec<(Uuid, String, String, String)> =
+        sqlx::query_as("SELECT id, note_id, database_id, content FROM hulunote_navs WHERE id = ANY($1)")
+            .bind(&relinked)
+            .fetch_all(&mut *conn)
+            .await?;
+    for (nav_id, note_id, database_id, content) in moved {
+        sync_nav_links(conn, nav_id, &note_id, &database_id, &content).await?;
+    }
+
+    touched.sort();
+    touched.dedup();
+    record_nav_revisions(conn, &touched, owner, "update").await?;
+
+    let previous: HashMap<Uuid, (String, String)> = touched
+        .iter()
+        .filter_map(|id| navs.get(id))
+        .map(|nav| (nav.id, (nav.note_id.clone(), nav.database_id.clone())))
+        .collect();
+    let repaired = fetch_navs(conn, &touched).await?;
+    events.extend(nav_batches(&repaired, &previous, backend_ts));
+
+    Ok(events)
+}
+
+/// A nav and everything below it, stopping at cycles
+fn descendants(nav_id: Uuid, children: &HashMap<&str, Vec<Uuid>>) -> Vec<Uuid> {
+    let mut seen = HashSet::from([nav_id]);
+    let mut queue = vec![nav_id];
+    let mut i = 0;
+    while i < queue.len() {
+        let id = queue[i].to_string();
+        for child in children.get(id.as_str()).into_iter().flatten() {
+            if seen.insert(*child) {
+                queue.push(*child);
+            }
+        }
+        i += 1;
+    }
+    queue
+}
+
+/// Make a note's root nav exist at the top level of the note, creating it
+/// or bringing it back as needed. Returns the root nav's id.
+async fn repair_root(
+    conn: &mut PgConnection,
+    account_id: i64,
+    note_id: &str,
+    database_id: &str,
+    root_nav_id: &str,
+) -> Result<Uuid> {
+    let root = match Uuid::parse_str(root_nav_id) {
+        Ok(root) => root,
+        Err(_) => {
+            let root = Uuid::new_v4();
+            sqlx::query("UPDATE hulunote_notes SET root_nav_id = $2, updated_at = NOW() WHERE id::text = $1")
+                .bind(note_id)
+                .bind(root.to_string())
+                .execute(&mut *conn)
+                .await?;
+            root
+        }
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id)
+        VALUES ($1, $2, 0, 'ROOT', $3, $4, $5)
+        ON CONFLICT (id) DO UPDATE
+        SET parid = EXCLUDED.parid,
+            note_id = EXCLUDED.note_id,
+            database_id = EXCLUDED.database_id,
+            is_delete = false,
+            deleted_at = NULL,
+            updated_at = NOW()
+        "#,
+    )
+    .bind(root)
+    .bind(ROOT_NAV_ID)
+    .bind(account_id)
+    .bind(note_id)
+    .bind(database_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(root)
+}
+
+/// Find or create the database's Recovered note, adding the event that
+/// announces it to `events`. Returns its id and root nav id.
+async fn recovered_note(
+    conn: &mut PgConnection,
+    account_id: i64,
+    database_id: &str,
+    backend_ts: i64,
+    events: &mut Vec<WsEvent>,
+) -> Result<(String, String)> {
+    let existing: Option<(Uuid, String)> =
+        sqlx::query_as("SELECT id, root_nav_id FROM hulunote_notes WHERE database_id = $1 AND title = $2")
+            .bind(database_id)
+            .bind(RECOVERED_NOTE_TITLE)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    if let Some((note_id, root_nav_id)) = existing {
+        let revived = sqlx::query(
+            "UPDATE hulunote_notes SET is_delete = false, deleted_at = NULL, updated_at = NOW() WHERE id = $1 AND is_delete",
+        )
+        .bind(note_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+        if revived > 0 {
+            events.push(WsEvent::NoteUpdated {
+                note_id: note_id.to_string(),
+                database_id: database_id.to_string(),
+                backend_ts,
+                title: None,
+                is_public: None,
+                is_shortcut: None,
+                is_template: None,
+                is_delete: Some(false),
+                rewritten_navs: Vec::new(),
+            });
+        }
+        let root = repair_root(conn, account_id, &note_id.to_string(), database_id, &root_nav_id).await?;
+        return Ok((note_id.to_string(), root.to_string()));
+    }
+
+    let note = insert_note(conn, account_id, Uuid::new_v4(), Uuid::new_v4(), database_id, RECOVERED_NOTE_TITLE)
+        .await?
+        .ok_or_else(|| AppError::Internal(format!("Note '{}' was created concurrently", RECOVERED_NOTE_TITLE)))?;
+    events.push(WsEvent::NoteCreated {
+        note_id: note.id.to_string(),
+        database_id: database_id.to_string(),
+        backend_ts,
+        title: note.title,
+        root_nav_id: note.root_nav_id.clone(),
+    });
+
+    Ok((note.id.to_string(), note.root_nav_id))
+}
@@ -1,20 +1,28 @@
 use axum::extract::{Multipart, State};
 use axum::Extension;
 use axum::Json;
+use chrono::Utc;
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::io::Read;
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
 use crate::models::*;
 
-use super::{get_database_id, AppState};
+use super::{
+    get_database_id, json_to_notes, markdown_to_note, opml_to_note, record_nav_revision, sync_nav_links, sync_nav_index,
+    ws::WsEvent, Access, AppState,
+};
 
 const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
 
-/// Extract JSON files from uploaded data.
-/// If the file is a .zip, extracts all .json entries inside it.
-/// Otherwise treats the file as a plain JSON file.
+/// File extensions that can be imported, directly or inside a ZIP
+const IMPORT_EXTENSIONS: [&str; 3] = [".json", ".md", ".opml"];
+
+/// Extract note files from uploaded data.
+/// If the file is a .zip, extracts all .json, .md and .opml entries inside it.
+/// Otherwise treats the file as a single note file.
 fn collect_json_files(filename: &str, data: Vec<u8>) -> Result<Vec<(String, Vec<u8>)>> {
     let lower = filename.to_lowercase();
     if lower.ends_with(".zip") {
@@ -29,8 +37,9 @@ fn collect_json_files(filename: &str, data: Vec<u8>) -> Result<Vec<(String, Vec<
             })?;
 
             let entry_name = entry.name().to_string();
-            // Skip directories and non-json files
-            if entry.is_dir() || !entry_name.to_lowercase().ends_with(".json") {
+            // Skip directories and files that are not notes
+            let entry_lower = entry_name.to_lowercase();
+            if entry.is_dir() || !IMPORT_EXTENSIONS.iter().any(|ext| entry_lower.ends_with(ext)) {
                 continue;
             }
 
@@ -46,11 +55,61 @@ fn collect_json_files(filename: &str, data: Vec<u8>) -> Result<Vec<(String, Vec<
     }
 }
 
+/// Note title for a file: its name without directories or extension
+fn file_title(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// Parse an uploaded file into notes, by file extension.
+/// JSON files may hold one Hulunote note or a whole Roam / Logseq graph.
+fn parse_note_file(filename: &str, data: &[u8]) -> Result<Vec<ImportNoteJson>> {
+    let lower = filename.to_lowercase();
+    if lower.ends_with(".md") || lower.ends_with(".opml") {
+        let text = std::str::from_utf8(data)
+            .map_err(|_| AppError::BadRequest(format!("{} is not valid UTF-8", filename)))?;
+        if lower.ends_with(".opml") {
+            return Ok(vec![opml_to_note(&file_title(filename), text)?]);
+        }
+        return Ok(vec![markdown_to_note(&file_title(filename), text)]);
+    }
+
+    json_to_notes(data)
+}
+
+/// Give a note and its navs fresh UUIDs, keeping the tree intact.
+/// Used to copy a note next to the one it was exported from.
+fn assign_new_ids(data: &mut ImportNoteJson) {
+    let mut ids: HashMap<String, String> = HashMap::new();
+    ids.insert(data.note.root_nav_id.clone(), Uuid::new_v4().to_string());
+    for nav in &data.navs {
+        ids.entry(nav.id.clone())
+            .or_insert_with(|| Uuid::new_v4().to_string());
+    }
+
+    let note_id = Uuid::new_v4().to_string();
+    data.note.id = note_id.clone();
+    data.note.root_nav_id = ids[&data.note.root_nav_id].clone();
+
+    for nav in &mut data.navs {
+        nav.id = ids[&nav.id].clone();
+        if let Some(parid) = ids.get(&nav.parid) {
+            nav.parid = parid.clone();
+        }
+        nav.hulunote_note = note_id.clone();
+    }
+}
+
 /// Import notes from uploaded JSON / ZIP files (multipart form)
 ///
 /// Form fields:
 ///   - `database-id` or `database-name`: target database
-///   - one or more file fields: JSON files or ZIP archives containing JSON files
+///   - `new-ids` (optional): `true` to give imported notes and navs fresh UUIDs
+///   - one or more file fields: JSON (Hulunote, Roam or Logseq), Markdown (.md) or
+///     OPML (.opml) files, or ZIP archives containing them
 pub async fn import_notes(
     State(state): State<AppState>,
     Extension(account_id): Extension<i64>,
@@ -58,6 +117,7 @@ pub async fn import_notes(
 ) -> Result<Json<Value>> {
     let mut database_id_str: Option<String> = None;
     let mut database_name_str: Option<String> = None;
+    let mut new_ids = false;
     let mut json_files: Vec<(String, Vec<u8>)> = Vec::new();
 
     // Parse multipart fields
@@ -83,6 +143,13 @@ pub async fn import_notes(
                     .map_err(|e| AppError::BadRequest(format!("Failed to read field: {}", e)))?;
                 database_name_str = Some(text);
             }
+            "new-ids" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to read field: {}", e)))?;
+                new_ids = text.trim() == "true";
+            }
             _ => {
                 // Treat as file upload
                 let filename = field
@@ -94,7 +161,7 @@ pub async fn import_notes(
                     .await
                     .map_err(|e| AppError::BadRequest(format!("Failed to read file: {}", e)))?;
 
-                // Expand ZIP files into individual JSON files
+                // Expand ZIP files into individual note files
                 let extracted = collect_json_files(&filename, data.to_vec())?;
                 json_files.extend(extracted);
             }
@@ -103,7 +170,7 @@ pub async fn import_notes(
 
     if json_files.is_empty() {
         return Err(AppError::BadRequest(
-            "No JSON files uploaded (or ZIP contains no .json files)".to_string(),
+            "No note files uploaded (or ZIP contains no .json, .md or .opml files)".to_string(),
         ));
     }
 
@@ -113,6 +180,7 @@ pub async fn import_notes(
         account_id,
         database_id_str.as_deref(),
         database_name_str.as_deref(),
+        Access::Write,
     )
     .await?
     .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
@@ -123,23 +191,53 @@ pub async fn import_notes(
     let mut errors: Vec<Value> = Vec::new();
 
     for (filename, data) in &json_files {
-        match import_single_note(
-            state.pool.as_ref(),
-            account_id,
-            &database_id_s,
-            filename,
-            data,
-        )
-        .await
-        {
-            Ok(info) => imported.push(info),
-            Err(e) => errors.push(json!({
-                "file": filename,
-                "error": e.to_string()
-            })),
+        let notes = match parse_note_file(filename, data) {
+            Ok(notes) => notes,
+            Err(e) => {
+                errors.push(json!({
+                    "file": filename,
+                    "error": e.to_string()
+                }));
+                continue;
+            }
+        };
+
+        for note in notes {
+            let title = note.note.title.clone();
+            match import_single_note(
+                state.pool.as_ref(),
+                account_id,
+                &database_id_s,
+                filename,
+                note,
+                new_ids,
+            )
+            .await
+            {
+                Ok(info) => imported.push(info),
+                Err(e) => errors.push(json!({
+                    "file": filename,
+                    "title": title,
+                    "error": e.to_string()
+                })),
+            }
         }
     }
 
+    if !imported.is_empty() {
+        state
+            .ws_broadcaster
+            .broadcast_to_collaborators(
+                state.pool.as_ref(),
+                WsEvent::NotesImported {
+                    database_id: database_id_s.clone(),
+                    backend_ts: Utc::now().timestamp_millis(),
+                    notes: imported.clone(),
+                },
+            )
+            .await;
+    }
+
     Ok(Json(json!({
         "success": true,
         "imported-count": imported.len(),
@@ -149,16 +247,18 @@ pub async fn import_notes(
     })))
 }
 
-/// Import a single note JSON file into the database
+/// Import a single parsed note into the database
 async fn import_single_note(
     pool: &sqlx::PgPool,
     account_id: i64,
     database_id: &str,
     filename: &str,
-    data: &[u8],
+    mut import_data: ImportNoteJson,
+    new_ids: bool,
 ) -> Result<Value> {
-    let import_data: ImportNoteJson = serde_json::from_slice(data)
-        .map_err(|e| AppError::BadRequest(format!("Invalid JSON in {}: {}", filename, e)))?;
+    if new_ids {
+        assign_new_ids(&mut import_data);
+    }
 
     let note_data = &import_data.note;
 
@@ -168,6 +268,20 @@ async fn import_single_note(
     let root_nav_id = Uuid::parse_str(&note_data.root_nav_id)
         .map_err(|_| AppError::BadRequest(format!("Invalid root nav ID in {}", filename)))?;
 
+    // Navs can only hang under the note's root or under other navs of the file
+    let known: HashSet<&str> = import_data
+        .navs
+        .iter()
+        .map(|nav| nav.id.as_str())
+        .chain([note_data.root_nav_id.as_str()])
+        .collect();
+    if let Some(nav) = import_data.navs.iter().find(|nav| !known.contains(nav.parid.as_str())) {
+        return Err(AppError::BadRequest(format!(
+            "Nav {} in {} has a parent outside the note: {}",
+            nav.id, filename, nav.parid
+        )));
+    }
+
     // Check if note with same ID already exists
     let exists: Option<(Uuid,)> =
         sqlx::query_as("SELECT id FROM hulunote_notes WHERE id = $1")
@@ -202,8 +316,9 @@ async fn import_single_note(
     // Insert the note
     sqlx::query(
         r#"
-        INSERT INTO hulunote_notes (id, title, database_id, root_nav_id, is_delete, is_public, is_shortcut, account_id)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        INSERT INTO hulunote_notes (id, title, database_id, root_nav_id, is_delete, is_public, is_shortcut, is_template,
+                                    account_id, deleted_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, CASE WHEN $5 THEN NOW() END)
         "#,
     )
     .bind(note_id)
@@ -213,12 +328,13 @@ async fn import_single_note(
     .bind(note_data.is_delete.unwrap_or(false))
     .bind(note_data.is_public.unwrap_or(false))
     .bind(note_data.is_shortcut.unwrap_or(false))
+    .bind(note_data.is_template.unwrap_or(false))
     .bind(account_id)
     .execute(&mut *tx)
     .await?;
 
     // Insert root nav
-    sqlx::query(
+    let inserted = sqlx::query(
         r#"
         INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id)
         VALUES ($1, $2, 0, 'ROOT', $3, $4, $5)
@@ -232,6 +348,9 @@ async fn import_single_note(
     .bind(database_id)
     .execute(&mut *tx)
     .await?;
+    if inserted.rows_affected() == 0 {
+        return Err(nav_exists(&note_data.root_nav_id));
+    }
 
     // Insert all navs
     let mut nav_count: usize = 0;
@@ -241,11 +360,13 @@ async fn import_single_note(
 
         let is_display = nav.is_display.unwrap_or(true);
         let is_delete = nav.is_delete.unwrap_or(false);
+        let properties = nav.properties.as_deref().unwrap_or("");
+        let extra_id = nav.extra_id.as_deref().unwrap_or("");
 
-        sqlx::query(
+        let inserted = sqlx::query(
             r#"
-            INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id, is_display, is_delete)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id, is_display, is_delete, properties, extra_id, deleted_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, CASE WHEN $9 THEN NOW() END)
             ON CONFLICT (id) DO NOTHING
             "#,
         )
@@ -258,8 +379,18 @@ async fn import_single_note(
         .bind(database_id)
         .bind(is_display)
         .bind(is_delete)
+        .bind(properties)
+        .bind(extra_id)
         .execute(&mut *tx)
         .await?;
+        // Skipping the nav would leave its children without a parent
+        if inserted.rows_affected() == 0 {
+            return Err(nav_exists(&nav.id));
+        }
+
+        sync_nav_links(&mut tx, nav_id, &note_id.to_string(), database_id, &nav.content).await?;
+        sync_nav_index(&mut tx, nav_id, &nav.content, properties).await?;
+        record_nav_revision(&mut tx, nav_id, account_id, "import").await?;
 
         nav_count += 1;
     }
@@ -273,3 +404,10 @@ async fn import_single_note(
         "nav-count": nav_count
     }))
 }
+
+fn nav_exists(nav_id: &str) -> AppError {
+    AppError::BadRequest(format!(
+        "Nav {} already exists; import with new-ids to make a copy",
+        nav_id
+    ))
+}
@@ -0,0 +1,261 @@
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_nav, authorize_note, database_role, field_clocks, get_nav_paths, record_nav_revision, sync_nav_index, Access, AppState,
+};
+
+/// A reference written into nav content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    /// `[[Note Title]]`
+    Page(String),
+    /// `((nav-uuid))`
+    Block(Uuid),
+}
+
+/// Parse the `[[Page]]` and `((nav-uuid))` references in nav content.
+///
+/// For nested page links such as `[[a [[b]]]]` only the innermost title is
+/// taken. Duplicates are returned once.
+pub fn parse_references(content: &str) -> Vec<Reference> {
+    let mut refs = Vec::new();
+
+    let mut from = 0;
+    while let Some(close) = content[from..].find("]]") {
+        let close = from + close;
+        if let Some(open) = content[from..close].rfind("[[") {
+            let title = &content[from + open + 2..close];
+            if !title.trim().is_empty() && !title.contains(['[', ']']) {
+                refs.push(Reference::Page(title.to_string()));
+            }
+        }
+        from = close + 2;
+    }
+
+    let mut from = 0;
+    while let Some(open) = content[from..].find("((") {
+        let start = from + open + 2;
+        match content[start..].find("))") {
+            Some(len) => {
+                if let Ok(id) = Uuid::parse_str(&content[start..start + len]) {
+                    refs.push(Reference::Block(id));
+                }
+                from = start + len + 2;
+            }
+            None => break,
+        }
+    }
+
+    let mut unique = Vec::with_capacity(refs.len());
+    for r in refs {
+        if !unique.contains(&r) {
+            unique.push(r);
+        }
+    }
+    unique
+}
+
+/// Replace the links stored for a nav with the references in its content
+pub async fn sync_nav_links(
+    conn: &mut sqlx::PgConnection,
+    nav_id: Uuid,
+    note_id: &str,
+    database_id: &str,
+    content: &str,
+) -> Result<()> {
+    sqlx::query("DELETE FROM hulunote_links WHERE source_nav_id = $1")
+        .bind(nav_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for reference in parse_references(content) {
+        let (link_type, target_title, target_nav_id) = match reference {
+            Reference::Page(title) => ("page", Some(title), None),
+            Reference::Block(id) => ("block", None, Some(id)),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO hulunote_links (source_nav_id, source_note_id, database_id, link_type, target_title, target_nav_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(nav_id)
+        .bind(note_id)
+        .bind(database_id)
+        .bind(link_type)
+        .bind(target_title)
+        .bind(target_nav_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Rewrite `[[old_title]]` to `[[new_title]]` in every nav of a database
+/// that links to the old title. Returns the rewritten navs.
+pub async fn rewrite_page_references(
+    conn: &mut sqlx::PgConnection,
+    account_id: i64,
+    database_id: &str,
+    old_title: &str,
+    new_title: &str,
+) -> Result<Vec<NavContent>> {
+    let navs: Vec<(Uuid, String, String, String)> = sqlx::query_as(
+        r#"
+        UPDATE hulunote_navs v
+        SET content = REPLACE(v.content, $3, $4), last_account_id = $5,
+            field_clocks = v.field_clocks || $6, updated_at = NOW()
+        WHERE v.id IN (
+            SELECT source_nav_id FROM hulunote_links
+            WHERE database_id = $1 AND link_type = 'page' AND target_title = $2
+        )
+        RETURNING v.id, v.note_id, v.content, v.properties
+        "#,
+    )
+    .bind(database_id)
+    .bind(old_title)
+    .bind(format!("[[{}]]", old_title))
+    .bind(format!("[[{}]]", new_title))
+    .bind(account_id)
+    .bind(field_clocks(&["content"], Utc::now().timestamp_millis()))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for (nav_id, note_id, content, properties) in &navs {
+        sync_nav_links(conn, *nav_id, note_id, database_id, content).await?;
+        sync_nav_index(conn, *nav_id, content, properties).await?;
+        record_nav_revision(conn, *nav_id, account_id, "update").await?;
+    }
+
+    Ok(navs
+        .into_iter()
+        .map(|(id, note_id, content, _)| NavContent { id: id.to_string(), note_id, content })
+        .collect())
+}
+
+/// Whether the caller only sees what is public in a database: the target of
+/// a backlink can be public while the notes referencing it are not
+async fn public_only(pool: &sqlx::PgPool, account_id: i64, database_id: &str) -> Result<bool> {
+    let database_uuid = Uuid::parse_str(database_id)
+        .map_err(|_| AppError::Internal("Invalid database ID".to_string()))?;
+    Ok(database_role(pool, account_id, database_uuid).await?.is_none())
+}
+
+/// Get every nav that references a note (by title) or a nav (by id).
+/// Callers without a role in the database only get references from public
+/// notes.
+pub async fn get_backlinks(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetBacklinksRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+
+    let navs: Vec<HulunoteNav> = if let Some(nav_id) = &req.nav_id {
+        let nav_uuid = Uuid::parse_str(nav_id)
+            .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+        let (_, database_id) = authorize_nav(pool, account_id, nav_uuid, Access::Read).await?;
+        let public_only = public_only(pool, account_id, &database_id).await?;
+
+        sqlx::query_as(
+            r#"
+            SELECT v.id, v.parid, v.same_deep_order, v.content, v.account_id, v.last_account_id, v.note_id, v.database_id,
+                   v.is_display, v.is_public, v.is_delete, v.properties, v.extra_id, v.created_at, v.updated_at
+            FROM hulunote_navs v
+            WHERE v.id IN (
+                SELECT source_nav_id FROM hulunote_links
+                WHERE database_id = $1 AND link_type = 'block' AND target_nav_id = $2
+            )
+            AND v.is_delete = false
+            AND (NOT $3 OR EXISTS (
+                SELECT 1 FROM hulunote_notes n
+                LEFT JOIN hulunote_databases d ON d.id::text = n.database_id
+                WHERE n.id::text = v.note_id AND (n.is_public OR COALESCE(d.is_public, false))
+            ))
+            ORDER BY v.note_id, v.same_deep_order ASC
+            "#,
+        )
+        .bind(&database_id)
+        .bind(nav_uuid)
+        .bind(public_only)
+        .fetch_all(pool)
+        .await?
+    } else if let Some(note_id) = &req.note_id {
+        let note_uuid = Uuid::parse_str(note_id)
+            .map_err(|_| AppError::BadRequest("Invalid note ID".to_string()))?;
+        let database_id = authorize_note(pool, account_id, note_uuid, Access::Read).await?;
+        let public_only = public_only(pool, account_id, &database_id).await?;
+
+        let (title,): (String,) = sqlx::query_as("SELECT title FROM hulunote_notes WHERE id = $1")
+            .bind(note_uuid)
+            .fetch_one(pool)
+            .await?;
+
+        sqlx::query_as(
+            r#"
+            SELECT v.id, v.parid, v.same_deep_order, v.content, v.account_id, v.last_account_id, v.note_id, v.database_id,
+                   v.is_display, v.is_public, v.is_delete, v.properties, v.extra_id, v.created_at, v.updated_at
+            FROM hulunote_navs v
+            WHERE v.id IN (
+                SELECT source_nav_id FROM hulunote_links
+                WHERE database_id = $1 AND link_type = 'page' AND target_title = $2
+            )
+            AND v.is_delete = false
+            AND (NOT $3 OR EXISTS (
+                SELECT 1 FROM hulunote_notes n
+                LEFT JOIN hulunote_databases d ON d.id::text = n.database_id
+                WHERE n.id::text = v.note_id AND (n.is_public OR COALESCE(d.is_public, false))
+            ))
+            ORDER BY v.note_id, v.same_deep_order ASC
+            "#,
+        )
+        .bind(&database_id)
+        .bind(&title)
+        .bind(public_only)
+        .fetch_all(pool)
+        .await?
+    } else {
+        return Err(AppError::BadRequest("note-id or nav-id required".to_string()));
+    };
+
+    // Titles of the referencing notes (deleted notes are left out)
+    let note_ids: Vec<String> = navs.iter().map(|n| n.note_id.clone()).collect();
+    let titles: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+        "SELECT id::text, title FROM hulunote_notes WHERE id::text = ANY($1) AND is_delete = false"
+    )
+    .bind(&note_ids)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let nav_ids: Vec<Uuid> = navs.iter().map(|n| n.id).collect();
+    let mut paths = get_nav_paths(pool, &nav_ids).await?;
+
+    let backlinks: Vec<Value> = navs
+        .into_iter()
+        .filter_map(|nav| {
+            let title = titles.get(&nav.note_id)?.clone();
+            let path = paths.remove(&nav.id).unwrap_or_default();
+            Some(json!({
+                "note-id": nav.note_id.clone(),
+                "note-title": title,
+                "path": path,
+                "nav": NavInfo::from(nav)
+            }))
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "backlinks": backlinks
+    })))
+}
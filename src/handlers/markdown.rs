@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::models::*;
+
+use super::children_by_parent;
+
+/// Property written for collapsed navs (`is_display = false`)
+pub(crate) const COLLAPSED_PROPERTY: &str = "collapsed";
+
+/// Property used when a nav's properties are not `key:: value` lines.
+/// Its value is the original text as a JSON string.
+pub(crate) const RAW_PROPERTIES_PROPERTY: &str = "properties";
+
+/// Split a `key:: value` line into key and value
+pub fn parse_property_line(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().split_once("::")?;
+    let valid_key = !key.is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid_key || !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// Format a property as a `key:: value` line. Values that span lines
+/// or start with a quote are written as JSON strings.
+pub fn format_property_line(key: &str, value: &str) -> String {
+    if value.contains(['\n', '\r']) || value.starts_with('"') {
+        format!("{}:: {}", key, serde_json::Value::String(value.to_string()))
+    } else {
+        format!("{}:: {}", key, value)
+    }
+}
+
+/// Value of a property line, decoding values written as JSON strings
+pub fn property_value(value: &str) -> String {
+    if value.starts_with('"') {
+        if let Ok(decoded) = serde_json::from_str::<String>(value) {
+            return decoded;
+        }
+    }
+    value.to_string()
+}
+
+/// Turn nav properties into `key:: value` lines that survive a round trip
+fn property_lines(properties: &str) -> Vec<String> {
+    let lines: Vec<&str> = properties
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    let all_key_value = lines.iter().all(|line| match parse_property_line(line) {
+        Some((key, _)) => key != COLLAPSED_PROPERTY && key != RAW_PROPERTIES_PROPERTY,
+        None => false,
+    });
+
+    if all_key_value {
+        lines.into_iter().map(|line| line.trim().to_string()).collect()
+    } else {
+        vec![format!(
+            "{}:: {}",
+            RAW_PROPERTIES_PROPERTY,
+            serde_json::Value::String(properties.to_string())
+        )]
+    }
+}
+
+/// Escape character for content lines that would otherwise not read back
+/// as they were written
+const ESCAPE: char = '\\';
+
+/// Write an extra content line of a nav so that import reads it back as
+/// content. Lines that look like a bullet or a property, are blank, start
+/// with the escape or have surrounding whitespace get a leading `\`.
+fn escape_content_line(line: &str) -> String {
+    let needs_escape = line.trim().is_empty()
+        || line.trim() != line
+        || line.starts_with(ESCAPE)
+        || bullet_content(line).is_some()
+        || parse_property_line(line).is_some();
+    if needs_escape {
+        format!("{}{}", ESCAPE, line)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Write a note as nested `- ` bullets, two spaces per level.
+///
+/// Extra content lines and properties are indented under their bullet.
+/// Collapsed navs get a `collapsed:: true` property.
+pub fn note_to_markdown(note: &ImportNoteJson) -> String {
+    let children = children_by_parent(&note.navs);
+    let mut out = String::new();
+    write_navs(&children, &note.note.root_nav_id, 0, &mut out);
+    out
+}
+
+fn write_navs(children: &HashMap<&str, Vec<&ImportNavData>>, parid: &str, depth: usize, out: &mut String) {
+    let Some(navs) = children.get(parid) else { return };
+    let indent = "  ".repeat(depth);
+
+    for nav in navs {
+        let mut lines = nav.content.lines();
+        out.push_str(&format!("{}- {}\n", indent, lines.next().unwrap_or("")));
+        for line in lines {
+            out.push_str(&format!("{}  {}\n", indent, escape_content_line(line)));
+        }
+
+        if nav.is_display == Some(false) {
+            out.push_str(&format!("{}  {}:: true\n", indent, COLLAPSED_PROPERTY));
+        }
+        if let Some(properties) = nav.properties.as_deref().filter(|p| !p.is_empty()) {
+            for line in property_lines(properties) {
+                out.push_str(&format!("{}  {}\n", indent, line));
+            }
+        }
+
+        write_navs(children, &nav.id, depth + 1, out);
+    }
+}
+
+/// A nav being built from Markdown
+struct ParsedNav {
+    indent: usize,
+    parent: Option<usize>,
+    content: Vec<String>,
+    properties: Vec<String>,
+    collapsed: bool,
+}
+
+/// Width of leading whitespace, counting a tab as two spaces
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 2 } else { 1 })
+        .sum()
+}
+
+/// Content of a bullet line (`- `, `* ` or `+ `), if it is one
+fn bullet_content(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(marker) {
+            return Some(rest);
+        }
+    }
+    if matches!(trimmed, "-" | "*" | "+") {
+        return Some("");
+    }
+    None
+}
+
+/// Build a note from a Markdown outline. The tree follows the bullet
+/// indentation; lines that are not bullets either continue the bullet above
+/// them (when indented under it) or become top-level navs.
+pub fn markdown_to_note(title: &str, text: &str) -> ImportNoteJson {
+    let mut parsed: Vec<ParsedNav> = Vec::new();
+    // Indices into `parsed` of the bullets that can still take children
+    let mut stack: Vec<usize> = Vec::new();
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = indent_width(line);
+
+        if let Some(content) = bullet_content(line) {
+            while stack.last().is_some_and(|&i| parsed[i].indent >= indent) {
+                stack.pop();
+            }
+            parsed.push(ParsedNav {
+                indent,
+                parent: stack.last().copied(),
+                content: vec![content.to_string()],
+                properties: Vec::new(),
+                collapsed: false,
+            });
+            stack.push(parsed.len() - 1);
+            continue;
+        }
+
+        // Continuation of the closest bullet this line is indented under
+        while stack.last().is_some_and(|&i| parsed[i].indent >= indent) {
+            stack.pop();
+        }
+        if let Some(&owner) = stack.last() {
+            let nav = &mut parsed[owner];
+            // An escaped line is content, kept as written
+            if let Some(escaped) = line.trim_start().strip_prefix(ESCAPE) {
+                nav.content.push(escaped.to_string());
+                continue;
+            }
+            match parse_property_line(line) {
+                Some((COLLAPSED_PROPERTY, value)) => nav.collapsed = value == "true",
+                Some((RAW_PROPERTIES_PROPERTY, value)) => {
+                    match serde_json::from_str::<String>(value) {
+                        Ok(raw) => nav.properties.push(raw),
+                        Err(_) => nav.properties.push(line.trim().to_string()),
+                    }
+                }
+                Some(_) => nav.properties.push(line.trim().to_string()),
+                None => nav.content.push(line.trim().to_string()),
+            }
+        } else {
+            // A paragraph outside any bullet is a top-level nav
+            parsed.push(ParsedNav {
+                indent,
+                parent: None,
+                content: vec![line.trim().to_string()],
+                properties: Vec::new(),
+                collapsed: false,
+            });
+            stack.clear();
+        }
+    }
+
+    let items = parsed
+        .into_iter()
+        .map(|nav| OutlineItem {
+            parent: nav.parent,
+            content: nav.content.join("\n"),
+            properties: nav.properties.join("\n"),
+            is_display: !nav.collapsed,
+            extra_id: None,
+        })
+        .collect();
+
+    outline_to_note(title, items)
+}
+
+/// A nav read from an outline file, with its parent as an index into the
+/// same list. Parents always come before their children.
+pub struct OutlineItem {
+    pub parent: Option<usize>,
+    pub content: String,
+    pub properties: String,
+    pub is_display: bool,
+    pub extra_id: Option<String>,
+}
+
+/// Build a note with fresh UUIDs from outline items, numbering
+/// `same-deep-order` 1.0, 2.0, ... among siblings
+pub fn outline_to_note(title: &str, items: Vec<OutlineItem>) -> ImportNoteJson {
+    let note_id = Uuid::new_v4().to_string();
+    let root_nav_id = Uuid::new_v4().to_string();
+    let ids: Vec<String> = items.iter().map(|_| Uuid::new_v4().to_string()).collect();
+    let mut next_order: HashMap<Option<usize>, f64> = HashMap::new();
+
+    let navs = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let order = next_order.entry(item.parent).or_insert(0.0);
+            *order += 1.0;
+            ImportNavData {
+                id: ids[i].clone(),
+                parid: item.parent.map_or_else(|| root_nav_id.clone(), |p| ids[p].clone()),
+                content: item.content,
+                same_deep_order: *order,
+                hulunote_note: note_id.clone(),
+                is_display: Some(item.is_display),
+                is_delete: Some(false),
+                properties: Some(item.properties).filter(|p| !p.is_empty()),
+                extra_id: item.extra_id,
+            }
+        })
+        .collect();
+
+    ImportNoteJson {
+        note: ImportNoteData {
+            id: note_id,
+            title: title.to_string(),
+            root_nav_id,
+            is_delete: None,
+            is_public: None,
+            is_shortcut: None,
+            is_template: None,
+        },
+        navs,
+    }
+}
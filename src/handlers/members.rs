@@ -0,0 +1,202 @@
+use axum::{extract::State, Extension, Json};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{authorize_database, database_role, Access, AppState};
+
+fn parse_database_id(id: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid database ID".to_string()))
+}
+
+/// The account that created a database. It is always an owner, so its
+/// role cannot be changed and it cannot be removed.
+async fn database_creator(pool: &sqlx::PgPool, database_id: Uuid) -> Result<i64> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT account_id FROM hulunote_databases WHERE id = $1")
+        .bind(database_id)
+        .fetch_optional(pool)
+        .await?;
+    row.map(|(id,)| id)
+        .ok_or_else(|| AppError::NotFound("Database not found".to_string()))
+}
+
+/// Fetch one member with their account details
+async fn fetch_member(pool: &sqlx::PgPool, database_id: Uuid, account_id: i64) -> Result<DatabaseMember> {
+    sqlx::query_as(
+        r#"
+        SELECT m.account_id, a.username, a.nickname, m.role, m.invited_by, m.created_at
+        FROM database_members m
+        JOIN accounts a ON a.id = m.account_id
+        WHERE m.database_id = $1 AND m.account_id = $2
+        "#,
+    )
+    .bind(database_id)
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Member not found".to_string()))
+}
+
+/// Share a database with an existing account
+pub async fn invite_database_member(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<InviteMemberRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let database_id = parse_database_id(&req.database_id)?;
+    authorize_database(pool, account_id, database_id, Access::Manage).await?;
+
+    let invitee: Option<(i64,)> = match (req.account_id, &req.username, &req.email) {
+        (Some(id), None, None) => sqlx::query_as("SELECT id FROM accounts WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?,
+        (None, Some(username), None) => sqlx::query_as("SELECT id FROM accounts WHERE username = $1")
+            .bind(username)
+            .fetch_optional(pool)
+            .await?,
+        (None, None, Some(email)) => sqlx::query_as("SELECT id FROM accounts WHERE mail = $1")
+            .bind(email)
+            .fetch_optional(pool)
+            .await?,
+        _ => {
+            return Err(AppError::BadRequest(
+                "Exactly one of account-id, username or email required".to_string(),
+            ))
+        }
+    };
+    let (invitee,) = invitee.ok_or_else(|| AppError::NotFound("Account not found".to_string()))?;
+
+    if invitee == database_creator(pool, database_id).await? {
+        return Err(AppError::BadRequest("The database creator is already an owner".to_string()));
+    }
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO database_members (database_id, account_id, role, invited_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (database_id, account_id) DO NOTHING
+        "#,
+    )
+    .bind(database_id)
+    .bind(invitee)
+    .bind(req.role.as_str())
+    .bind(account_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    if inserted == 0 {
+        return Err(AppError::BadRequest("Account is already a member".to_string()));
+    }
+
+    let member = fetch_member(pool, database_id, invitee).await?;
+    Ok(Json(json!({
+        "success": true,
+        "member": DatabaseMemberInfo::from(member)
+    })))
+}
+
+/// List everyone a database is shared with, its creator first
+pub async fn get_database_members(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetMembersRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let database_id = parse_database_id(&req.database_id)?;
+    // Only members see who else is in a database, even when it is public
+    if database_role(pool, account_id, database_id).await?.is_none() {
+        database_creator(pool, database_id).await?;
+        return Err(AppError::PermissionDenied("Cannot read other's database members".to_string()));
+    }
+
+    let members: Vec<DatabaseMember> = sqlx::query_as(
+        r#"
+        SELECT a.id AS account_id, a.username, a.nickname, 'owner' AS role,
+               NULL::bigint AS invited_by, d.created_at, 0 AS rank
+        FROM hulunote_databases d
+        JOIN accounts a ON a.id = d.account_id
+        WHERE d.id = $1
+        UNION ALL
+        SELECT m.account_id, a.username, a.nickname, m.role, m.invited_by, m.created_at, 1 AS rank
+        FROM database_members m
+        JOIN accounts a ON a.id = m.account_id
+        WHERE m.database_id = $1
+        ORDER BY rank, created_at, account_id
+        "#,
+    )
+    .bind(database_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Json(json!({
+        "members": members.into_iter().map(DatabaseMemberInfo::from).collect::<Vec<_>>()
+    })))
+}
+
+/// Change a member's role
+pub async fn update_database_member(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<UpdateMemberRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let database_id = parse_database_id(&req.database_id)?;
+    authorize_database(pool, account_id, database_id, Access::Manage).await?;
+
+    if req.account_id == database_creator(pool, database_id).await? {
+        return Err(AppError::BadRequest("Cannot change the database creator's role".to_string()));
+    }
+
+    let updated = sqlx::query(
+        "UPDATE database_members SET role = $3, updated_at = NOW() WHERE database_id = $1 AND account_id = $2",
+    )
+    .bind(database_id)
+    .bind(req.account_id)
+    .bind(req.role.as_str())
+    .execute(pool)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Err(AppError::NotFound("Member not found".to_string()));
+    }
+
+    let member = fetch_member(pool, database_id, req.account_id).await?;
+    Ok(Json(json!({
+        "success": true,
+        "member": DatabaseMemberInfo::from(member)
+    })))
+}
+
+/// Stop sharing a database with a member. Members can also remove
+/// themselves to leave a database.
+pub async fn remove_database_member(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<RemoveMemberRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let database_id = parse_database_id(&req.database_id)?;
+    if req.account_id != account_id {
+        authorize_database(pool, account_id, database_id, Access::Manage).await?;
+    }
+
+    if req.account_id == database_creator(pool, database_id).await? {
+        return Err(AppError::BadRequest("Cannot remove the database creator".to_string()));
+    }
+
+    let removed = sqlx::query("DELETE FROM database_members WHERE database_id = $1 AND account_id = $2")
+        .bind(database_id)
+        .bind(req.account_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    if removed == 0 {
+        return Err(AppError::NotFound("Member not found".to_string()));
+    }
+
+    Ok(Json(json!({"success": true})))
+}
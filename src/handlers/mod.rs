@@ -1,34 +1,76 @@
+mod access;
 mod auth;
+mod collab;
+mod daily;
 mod database;
+mod export;
+mod fsck;
 mod import;
+mod links;
+mod markdown;
+mod members;
 mod note;
 mod nav;
+mod opml;
+mod outliners;
+mod public;
+mod revisions;
+mod search;
+mod share;
+mod subtree;
+mod tags;
+mod tasks;
+mod templates;
+mod trash;
 mod user;
 pub mod ws;
 
+pub use access::*;
 pub use auth::*;
+pub use collab::*;
+pub use daily::*;
 pub use database::*;
+pub use export::*;
+pub use fsck::*;
 pub use import::*;
+pub use links::*;
+pub use markdown::*;
+pub use members::*;
 pub use note::*;
 pub use nav::*;
+pub use opml::*;
+pub use outliners::*;
+pub use public::*;
+pub use revisions::*;
+pub use search::*;
+pub use share::*;
+pub use subtree::*;
+pub use tags::*;
+pub use tasks::*;
+pub use templates::*;
+pub use trash::*;
 pub use user::*;
 
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::config::Config;
 use ws::WsBroadcaster;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: Arc<PgPool>,
     pub ws_broadcaster: WsBroadcaster,
+    /// Loaded once at startup
+    pub config: Arc<Config>,
 }
 
 impl AppState {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(pool: PgPool, config: Config) -> Self {
         Self {
             pool: Arc::new(pool),
             ws_broadcaster: WsBroadcaster::new(),
+            config: Arc::new(config),
         }
     }
 }
@@ -1,54 +1,49 @@
 use axum::{extract::State, Extension, Json};
 use chrono::Utc;
 use serde_json::{json, Value};
+use sqlx::PgConnection;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
 use crate::models::*;
 
-use super::{get_database_id, ws::WsEvent, AppState};
+use super::{
+    authorize_nav, authorize_note, get_database_id, record_nav_revision, record_nav_revisions, sync_nav_links, sync_nav_index,
+    ws::WsEvent,
+    Access, AppState,
+};
 
 const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
 
-/// Get database ID by note ID (note.id is UUID, note.database_id is VARCHAR)
-async fn get_database_id_by_note(pool: &sqlx::PgPool, note_id: &str) -> Result<Option<String>> {
-    let note_uuid = Uuid::parse_str(note_id)
-        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
-
-    let result: Option<(String,)> = sqlx::query_as(
-        "SELECT database_id FROM hulunote_notes WHERE id = $1"
-    )
-    .bind(note_uuid)
-    .fetch_optional(pool)
-    .await?;
-
-    Ok(result.map(|r| r.0))
-}
-
 /// Create or update a nav (outline node)
 pub async fn create_or_update_nav(
     State(state): State<AppState>,
     Extension(account_id): Extension<i64>,
     Json(req): Json<CreateOrUpdateNavRequest>,
 ) -> Result<Json<Value>> {
-    // Get database_id (as String since database_id column is VARCHAR)
-    let mut database_id = get_database_id(
+    let note_uuid = Uuid::parse_str(&req.note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+
+    // The note must be writable; its database is where the nav lives
+    // (database_id is VARCHAR, so keep it as String)
+    let database_id =
+        authorize_note(state.pool.as_ref(), account_id, note_uuid, Access::Write).await?;
+
+    // If the client also names a database, it must be the note's database
+    let requested = get_database_id(
         state.pool.as_ref(),
         account_id,
         req.database_id.as_deref(),
         req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Write,
     )
-    .await?
-    .map(|u| u.to_string());
+    .await?;
 
-    // If no database_id, try to get from note
-    if database_id.is_none() {
-        database_id = get_database_id_by_note(state.pool.as_ref(), &req.note_id).await?;
+    if requested.is_some_and(|id| id.to_string() != database_id) {
+        return Err(AppError::BadRequest("Note does not belong to this database".to_string()));
     }
 
-    let database_id = database_id
-        .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
-
     let now = Utc::now();
     let backend_ts = now.timestamp_millis();
 
@@ -66,59 +61,48 @@ pub async fn create_or_update_nav(
         .await?;
 
         if exists.is_some() {
-            // Update existing nav
-            if let Some(content) = &req.content {
-                sqlx::query("UPDATE hulunote_navs SET content = $1, updated_at = NOW() WHERE id = $2")
-                    .bind(content)
-                    .bind(nav_uuid)
-                    .execute(state.pool.as_ref())
-                    .await?;
+            let (nav_note_id, nav_database_id) =
+                authorize_nav(state.pool.as_ref(), account_id, nav_uuid, Access::Write).await?;
+
+            let changes = NavChanges {
+                parid: req.parid.as_deref(),
+                order: req.order,
+                content: req.content.as_deref(),
+                properties: req.properties.as_deref(),
+                is_display: req.is_display,
+                is_delete: req.is_delete,
+                is_public: req.is_public,
+                clock: None,
+            };
+
+            let mut tx = state.pool.begin().await?;
+            if let Some(parid) = changes.parid {
+                check_nav_parent(&mut tx, &nav_note_id, Some(nav_uuid), parid).await?;
             }
-
-            // parid is VARCHAR, use String
-            if let Some(parid) = &req.parid {
-                sqlx::query("UPDATE hulunote_navs SET parid = $1, updated_at = NOW() WHERE id = $2")
-                    .bind(parid)
-                    .bind(nav_uuid)
-                    .execute(state.pool.as_ref())
+            let mut nav =
+                update_nav(&mut tx, account_id, nav_uuid, &nav_note_id, &nav_database_id, &changes, "update")
                     .await?;
+            let reordered = if changes.parid.is_some() || changes.order.is_some() {
+                rebalance_siblings(&mut tx, account_id, &nav.parid).await?
+            } else {
+                Vec::new()
+            };
+            tx.commit().await?;
+
+            if let Some(order) = reordered.iter().find(|o| o.id == nav.id.to_string()) {
+                nav.same_deep_order = order.same_deep_order;
             }
 
-            if let Some(order) = req.order {
-                sqlx::query("UPDATE hulunote_navs SET same_deep_order = $1, updated_at = NOW() WHERE id = $2")
-                    .bind(order)
-                    .bind(nav_uuid)
-                    .execute(state.pool.as_ref())
-                    .await?;
-            }
-
-            if let Some(is_delete) = req.is_delete {
-                sqlx::query("UPDATE hulunote_navs SET is_delete = $1, updated_at = NOW() WHERE id = $2")
-                    .bind(is_delete)
-                    .bind(nav_uuid)
-                    .execute(state.pool.as_ref())
-                    .await?;
-            }
-
-            if let Some(is_display) = req.is_display {
-                sqlx::query("UPDATE hulunote_navs SET is_display = $1, updated_at = NOW() WHERE id = $2")
-                    .bind(is_display)
-                    .bind(nav_uuid)
-                    .execute(state.pool.as_ref())
-                    .await?;
-            }
-
-            if let Some(properties) = &req.properties {
-                sqlx::query("UPDATE hulunote_navs SET properties = $1, updated_at = NOW() WHERE id = $2")
-                    .bind(properties)
-                    .bind(nav_uuid)
-                    .execute(state.pool.as_ref())
-                    .await?;
-            }
+            state
+                .ws_broadcaster
+                .broadcast_to_collaborators(state.pool.as_ref(), nav_changed_event(&nav, &changes, backend_ts))
+                .await;
+            broadcast_reordered(&state, &nav_note_id, &nav_database_id, backend_ts, &reordered).await;
 
             return Ok(Json(json!({
                 "success": true,
                 "id": nav_id,
+                "reordered": reordered,
                 "backend-ts": backend_ts
             })));
         }
@@ -130,51 +114,550 @@ pub async fn create_or_update_nav(
         .and_then(|id| Uuid::parse_str(id).ok())
         .unwrap_or_else(Uuid::new_v4);
 
+    let changes = NavChanges {
+        parid: req.parid.as_deref(),
+        order: req.order,
+        content: req.content.as_deref(),
+        properties: req.properties.as_deref(),
+        is_display: None,
+        is_delete: None,
+        is_public: req.is_public,
+        clock: None,
+    };
+
+    let mut tx = state.pool.begin().await?;
+    check_nav_parent(&mut tx, &req.note_id, None, changes.parid.unwrap_or(ROOT_NAV_ID)).await?;
+    let mut nav = insert_nav(&mut tx, account_id, nav_id, &req.note_id, &database_id, &changes).await?;
+    let reordered = rebalance_siblings(&mut tx, account_id, &nav.parid).await?;
+    tx.commit().await?;
+
+    if let Some(order) = reordered.iter().find(|o| o.id == nav.id.to_string()) {
+        nav.same_deep_order = order.same_deep_order;
+    }
+
+    // Broadcast nav_created event to connected WebSocket clients
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(
+            state.pool.as_ref(),
+            WsEvent::NavCreated {
+                note_id: req.note_id.clone(),
+                database_id: database_id.clone(),
+                backend_ts,
+                nav: NavInfo::from(nav.clone()),
+            },
+        )
+        .await;
+    broadcast_reordered(&state, &req.note_id, &database_id, backend_ts, &reordered).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": nav.id.to_string(),
+        "nav": NavInfo::from(nav),
+        "reordered": reordered,
+        "backend-ts": backend_ts
+    })))
+}
+
+/// Most operations one nav batch may contain
+const MAX_BATCH_OPS: usize = 1000;
+
+/// Apply an ordered list of create, update, move and delete operations to
+/// the navs of one note in a single transaction. Either every operation is
+/// applied or, if one fails, none are.
+pub async fn batch_navs(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<BatchNavsRequest>,
+) -> Result<Json<Value>> {
+    let note_uuid = Uuid::parse_str(&req.note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+
+    if req.ops.len() > MAX_BATCH_OPS {
+        return Err(AppError::BadRequest(format!(
+            "A batch can have at most {} operations",
+            MAX_BATCH_OPS
+        )));
+    }
+
+    let database_id =
+        authorize_note(state.pool.as_ref(), account_id, note_uuid, Access::Write).await?;
+
+    let backend_ts = Utc::now().timestamp_millis();
+    let mut results = Vec::with_capacity(req.ops.len());
+    // Final state of every nav the batch touched, in first-touched order
+    let mut touched: Vec<HulunoteNav> = Vec::new();
+
+    let mut tx = state.pool.begin().await?;
+
+    for (index, op) in req.ops.iter().enumerate() {
+        let nav = apply_batch_op(&mut tx, account_id, &req.note_id, &database_id, op)
+            .await
+            .map_err(|e| match e {
+                AppError::BadRequest(msg) => AppError::BadRequest(format!("Operation {}: {}", index, msg)),
+                AppError::NotFound(msg) => AppError::NotFound(format!("Operation {}: {}", index, msg)),
+                e => e,
+            })?;
+
+        results.push(json!({
+            "index": index,
+            "op": op.op,
+            "id": nav.id.to_string(),
+            "success": true
+        }));
+
+        match touched.iter_mut().find(|n| n.id == nav.id) {
+            Some(existing) => *existing = nav,
+            None => touched.push(nav),
+        }
+    }
+
+    // Renumber wherever the batch left siblings too close together
+    let mut parids: Vec<String> = touched.iter().map(|nav| nav.parid.clone()).collect();
+    parids.sort();
+    parids.dedup();
+    let mut reordered = Vec::new();
+    for parid in &parids {
+        reordered.extend(rebalance_siblings(&mut tx, account_id, parid).await?);
+    }
+
+    tx.commit().await?;
+
+    for nav in touched.iter_mut() {
+        if let Some(order) = reordered.iter().find(|o| o.id == nav.id.to_string()) {
+            nav.same_deep_order = order.same_deep_order;
+        }
+    }
+
+    broadcast_reordered(&state, &req.note_id, &database_id, backend_ts, &reordered).await;
+
+    if !touched.is_empty() {
+        state
+            .ws_broadcaster
+            .broadcast_to_collaborators(
+                state.pool.as_ref(),
+                WsEvent::NavsBatch {
+                    note_id: req.note_id.clone(),
+                    database_id: database_id.clone(),
+                    backend_ts,
+                    navs: touched.into_iter().map(NavInfo::from).collect(),
+                },
+            )
+            .await;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "results": results,
+        "reordered": reordered,
+        "backend-ts": backend_ts
+    })))
+}
+
+async fn apply_batch_op(
+    conn: &mut PgConnection,
+    account_id: i64,
+    note_id: &str,
+    database_id: &str,
+    op: &BatchNavOp,
+) -> Result<HulunoteNav> {
+    let nav_id = op
+        .id
+        .as_deref()
+        .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid nav ID".to_string())))
+        .transpose()?;
+
+    if op.op == BatchNavOpKind::Create {
+        let nav_id = nav_id.unwrap_or_else(Uuid::new_v4);
+        let exists: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM hulunote_navs WHERE id = $1")
+            .bind(nav_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if exists.is_some() {
+            return Err(AppError::BadRequest("Nav already exists".to_string()));
+        }
+
+        check_nav_parent(conn, note_id, None, op.parid.as_deref().unwrap_or(ROOT_NAV_ID)).await?;
+
+        let changes = NavChanges {
+            parid: op.parid.as_deref(),
+            order: op.order,
+            content: op.content.as_deref(),
+            properties: op.properties.as_deref(),
+            is_display: op.is_display,
+            is_delete: None,
+            is_public: None,
+            clock: None,
+        };
+        return insert_nav(conn, account_id, nav_id, note_id, database_id, &changes).await;
+    }
+
+    let nav_id = nav_id.ok_or_else(|| AppError::BadRequest("Missing nav ID".to_string()))?;
+
+    let nav_note_id: Option<(String,)> = sqlx::query_as("SELECT note_id FROM hulunote_navs WHERE id = $1")
+        .bind(nav_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    match nav_note_id {
+        None => return Err(AppError::NotFound("Nav not found".to_string())),
+        Some((nav_note_id,)) if nav_note_id != note_id => {
+            return Err(AppError::BadRequest("Nav does not belong to this note".to_string()));
+        }
+        Some(_) => {}
+    }
+
+    let (changes, revision_op) = match op.op {
+        BatchNavOpKind::Update => (
+            NavChanges {
+                parid: op.parid.as_deref(),
+                order: op.order,
+                content: op.content.as_deref(),
+                properties: op.properties.as_deref(),
+                is_display: op.is_display,
+                is_delete: None,
+                is_public: None,
+                clock: None,
+            },
+            "update",
+        ),
+        BatchNavOpKind::Move => (
+            NavChanges {
+                parid: Some(
+                    op.parid
+                        .as_deref()
+                        .ok_or_else(|| AppError::BadRequest("Missing parid".to_string()))?,
+                ),
+                order: op.order,
+                ..NavChanges::default()
+            },
+            "update",
+        ),
+        BatchNavOpKind::Delete => (
+            NavChanges {
+                is_delete: Some(true),
+                ..NavChanges::default()
+            },
+            "delete",
+        ),
+        BatchNavOpKind::Create => unreachable!(),
+    };
+
+    if let Some(parid) = changes.parid {
+        check_nav_parent(conn, note_id, Some(nav_id), parid).await?;
+    }
+
+    update_nav(conn, account_id, nav_id, note_id, database_id, &changes, revision_op).await
+}
+
+/// Field changes to a nav. `None` leaves a field as it is, or gives it its
+/// default when creating.
+#[derive(Debug, Default)]
+pub(crate) struct NavChanges<'a> {
+    pub parid: Option<&'a str>,
+    pub order: Option<f32>,
+    pub content: Option<&'a str>,
+    pub properties: Option<&'a str>,
+    pub is_display: Option<bool>,
+    pub is_delete: Option<bool>,
+    pub is_public: Option<bool>,
+    /// backend-ts the changes were made at, or `None` for now
+    pub clock: Option<i64>,
+}
+
+impl NavChanges<'_> {
+    /// Columns this set of changes writes
+    pub(crate) fn fields(&self) -> Vec<&'static str> {
+        let set = [
+            ("parid", self.parid.is_some()),
+            ("same_deep_order", self.order.is_some()),
+            ("content", self.content.is_some()),
+            ("properties", self.properties.is_some()),
+            ("is_display", self.is_display.is_some()),
+            ("is_delete", self.is_delete.is_some()),
+            ("is_public", self.is_public.is_some()),
+        ];
+        set.into_iter().filter(|(_, set)| *set).map(|(field, _)| field).collect()
+    }
+
+    fn clock(&self) -> i64 {
+        self.clock.unwrap_or_else(|| Utc::now().timestamp_millis())
+    }
+}
+
+/// Nav columns that carry a last-writer-wins clock in `field_clocks`
+pub(crate) const NAV_FIELDS: [&str; 7] =
+    ["parid", "same_deep_order", "content", "properties", "is_display", "is_delete", "is_public"];
+
+/// A `field_clocks` value setting each of `fields` to `clock`
+pub(crate) fn field_clocks(fields: &[&str], clock: i64) -> Value {
+    Value::Object(fields.iter().map(|field| (field.to_string(), json!(clock))).collect())
+}
+
+/// Insert a nav, index its links and record its first revision
+pub(crate) async fn insert_nav(
+    conn: &mut PgConnection,
+    account_id: i64,
+    nav_id: Uuid,
+    note_id: &str,
+    database_id: &str,
+    changes: &NavChanges<'_>,
+) -> Result<HulunoteNav> {
     // parid, note_id, database_id are VARCHAR columns - use String
-    let parid = req.parid.as_deref().unwrap_or(ROOT_NAV_ID);
-    let content = req.content.as_deref().unwrap_or("");
-    let order = req.order.unwrap_or(0.0);
-    let properties = req.properties.as_deref().unwrap_or("");
+    let content = changes.content.unwrap_or("");
 
     let nav: HulunoteNav = sqlx::query_as(
         r#"
-        INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id, properties)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        RETURNING id, parid, same_deep_order, content, account_id, note_id, database_id,
+        INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id,
+                                   properties, is_display, is_public, last_account_id, field_clocks)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $5, $11)
+        RETURNING id, parid, same_deep_order, content, account_id, last_account_id, note_id, database_id,
                   is_display, is_public, is_delete, properties, extra_id, created_at, updated_at
         "#,
     )
     .bind(nav_id)           // id is UUID
-    .bind(parid)            // parid is VARCHAR
-    .bind(order)
+    .bind(changes.parid.unwrap_or(ROOT_NAV_ID))  // parid is VARCHAR
+    .bind(changes.order.unwrap_or(0.0))
     .bind(content)
     .bind(account_id)
-    .bind(&req.note_id)     // note_id is VARCHAR
-    .bind(&database_id)     // database_id is VARCHAR
-    .bind(properties)
-    .fetch_one(state.pool.as_ref())
+    .bind(note_id)          // note_id is VARCHAR
+    .bind(database_id)      // database_id is VARCHAR
+    .bind(changes.properties.unwrap_or(""))
+    .bind(changes.is_display.unwrap_or(true))
+    .bind(changes.is_public.unwrap_or(false))
+    .bind(field_clocks(&NAV_FIELDS, changes.clock()))
+    .fetch_one(&mut *conn)
     .await?;
 
-    // Broadcast nav_updated event to connected WebSocket clients
+    sync_nav_links(conn, nav.id, note_id, database_id, content).await?;
+    sync_nav_index(conn, nav.id, &nav.content, &nav.properties).await?;
+    record_nav_revision(conn, nav.id, account_id, "create").await?;
+
+    Ok(nav)
+}
+
+/// Apply field changes to a nav in one statement, re-index its links, tags
+/// and properties if they changed and record a revision
+pub(crate) async fn update_nav(
+    conn: &mut PgConnection,
+    account_id: i64,
+    nav_id: Uuid,
+    note_id: &str,
+    database_id: &str,
+    changes: &NavChanges<'_>,
+    op: &str,
+) -> Result<HulunoteNav> {
+    let nav: HulunoteNav = sqlx::query_as(
+        r#"
+        UPDATE hulunote_navs
+        SET parid = COALESCE($2, parid),
+            same_deep_order = COALESCE($3, same_deep_order),
+            content = COALESCE($4, content),
+            properties = COALESCE($5, properties),
+            is_display = COALESCE($6, is_display),
+            is_delete = COALESCE($7, is_delete),
+            is_public = COALESCE($8, is_public),
+            last_account_id = $9,
+            field_clocks = field_clocks || $10,
+            deleted_at = CASE
+                WHEN $7 IS NULL THEN deleted_at
+                WHEN $7 THEN COALESCE(deleted_at, NOW())
+            END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, parid, same_deep_order, content, account_id, last_account_id, note_id, database_id,
+                  is_display, is_public, is_delete, properties, extra_id, created_at, updated_at
+        "#,
+    )
+    .bind(nav_id)
+    .bind(changes.parid)
+    .bind(changes.order)
+    .bind(changes.content)
+    .bind(changes.properties)
+    .bind(changes.is_display)
+    .bind(changes.is_delete)
+    .bind(changes.is_public)
+    .bind(account_id)
+    .bind(field_clocks(&changes.fields(), changes.clock()))
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if let Some(content) = changes.content {
+        sync_nav_links(conn, nav_id, note_id, database_id, content).await?;
+    }
+    if changes.content.is_some() || changes.properties.is_some() {
+        sync_nav_index(conn, nav_id, &nav.content, &nav.properties).await?;
+    }
+    record_nav_revision(conn, nav_id, account_id, op).await?;
+
+    Ok(nav)
+}
+
+/// Smallest gap between two sibling orders, relative to their size, that
+/// still leaves room to insert between them. `same_deep_order` is an f32, so
+/// a few more halvings past this point and averaging makes siblings tie.
+const MIN_ORDER_GAP: f32 = 1.0 / 1024.0;
+
+/// Renumber the live children of `parid` to 1, 2, 3... when two of them tie
+/// or are too close to insert between. Returns the new order of every
+/// child, or nothing when the children were far enough apart.
+pub(crate) async fn rebalance_siblings(
+    conn: &mut PgConnection,
+    account_id: i64,
+    parid: &str,
+) -> Result<Vec<NavOrder>> {
+    // The root placeholder is the parent of every note's root nav
+    if parid == ROOT_NAV_ID {
+        return Ok(Vec::new());
+    }
+
+    let siblings: Vec<(Uuid, f32)> = sqlx::query_as(
+        r#"
+        SELECT id, same_deep_order FROM hulunote_navs
+        WHERE parid = $1 AND is_delete = false
+        ORDER BY same_deep_order, created_at, id
+        "#,
+    )
+    .bind(parid)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let crowded = siblings.windows(2).any(|pair| {
+        let (a, b) = (pair[0].1, pair[1].1);
+        b - a <= a.abs().max(b.abs()).max(1.0) * MIN_ORDER_GAP
+    });
+    if !crowded {
+        return Ok(Vec::new());
+    }
+
+    let ids: Vec<Uuid> = siblings.iter().map(|(id, _)| *id).collect();
+    let orders: Vec<f32> = (1..=siblings.len()).map(|i| i as f32).collect();
+
+    // Bump the order clocks too, so late WebSocket edits cannot put back
+    // the crowded orders
+    let changed: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        UPDATE hulunote_navs v
+        SET same_deep_order = o.same_deep_order, field_clocks = v.field_clocks || $3, updated_at = NOW()
+        FROM UNNEST($1::uuid[], $2::real[]) AS o(id, same_deep_order)
+        WHERE v.id = o.id AND v.same_deep_order <> o.same_deep_order
+        RETURNING v.id
+        "#,
+    )
+    .bind(&ids)
+    .bind(&orders)
+    .bind(field_clocks(&["same_deep_order"], Utc::now().timestamp_millis()))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    record_nav_revisions(conn, &changed, account_id, "update").await?;
+
+    Ok(ids
+        .into_iter()
+        .zip(orders)
+        .map(|(id, same_deep_order)| NavOrder {
+            id: id.to_string(),
+            same_deep_order,
+        })
+        .collect())
+}
+
+/// The event for changes applied to a nav: `nav_deleted` when it moved to
+/// the trash, otherwise `nav_updated` with the new value of each field
+pub(crate) fn nav_changed_event(nav: &HulunoteNav, changes: &NavChanges<'_>, backend_ts: i64) -> WsEvent {
+    let nav_id = nav.id.to_string();
+    if changes.is_delete == Some(true) {
+        return WsEvent::NavDeleted {
+            nav_id,
+            note_id: nav.note_id.clone(),
+            database_id: nav.database_id.clone(),
+            backend_ts,
+        };
+    }
+
+    WsEvent::NavUpdated {
+        nav_id,
+        note_id: nav.note_id.clone(),
+        database_id: nav.database_id.clone(),
+        backend_ts,
+        parid: changes.parid.map(|_| nav.parid.clone()),
+        same_deep_order: changes.order.map(|_| nav.same_deep_order),
+        content: changes.content.map(|_| nav.content.clone()),
+        properties: changes.properties.map(|_| nav.properties.clone()),
+        is_display: changes.is_display.map(|_| nav.is_display),
+        is_public: changes.is_public.map(|_| nav.is_public),
+        is_delete: changes.is_delete.map(|_| nav.is_delete),
+    }
+}
+
+/// Tell connected clients about renumbered siblings
+pub(crate) async fn broadcast_reordered(
+    state: &AppState,
+    note_id: &str,
+    database_id: &str,
+    backend_ts: i64,
+    navs: &[NavOrder],
+) {
+    if navs.is_empty() {
+        return;
+    }
+
     state
         .ws_broadcaster
-        .broadcast(
-            account_id,
-            WsEvent::NavUpdated {
-                nav_id: nav.id.to_string(),
-                note_id: req.note_id.clone(),
-                database_id: database_id.clone(),
-                content: content.to_string(),
+        .broadcast_to_collaborators(
+            state.pool.as_ref(),
+            WsEvent::NavsReordered {
+                note_id: note_id.to_string(),
+                database_id: database_id.to_string(),
+                backend_ts,
+                navs: navs.to_vec(),
             },
         )
         .await;
+}
 
-    Ok(Json(json!({
-        "success": true,
-        "id": nav.id.to_string(),
-        "nav": NavInfo::from(nav),
-        "backend-ts": backend_ts
-    })))
+/// Check that `parid` can be the parent of a nav in `note_id`: it is either
+/// the root placeholder or a nav of the same note, and it is not the nav
+/// itself or one of its descendants.
+pub(crate) async fn check_nav_parent(
+    conn: &mut PgConnection,
+    note_id: &str,
+    nav_id: Option<Uuid>,
+    parid: &str,
+) -> Result<()> {
+    if parid == ROOT_NAV_ID {
+        return Ok(());
+    }
+    let parent = Uuid::parse_str(parid)
+        .map_err(|_| AppError::BadRequest("Parent nav not found in this note".to_string()))?;
+
+    let ancestors: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parid, 1 AS depth
+            FROM hulunote_navs
+            WHERE id = $1 AND note_id = $2
+            UNION ALL
+            SELECT p.id, p.parid, a.depth + 1
+            FROM ancestors a
+            JOIN hulunote_navs p ON p.id = a.parid::uuid
+            WHERE a.depth < 1000
+        )
+        SELECT id FROM ancestors
+        "#,
+    )
+    .bind(parent)
+    .bind(note_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if ancestors.is_empty() {
+        return Err(AppError::BadRequest("Parent nav not found in this note".to_string()));
+    }
+    if nav_id.is_some_and(|id| ancestors.iter().any(|(a,)| *a == id)) {
+        return Err(AppError::BadRequest("A nav cannot be moved under itself".to_string()));
+    }
+
+    Ok(())
 }
 
 /// Get navs for a note
@@ -187,22 +670,27 @@ pub async fn get_note_navs(
     let note_uuid = Uuid::parse_str(&req.note_id)
         .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
 
-    // Check note access (simplified - just check if note exists)
-    let note_exists: Option<(i64,)> = sqlx::query_as(
-        "SELECT account_id FROM hulunote_notes WHERE id = $1"
-    )
-    .bind(note_uuid)
-    .fetch_optional(state.pool.as_ref())
-    .await?;
-
-    if note_exists.is_none() {
-        return Err(AppError::NotFound("Note not found".to_string()));
+    // Check note access
+    authorize_note(state.pool.as_ref(), account_id, note_uuid, Access::Read).await?;
+
+    if req.tree {
+        let (root_nav_id,): (String,) = sqlx::query_as("SELECT root_nav_id FROM hulunote_notes WHERE id = $1")
+            .bind(note_uuid)
+            .fetch_one(state.pool.as_ref())
+            .await?;
+        let root_uuid = Uuid::parse_str(&root_nav_id)
+            .map_err(|_| AppError::Internal("Invalid root nav ID".to_string()))?;
+
+        let tree = get_nav_tree(state.pool.as_ref(), root_uuid, req.depth, req.skip_hidden).await?;
+        return Ok(Json(json!({
+            "nav-tree": tree
+        })));
     }
 
     // navs.note_id is VARCHAR, so use String for the query
     let navs: Vec<HulunoteNav> = sqlx::query_as(
         r#"
-        SELECT id, parid, same_deep_order, content, account_id, note_id, database_id,
+        SELECT id, parid, same_deep_order, content, account_id, last_account_id, note_id, database_id,
                is_display, is_public, is_delete, properties, extra_id, created_at, updated_at
         FROM hulunote_navs
         WHERE note_id = $1 AND is_delete = false
@@ -220,6 +708,85 @@ pub async fn get_note_navs(
     })))
 }
 
+/// Get a nav and its descendants as a tree
+pub async fn get_nav_subtree(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetNavSubtreeRequest>,
+) -> Result<Json<Value>> {
+    let nav_uuid = Uuid::parse_str(&req.nav_id)
+        .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+
+    authorize_nav(state.pool.as_ref(), account_id, nav_uuid, Access::Read).await?;
+
+    let tree = get_nav_tree(state.pool.as_ref(), nav_uuid, req.depth, req.skip_hidden).await?;
+
+    Ok(Json(json!({
+        "nav-tree": tree
+    })))
+}
+
+/// Deepest tree a single request walks
+const MAX_TREE_DEPTH: i32 = 1000;
+
+/// Load the live navs under `nav_id`, at most `depth` levels down. With
+/// `skip_hidden`, collapsed navs (`is_display = false`) below the top one
+/// are returned without their children.
+pub(crate) async fn get_nav_tree(
+    pool: &sqlx::PgPool,
+    nav_id: Uuid,
+    depth: Option<i32>,
+    skip_hidden: bool,
+) -> Result<NavTree> {
+    let depth = depth.unwrap_or(MAX_TREE_DEPTH).clamp(0, MAX_TREE_DEPTH);
+
+    let navs: Vec<HulunoteNav> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT id, note_id, is_display, 0 AS depth
+            FROM hulunote_navs
+            WHERE id = $1 AND is_delete = false
+            UNION ALL
+            SELECT v.id, v.note_id, v.is_display, t.depth + 1
+            FROM tree t
+            JOIN hulunote_navs v ON v.parid = t.id::text AND v.note_id = t.note_id
+            WHERE v.is_delete = false
+            AND t.depth < $2
+            AND NOT ($3 AND t.depth > 0 AND NOT t.is_display)
+        )
+        SELECT v.id, v.parid, v.same_deep_order, v.content, v.account_id, v.last_account_id, v.note_id, v.database_id,
+               v.is_display, v.is_public, v.is_delete, v.properties, v.extra_id, v.created_at, v.updated_at
+        FROM tree t
+        JOIN hulunote_navs v ON v.id = t.id
+        ORDER BY t.depth, v.same_deep_order
+        "#,
+    )
+    .bind(nav_id)
+    .bind(depth)
+    .bind(skip_hidden)
+    .fetch_all(pool)
+    .await?;
+
+    let mut navs = navs.into_iter();
+    let root = navs.next().ok_or_else(|| AppError::NotFound("Nav not found".to_string()))?;
+
+    // Rows come ordered, so each parent's children are already in order
+    let mut children: HashMap<String, Vec<HulunoteNav>> = HashMap::new();
+    for nav in navs {
+        children.entry(nav.parid.clone()).or_default().push(nav);
+    }
+
+    fn build(nav: HulunoteNav, children: &mut HashMap<String, Vec<HulunoteNav>>) -> NavTree {
+        let kids = children.remove(&nav.id.to_string()).unwrap_or_default();
+        NavTree {
+            nav: NavInfo::from(nav),
+            children: kids.into_iter().map(|kid| build(kid, children)).collect(),
+        }
+    }
+
+    Ok(build(root, &mut children))
+}
+
 /// Get all navs in a database by page
 pub async fn get_all_navs_by_page(
     State(state): State<AppState>,
@@ -231,6 +798,7 @@ pub async fn get_all_navs_by_page(
         account_id,
         req.database_id.as_deref(),
         req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
     )
     .await?
     .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
@@ -261,7 +829,7 @@ pub async fn get_all_navs_by_page(
     // Get navs (database_id is VARCHAR)
     let navs: Vec<HulunoteNav> = sqlx::query_as(
         r#"
-        SELECT id, parid, same_deep_order, content, account_id, note_id, database_id,
+        SELECT id, parid, same_deep_order, content, account_id, last_account_id, note_id, database_id,
                is_display, is_public, is_delete, properties, extra_id, created_at, updated_at
         FROM hulunote_navs
         WHERE database_id = $1
@@ -298,6 +866,7 @@ pub async fn get_all_navs(
         account_id,
         req.database_id.as_deref(),
         req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
     )
     .await?
     .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
@@ -308,7 +877,7 @@ pub async fn get_all_navs(
 
     let navs: Vec<HulunoteNav> = sqlx::query_as(
         r#"
-        SELECT id, parid, same_deep_order, content, account_id, note_id, database_id,
+        SELECT id, parid, same_deep_order, content, account_id, last_account_id, note_id, database_id,
                is_display, is_public, is_delete, properties, extra_id, created_at, updated_at
         FROM hulunote_navs
         WHERE database_id = $1
@@ -329,3 +898,44 @@ pub async fn get_all_navs(
         "backend-ts": new_backend_ts
     })))
 }
+
+/// Get the ancestor path of each nav, from the top-level nav down to the
+/// nav's parent. The note's root nav is not part of the path.
+pub async fn get_nav_paths(
+    pool: &sqlx::PgPool,
+    nav_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<Value>>> {
+    let rows: Vec<(Uuid, Uuid, String)> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT v.id AS nav_id, p.id, p.parid, p.content, 1 AS depth
+            FROM hulunote_navs v
+            JOIN hulunote_navs p ON p.id = v.parid::uuid
+            WHERE v.id = ANY($1)
+            UNION ALL
+            SELECT a.nav_id, p.id, p.parid, p.content, a.depth + 1
+            FROM ancestors a
+            JOIN hulunote_navs p ON p.id = a.parid::uuid
+            WHERE a.depth < 1000
+        )
+        SELECT nav_id, id, content
+        FROM ancestors
+        WHERE parid <> $2
+        ORDER BY nav_id, depth DESC
+        "#,
+    )
+    .bind(nav_ids)
+    .bind(ROOT_NAV_ID)
+    .fetch_all(pool)
+    .await?;
+
+    let mut paths: HashMap<Uuid, Vec<Value>> = HashMap::new();
+    for (nav_id, id, content) in rows {
+        paths
+            .entry(nav_id)
+            .or_default()
+            .push(json!({ "id": id.to_string(), "content": content }));
+    }
+
+    Ok(paths)
+}
@@ -1,11 +1,14 @@
 use axum::{extract::State, Extension, Json};
+use chrono::Utc;
 use serde_json::{json, Value};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
 use crate::models::*;
 
-use super::{get_database_id, ws::WsEvent, AppState};
+use super::{
+    authorize_note, get_database_id, insert_nav, rewrite_page_references, ws::WsEvent, Access, AppState, NavChanges,
+};
 
 const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
 
@@ -20,6 +23,7 @@ pub async fn create_note(
         account_id,
         req.database_id.as_deref(),
         req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Write,
     )
     .await?
     .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
@@ -66,69 +70,38 @@ pub async fn create_note(
     }
 
     // Create the note (handle duplicate title gracefully)
-    let insert_result = sqlx::query_as::<_, HulunoteNote>(
-        r#"
-        INSERT INTO hulunote_notes (id, title, database_id, root_nav_id, account_id)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, title, database_id, root_nav_id, is_delete, is_public,
-                  is_shortcut, account_id, pv, created_at, updated_at
-        "#,
-    )
-    .bind(note_id)
-    .bind(&req.title)
-    .bind(database_id.to_string())
-    .bind(root_nav_id.to_string())
-    .bind(account_id)
-    .fetch_one(state.pool.as_ref())
-    .await;
-
-    let note: HulunoteNote = match insert_result {
-        Ok(note) => note,
-        Err(sqlx::Error::Database(ref db_err)) if db_err.code().as_deref() == Some("23505") => {
-            // Duplicate title — return the existing note instead of failing
-            let existing: HulunoteNote = sqlx::query_as(
-                r#"
-                SELECT id, title, database_id, root_nav_id, is_delete, is_public,
-                       is_shortcut, account_id, pv, created_at, updated_at
-                FROM hulunote_notes
-                WHERE database_id = $1 AND title = $2 AND is_delete = false
-                "#,
-            )
-            .bind(database_id.to_string())
-            .bind(&req.title)
-            .fetch_optional(state.pool.as_ref())
-            .await?
-            .ok_or_else(|| AppError::BadRequest(
-                format!("A note titled '{}' already exists but could not be retrieved", req.title)
-            ))?;
-            return Ok(Json(json!(NoteInfo::from(existing))));
-        }
-        Err(e) => return Err(e.into()),
+    let mut tx = state.pool.begin().await?;
+    let Some(note) = insert_note(&mut tx, account_id, note_id, root_nav_id, &database_id.to_string(), &req.title).await?
+    else {
+        // Duplicate title — return the existing note instead of failing
+        let existing: HulunoteNote = sqlx::query_as(
+            r#"
+            SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+                   is_shortcut, is_template, account_id, pv, created_at, updated_at
+            FROM hulunote_notes
+            WHERE database_id = $1 AND title = $2 AND is_delete = false
+            "#,
+        )
+        .bind(database_id.to_string())
+        .bind(&req.title)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(
+            format!("A note titled '{}' already exists but could not be retrieved", req.title)
+        ))?;
+        return Ok(Json(json!(NoteInfo::from(existing))));
     };
-
-    // Create the root nav for this note
-    sqlx::query(
-        r#"
-        INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id)
-        VALUES ($1, $2, 0, 'ROOT', $3, $4, $5)
-        "#,
-    )
-    .bind(root_nav_id)
-    .bind(ROOT_NAV_ID)
-    .bind(account_id)
-    .bind(note_id.to_string())
-    .bind(database_id.to_string())
-    .execute(state.pool.as_ref())
-    .await?;
+    tx.commit().await?;
 
     // Broadcast note_created event to connected WebSocket clients
     state
         .ws_broadcaster
-        .broadcast(
-            account_id,
+        .broadcast_to_collaborators(
+            state.pool.as_ref(),
             WsEvent::NoteCreated {
                 note_id: note_id.to_string(),
                 database_id: database_id.to_string(),
+                backend_ts: Utc::now().timestamp_millis(),
                 title: req.title.clone(),
                 root_nav_id: root_nav_id.to_string(),
             },
@@ -149,6 +122,7 @@ pub async fn get_note_list(
         account_id,
         req.database_id.as_deref(),
         req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
     )
     .await?
     .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
@@ -171,7 +145,7 @@ pub async fn get_note_list(
     let notes: Vec<HulunoteNote> = sqlx::query_as(
         r#"
         SELECT id, title, database_id, root_nav_id, is_delete, is_public, 
-               is_shortcut, account_id, pv, created_at, updated_at
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
         FROM hulunote_notes 
         WHERE database_id = $1 AND is_delete = false
         ORDER BY updated_at DESC
@@ -203,6 +177,7 @@ pub async fn get_all_note_list(
         account_id,
         req.database_id.as_deref(),
         req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
     )
     .await?
     .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
@@ -210,7 +185,7 @@ pub async fn get_all_note_list(
     let notes: Vec<HulunoteNote> = sqlx::query_as(
         r#"
         SELECT id, title, database_id, root_nav_id, is_delete, is_public, 
-               is_shortcut, account_id, pv, created_at, updated_at
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
         FROM hulunote_notes 
         WHERE database_id = $1 AND is_delete = false
         ORDER BY updated_at DESC
@@ -237,34 +212,46 @@ pub async fn update_note(
         .map_err(|_| AppError::BadRequest("Invalid note ID".to_string()))?;
 
     // Check ownership
-    let exists: Option<(i64,)> = sqlx::query_as(
-        "SELECT account_id FROM hulunote_notes WHERE id = $1"
-    )
-    .bind(note_uuid)
-    .fetch_optional(state.pool.as_ref())
-    .await?;
+    let database_id =
+        authorize_note(state.pool.as_ref(), account_id, note_uuid, Access::Write).await?;
 
-    match exists {
-        Some((owner_id,)) if owner_id != account_id => {
-            return Err(AppError::PermissionDenied("Cannot update other's note".to_string()));
-        }
-        None => {
-            return Err(AppError::NotFound("Note not found".to_string()));
-        }
-        _ => {}
-    }
+    let backend_ts = Utc::now().timestamp_millis();
 
     // Build update
+    let mut rewritten_navs = Vec::new();
     if let Some(title) = &req.title {
+        let mut tx = state.pool.begin().await?;
+
+        let (old_title,): (String,) =
+            sqlx::query_as("SELECT title FROM hulunote_notes WHERE id = $1 FOR UPDATE")
+                .bind(note_uuid)
+                .fetch_one(&mut *tx)
+                .await?;
+
         sqlx::query("UPDATE hulunote_notes SET title = $1, updated_at = NOW() WHERE id = $2")
             .bind(title)
             .bind(note_uuid)
-            .execute(state.pool.as_ref())
+            .execute(&mut *tx)
             .await?;
+
+        // Point [[Old Title]] references at the new title
+        if req.rewrite_references.unwrap_or(false) && old_title != *title {
+            rewritten_navs = rewrite_page_references(&mut tx, account_id, &database_id, &old_title, title).await?;
+        }
+
+        tx.commit().await?;
     }
 
     if let Some(is_delete) = req.is_delete {
-        sqlx::query("UPDATE hulunote_notes SET is_delete = $1, updated_at = NOW() WHERE id = $2")
+        sqlx::query(
+            r#"
+            UPDATE hulunote_notes
+            SET is_delete = $1,
+                deleted_at = CASE WHEN $1 THEN COALESCE(deleted_at, NOW()) END,
+                updated_at = NOW()
+            WHERE id = $2
+            "#,
+        )
             .bind(is_delete)
             .bind(note_uuid)
             .execute(state.pool.as_ref())
@@ -287,7 +274,48 @@ pub async fn update_note(
             .await?;
     }
 
-    Ok(Json(json!({"success": true})))
+    if let Some(is_template) = req.is_template {
+        sqlx::query("UPDATE hulunote_notes SET is_template = $1, updated_at = NOW() WHERE id = $2")
+            .bind(is_template)
+            .bind(note_uuid)
+            .execute(state.pool.as_ref())
+            .await?;
+    }
+
+    let rewritten_count = rewritten_navs.len();
+    let changed = req.title.is_some()
+        || req.is_public.is_some()
+        || req.is_shortcut.is_some()
+        || req.is_template.is_some()
+        || req.is_delete.is_some();
+    let event = if req.is_delete == Some(true) {
+        WsEvent::NoteDeleted {
+            note_id: req.note_id.clone(),
+            database_id,
+            backend_ts,
+        }
+    } else {
+        WsEvent::NoteUpdated {
+            note_id: req.note_id.clone(),
+            database_id,
+            backend_ts,
+            title: req.title.clone(),
+            is_public: req.is_public,
+            is_shortcut: req.is_shortcut,
+            is_template: req.is_template,
+            is_delete: req.is_delete,
+            rewritten_navs,
+        }
+    };
+    if changed {
+        state.ws_broadcaster.broadcast_to_collaborators(state.pool.as_ref(), event).await;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "rewritten-navs": rewritten_count,
+        "backend-ts": backend_ts
+    })))
 }
 
 /// Get shortcut notes
@@ -301,6 +329,7 @@ pub async fn get_shortcuts_note_list(
         account_id,
         req.database_id.as_deref(),
         req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
     )
     .await?
     .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
@@ -308,7 +337,7 @@ pub async fn get_shortcuts_note_list(
     let notes: Vec<HulunoteNote> = sqlx::query_as(
         r#"
         SELECT id, title, database_id, root_nav_id, is_delete, is_public, 
-               is_shortcut, account_id, pv, created_at, updated_at
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
         FROM hulunote_notes 
         WHERE database_id = $1 AND is_delete = false AND is_shortcut = true
         ORDER BY updated_at DESC
@@ -324,3 +353,44 @@ pub async fn get_shortcuts_note_list(
         "note-list": note_list
     })))
 }
+
+/// Create a note with its root nav. Returns `None` when the database already
+/// has a note with this title, live or in the trash.
+pub(crate) async fn insert_note(
+    conn: &mut sqlx::PgConnection,
+    account_id: i64,
+    note_id: Uuid,
+    root_nav_id: Uuid,
+    database_id: &str,
+    title: &str,
+) -> Result<Option<HulunoteNote>> {
+    let note: Option<HulunoteNote> = sqlx::query_as(
+        r#"
+        INSERT INTO hulunote_notes (id, title, database_id, root_nav_id, account_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (database_id, title) DO NOTHING
+        RETURNING id, title, database_id, root_nav_id, is_delete, is_public,
+                  is_shortcut, is_template, account_id, pv, created_at, updated_at
+        "#,
+    )
+    .bind(note_id)
+    .bind(title)
+    .bind(database_id)
+    .bind(root_nav_id.to_string())
+    .bind(account_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if note.is_none() {
+        return Ok(None);
+    }
+
+    let root = NavChanges {
+        parid: Some(ROOT_NAV_ID),
+        order: Some(0.0),
+        content: Some("ROOT"),
+        ..NavChanges::default()
+    };
+    insert_nav(conn, account_id, root_nav_id, &note_id.to_string(), database_id, &root).await?;
+
+    Ok(note)
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    children_by_parent, format_property_line, outline_to_note, parse_property_line, property_value,
+    OutlineItem, COLLAPSED_PROPERTY, RAW_PROPERTIES_PROPERTY,
+};
+
+/// Escape text for use inside a double-quoted XML attribute.
+/// Line breaks are written as character references so they survive
+/// attribute value normalization.
+fn escape_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            '\t' => out.push_str("&#9;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether a property key can be written as an XML attribute name
+fn is_attribute_name(key: &str) -> bool {
+    key != "text"
+        && key.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Turn nav properties into OPML attributes. `key:: value` lines become
+/// one attribute each; anything else is kept verbatim in `properties`.
+fn property_attributes(properties: &str) -> Vec<(String, String)> {
+    let lines: Vec<&str> = properties
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    let pairs: Option<Vec<(String, String)>> = lines
+        .iter()
+        .map(|line| match parse_property_line(line) {
+            Some((key, value))
+                if is_attribute_name(key)
+                    && key != COLLAPSED_PROPERTY
+                    && key != RAW_PROPERTIES_PROPERTY =>
+            {
+                Some((key.to_string(), property_value(value)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    pairs.unwrap_or_else(|| vec![(RAW_PROPERTIES_PROPERTY.to_string(), properties.to_string())])
+}
+
+/// Write a note as an OPML 2.0 document, one `<outline>` per nav.
+///
+/// Properties become attributes (so `_note` and `_complete` round-trip)
+/// and collapsed navs get `collapsed="true"`.
+pub fn note_to_opml(note: &ImportNoteJson) -> String {
+    let children = children_by_parent(&note.navs);
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n");
+    out.push_str(&format!("    <title>{}</title>\n", escape_attribute(&note.note.title)));
+    out.push_str("  </head>\n");
+    out.push_str("  <body>\n");
+    write_outlines(&children, &note.note.root_nav_id, 2, &mut out);
+    out.push_str("  </body>\n");
+    out.push_str("</opml>\n");
+    out
+}
+
+fn write_outlines(children: &HashMap<&str, Vec<&ImportNavData>>, parid: &str, depth: usize, out: &mut String) {
+    let Some(navs) = children.get(parid) else { return };
+    let indent = "  ".repeat(depth);
+
+    for nav in navs {
+        out.push_str(&format!("{}<outline text=\"{}\"", indent, escape_attribute(&nav.content)));
+        if nav.is_display == Some(false) {
+            out.push_str(&format!(" {}=\"true\"", COLLAPSED_PROPERTY));
+        }
+        if let Some(properties) = nav.properties.as_deref().filter(|p| !p.is_empty()) {
+            for (key, value) in property_attributes(properties) {
+                out.push_str(&format!(" {}=\"{}\"", key, escape_attribute(&value)));
+            }
+        }
+
+        if children.contains_key(nav.id.as_str()) {
+            out.push_str(">\n");
+            write_outlines(children, &nav.id, depth + 1, out);
+            out.push_str(&format!("{}</outline>\n", indent));
+        } else {
+            out.push_str("/>\n");
+        }
+    }
+}
+
+/// Build a note from an OPML document. `<outline>` elements under `<body>`
+/// become navs; every attribute other than `text` is kept as a
+/// `key:: value` property. The title comes from `<head><title>`, falling
+/// back to `default_title`.
+pub fn opml_to_note(default_title: &str, text: &str) -> Result<ImportNoteJson> {
+    let doc = roxmltree::Document::parse(text)
+        .map_err(|e| AppError::BadRequest(format!("Invalid OPML: {}", e)))?;
+
+    let root = doc.root_element();
+    if !root.has_tag_name("opml") {
+        return Err(AppError::BadRequest("Invalid OPML: missing <opml> element".to_string()));
+    }
+
+    let title = root
+        .children()
+        .find(|n| n.has_tag_name("head"))
+        .and_then(|head| head.children().find(|n| n.has_tag_name("title")))
+        .and_then(|title| title.text())
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .unwrap_or(default_title);
+
+    let body = root
+        .children()
+        .find(|n| n.has_tag_name("body"))
+        .ok_or_else(|| AppError::BadRequest("Invalid OPML: missing <body> element".to_string()))?;
+
+    let mut items = Vec::new();
+    read_outlines(body, None, &mut items);
+    Ok(outline_to_note(title, items))
+}
+
+fn read_outlines(parent_node: roxmltree::Node, parent: Option<usize>, items: &mut Vec<OutlineItem>) {
+    for node in parent_node.children().filter(|n| n.has_tag_name("outline")) {
+        let mut properties = Vec::new();
+        let mut is_display = true;
+        for attribute in node.attributes() {
+            match attribute.name() {
+                "text" => {}
+                COLLAPSED_PROPERTY => is_display = attribute.value() != "true",
+                RAW_PROPERTIES_PROPERTY => properties.push(attribute.value().to_string()),
+                key => properties.push(format_property_line(key, attribute.value())),
+            }
+        }
+
+        items.push(OutlineItem {
+            parent,
+            content: node.attribute("text").unwrap_or("").to_string(),
+            properties: properties.join("\n"),
+            is_display,
+            extra_id: None,
+        });
+        let index = items.len() - 1;
+        read_outlines(node, Some(index), items);
+    }
+}
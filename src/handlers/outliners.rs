@@ -0,0 +1,207 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    format_property_line, normalize_daily_title, outline_to_note, parse_property_line, OutlineItem,
+    COLLAPSED_PROPERTY,
+};
+
+/// Replace the inside of every innermost `open ... close` pair for which
+/// `replace` returns a new value
+fn replace_delimited(
+    content: &str,
+    open: &str,
+    close: &str,
+    replace: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find(open) {
+        let inner_start = start + open.len();
+        let Some(len) = rest[inner_start..].find(close) else { break };
+        let inner = &rest[inner_start..inner_start + len];
+
+        // `[[a [[b]]]]`: continue from the innermost opening
+        if let Some(nested) = inner.rfind(open) {
+            out.push_str(&rest[..inner_start + nested]);
+            rest = &rest[inner_start + nested..];
+            continue;
+        }
+
+        let end = inner_start + len + close.len();
+        match replace(inner) {
+            Some(new) => {
+                out.push_str(&rest[..inner_start]);
+                out.push_str(&new);
+                out.push_str(close);
+            }
+            None => out.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Point references inside the imported notes at their new targets:
+/// `((uid))` block references to the new nav ids, and `[[Title]]` page
+/// references to renamed daily notes
+fn rewrite_references(notes: &mut [ImportNoteJson], renamed: &HashMap<String, String>) {
+    let nav_ids: HashMap<String, String> = notes
+        .iter()
+        .flat_map(|note| &note.navs)
+        .filter_map(|nav| Some((nav.extra_id.clone()?, nav.id.clone())))
+        .collect();
+
+    for nav in notes.iter_mut().flat_map(|note| &mut note.navs) {
+        let content = replace_delimited(&nav.content, "((", "))", |uid| nav_ids.get(uid).cloned());
+        nav.content = replace_delimited(&content, "[[", "]]", |title| renamed.get(title).cloned());
+    }
+}
+
+/// Convert pages into notes, normalizing daily-note titles
+fn pages_to_notes(pages: Vec<(String, Vec<OutlineItem>)>) -> Vec<ImportNoteJson> {
+    let mut renamed = HashMap::new();
+    let mut notes: Vec<ImportNoteJson> = pages
+        .into_iter()
+        .map(|(title, items)| {
+            let normalized = normalize_daily_title(&title);
+            if normalized != title {
+                renamed.insert(title, normalized.clone());
+            }
+            outline_to_note(&normalized, items)
+        })
+        .collect();
+
+    rewrite_references(&mut notes, &renamed);
+    notes
+}
+
+fn push_roam_blocks(blocks: Vec<RoamBlock>, parent: Option<usize>, items: &mut Vec<OutlineItem>) {
+    for block in blocks {
+        items.push(OutlineItem {
+            parent,
+            content: block.string,
+            properties: String::new(),
+            is_display: block.open.unwrap_or(true),
+            extra_id: block.uid,
+        });
+        let index = items.len() - 1;
+        push_roam_blocks(block.children, Some(index), items);
+    }
+}
+
+/// Convert a Roam Research JSON export (`[{title, children: [{string, uid, children}]}]`)
+/// into notes. Block uids are kept as `extra-id`.
+pub fn roam_to_notes(pages: Vec<RoamPage>) -> Vec<ImportNoteJson> {
+    let pages = pages
+        .into_iter()
+        .map(|page| {
+            let mut items = Vec::new();
+            push_roam_blocks(page.children, None, &mut items);
+            (page.title, items)
+        })
+        .collect();
+    pages_to_notes(pages)
+}
+
+/// Text of a Logseq property value
+fn logseq_property_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(logseq_property_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn push_logseq_blocks(blocks: Vec<LogseqBlock>, parent: Option<usize>, items: &mut Vec<OutlineItem>) {
+    for block in blocks {
+        let is_property = |key: &str| {
+            key == "id" || block.properties.keys().any(|k| k.eq_ignore_ascii_case(key))
+        };
+
+        // Logseq repeats block properties as `key:: value` lines in the content
+        let content = block
+            .content
+            .lines()
+            .filter(|line| !parse_property_line(line).is_some_and(|(key, _)| is_property(key)))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut is_display = true;
+        let mut properties = Vec::new();
+        for (key, value) in &block.properties {
+            match key.as_str() {
+                "id" => {}
+                COLLAPSED_PROPERTY => is_display = value != &Value::Bool(true),
+                _ => properties.push(format_property_line(key, &logseq_property_value(value))),
+            }
+        }
+
+        items.push(OutlineItem {
+            parent,
+            content,
+            properties: properties.join("\n"),
+            is_display,
+            extra_id: block.id,
+        });
+        let index = items.len() - 1;
+        push_logseq_blocks(block.children, Some(index), items);
+    }
+}
+
+/// Convert a Logseq JSON export into notes, one per top-level page block.
+/// Block ids are kept as `extra-id`.
+pub fn logseq_to_notes(export: LogseqExport) -> Vec<ImportNoteJson> {
+    let pages = export
+        .blocks
+        .into_iter()
+        .map(|page| {
+            let title = page
+                .original_name
+                .or(page.page_name)
+                .or(page.title)
+                .unwrap_or_else(|| page.content.clone());
+            let mut items = Vec::new();
+            push_logseq_blocks(page.children, None, &mut items);
+            (title, items)
+        })
+        .collect();
+    pages_to_notes(pages)
+}
+
+/// Parse a JSON file in any supported shape: a Hulunote note, a Roam
+/// Research export or a Logseq export
+pub fn json_to_notes(data: &[u8]) -> Result<Vec<ImportNoteJson>> {
+    let value: Value = serde_json::from_slice(data)
+        .map_err(|e| AppError::BadRequest(format!("Invalid JSON: {}", e)))?;
+
+    let invalid = |format: &str, e: serde_json::Error| {
+        AppError::BadRequest(format!("Invalid {} export: {}", format, e))
+    };
+
+    if value.is_array() {
+        let pages: Vec<RoamPage> = serde_json::from_value(value).map_err(|e| invalid("Roam", e))?;
+        Ok(roam_to_notes(pages))
+    } else if value.get("blocks").is_some() {
+        let export: LogseqExport = serde_json::from_value(value).map_err(|e| invalid("Logseq", e))?;
+        Ok(logseq_to_notes(export))
+    } else if value.get("note").is_some() {
+        let note: ImportNoteJson = serde_json::from_value(value).map_err(|e| invalid("Hulunote", e))?;
+        Ok(vec![note])
+    } else {
+        Err(AppError::BadRequest(
+            "Unrecognized JSON format: expected a Hulunote note, a Roam export or a Logseq export"
+                .to_string(),
+        ))
+    }
+}
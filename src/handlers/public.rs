@@ -0,0 +1,374 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use bcrypt::verify;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{authorize_share_target, get_nav_tree, AppState};
+
+/// Header carrying the password of a protected share link. Browsers opening
+/// the HTML view send it as the `password` query parameter instead.
+const SHARE_PASSWORD_HEADER: &str = "x-share-password";
+
+/// Escape text for use in HTML element content. Line breaks become `<br>`
+/// so multi-line navs keep their shape.
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '\n' => out.push_str("<br>"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Wrap a rendered body in a minimal standalone page
+fn html_page(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape_html(title),
+        body = body
+    ))
+}
+
+/// Render navs as nested lists
+fn render_navs(navs: &[PublicNav], out: &mut String) {
+    if navs.is_empty() {
+        return;
+    }
+    out.push_str("<ul>\n");
+    for nav in navs {
+        out.push_str("<li>");
+        out.push_str(&escape_html(&nav.content));
+        out.push('\n');
+        render_navs(&nav.children, out);
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+fn parse_id(id: &str, what: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|_| AppError::NotFound(format!("{} not found", what)))
+}
+
+/// What a share link opens
+enum Shared {
+    Note { note: HulunoteNote, navs: Vec<PublicNav> },
+    Nav { note: HulunoteNote, nav: PublicNav },
+    Database { database: HulunoteDatabase, notes: Vec<HulunoteNote> },
+}
+
+/// Find a live share link and check its password. Revoked, expired and
+/// unknown tokens are all reported as not found.
+async fn resolve_link(pool: &sqlx::PgPool, token: &str, password: Option<&str>) -> Result<ShareLink> {
+    let link: ShareLink = sqlx::query_as(
+        r#"
+        SELECT id, token, account_id, target_type, target_id, password_hash, expires_at,
+               view_count, last_viewed_at, revoked_at, created_at
+        FROM hulunote_share_links
+        WHERE token = $1 AND revoked_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
+
+    // Links go offline when their creator loses the right to share the target
+    let target = match link.target_type.as_str() {
+        "note" => ShareTarget::Note,
+        "nav" => ShareTarget::Nav,
+        _ => ShareTarget::Database,
+    };
+    let target_id = parse_id(&link.target_id, "Share link")?;
+    if authorize_share_target(pool, link.account_id, target, target_id).await.is_err() {
+        return Err(AppError::NotFound("Share link not found".to_string()));
+    }
+
+    if let Some(password_hash) = &link.password_hash {
+        match password {
+            None => return Err(AppError::Auth("Password required".to_string())),
+            Some(password) if !verify(password, password_hash)? => {
+                return Err(AppError::Auth("Wrong password".to_string()))
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok(link)
+}
+
+/// Count a view of a live note in a live database, and return it. With
+/// `database_id`, the note must also belong to that database.
+async fn view_note(pool: &sqlx::PgPool, note_id: Uuid, database_id: Option<&str>) -> Result<HulunoteNote> {
+    sqlx::query_as(
+        r#"
+        UPDATE hulunote_notes n
+        SET pv = n.pv + 1
+        FROM hulunote_databases d
+        WHERE n.id = $1
+        AND d.id::text = n.database_id
+        AND n.is_delete = false AND d.is_delete = false
+        AND ($2::text IS NULL OR n.database_id = $2)
+        RETURNING n.id, n.title, n.database_id, n.root_nav_id, n.is_delete, n.is_public,
+                  n.is_shortcut, n.is_template, n.account_id, n.pv, n.created_at, n.updated_at
+        "#,
+    )
+    .bind(note_id)
+    .bind(database_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Note not found".to_string()))
+}
+
+/// A note and the navs under its root
+async fn shared_note(pool: &sqlx::PgPool, note_id: Uuid, database_id: Option<&str>) -> Result<Shared> {
+    let note = view_note(pool, note_id, database_id).await?;
+    let root = parse_id(&note.root_nav_id, "Note")?;
+    let tree = get_nav_tree(pool, root, None, false).await?;
+    Ok(Shared::Note {
+        note,
+        navs: tree.children.into_iter().map(PublicNav::from).collect(),
+    })
+}
+
+/// A live nav and its subtree; viewing it counts as a view of its note
+async fn shared_nav(pool: &sqlx::PgPool, nav_id: Uuid) -> Result<Shared> {
+    let row: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT n.id
+        FROM hulunote_navs v
+        JOIN hulunote_notes n ON n.id::text = v.note_id
+        WHERE v.id = $1 AND v.is_delete = false
+        "#,
+    )
+    .bind(nav_id)
+    .fetch_optional(pool)
+    .await?;
+    let (note_id,) = row.ok_or_else(|| AppError::NotFound("Nav not found".to_string()))?;
+
+    let note = view_note(pool, note_id, None).await?;
+    let tree = get_nav_tree(pool, nav_id, None, false).await?;
+    Ok(Shared::Nav { note, nav: PublicNav::from(tree) })
+}
+
+/// A live database and its live notes
+async fn shared_database(pool: &sqlx::PgPool, database_id: Uuid) -> Result<Shared> {
+    let database: HulunoteDatabase = sqlx::query_as(
+        r#"
+        SELECT id, name, description, is_delete, is_public, is_offline, is_default,
+               account_id, setting, created_at, updated_at
+        FROM hulunote_databases
+        WHERE id = $1 AND is_delete = false
+        "#,
+    )
+    .bind(database_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Database not found".to_string()))?;
+
+    let notes: Vec<HulunoteNote> = sqlx::query_as(
+        r#"
+        SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
+        FROM hulunote_notes
+        WHERE database_id = $1 AND is_delete = false
+        ORDER BY title
+        "#,
+    )
+    .bind(database_id.to_string())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Shared::Database { database, notes })
+}
+
+/// Open what a share link points to and count the view. `note_id` opens a
+/// note inside a shared database.
+async fn open_link(
+    pool: &sqlx::PgPool,
+    token: &str,
+    password: Option<&str>,
+    note_id: Option<&str>,
+) -> Result<(ShareLink, Shared)> {
+    let link = resolve_link(pool, token, password).await?;
+
+    let shared = match (link.target_type.as_str(), note_id) {
+        ("note", None) => shared_note(pool, parse_id(&link.target_id, "Note")?, None).await?,
+        ("nav", None) => shared_nav(pool, parse_id(&link.target_id, "Nav")?).await?,
+        ("database", None) => shared_database(pool, parse_id(&link.target_id, "Database")?).await?,
+        ("database", Some(note_id)) => {
+            shared_note(pool, parse_id(note_id, "Note")?, Some(&link.target_id)).await?
+        }
+        _ => return Err(AppError::NotFound("Note not found".to_string())),
+    };
+
+    sqlx::query(
+        "UPDATE hulunote_share_links SET view_count = view_count + 1, last_viewed_at = NOW() WHERE id = $1",
+    )
+    .bind(link.id)
+    .execute(pool)
+    .await?;
+
+    Ok((link, shared))
+}
+
+fn share_password(headers: &HeaderMap, query: &SharePasswordQuery) -> Option<String> {
+    headers
+        .get(SHARE_PASSWORD_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| query.password.clone())
+}
+
+/// The parts of a note shown to visitors
+fn note_summary(note: &HulunoteNote) -> Value {
+    json!({
+        "id": note.id.to_string(),
+        "title": note.title,
+        "database-id": note.database_id,
+        "pv": note.pv,
+        "updated-at": note.updated_at.to_rfc3339()
+    })
+}
+
+fn shared_json(link: &ShareLink, shared: Shared) -> Value {
+    let share = json!({
+        "target-type": link.target_type,
+        "expires-at": link.expires_at.map(|at| at.to_rfc3339())
+    });
+    match shared {
+        Shared::Note { note, navs } => json!({ "share": share, "note": note_summary(&note), "navs": navs }),
+        Shared::Nav { note, nav } => json!({ "share": share, "note": note_summary(&note), "nav": nav }),
+        Shared::Database { database, notes } => json!({
+            "share": share,
+            "database": {
+                "id": database.id.to_string(),
+                "name": database.name,
+                "description": database.description
+            },
+            "notes": notes.iter().map(note_summary).collect::<Vec<_>>()
+        }),
+    }
+}
+
+fn updated_line(updated_at: DateTime<Utc>) -> String {
+    format!("<p><small>Updated {}</small></p>\n", updated_at.format("%Y-%m-%d"))
+}
+
+fn shared_html(link: &ShareLink, shared: Shared) -> Html<String> {
+    let mut body = String::new();
+    match shared {
+        Shared::Note { note, navs } => {
+            render_navs(&navs, &mut body);
+            body.push_str(&updated_line(note.updated_at));
+            html_page(&note.title, &body)
+        }
+        Shared::Nav { note, nav } => {
+            render_navs(std::slice::from_ref(&nav), &mut body);
+            body.push_str(&updated_line(note.updated_at));
+            html_page(&note.title, &body)
+        }
+        Shared::Database { database, notes } => {
+            if let Some(description) = database.description.as_deref().filter(|d| !d.is_empty()) {
+                body.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+            }
+            body.push_str("<ul>\n");
+            for note in &notes {
+                body.push_str(&format!(
+                    "<li><a href=\"/public/share/{}/note/{}/html\">{}</a></li>\n",
+                    link.token,
+                    note.id,
+                    escape_html(&note.title)
+                ));
+            }
+            body.push_str("</ul>\n");
+            html_page(&database.name, &body)
+        }
+    }
+}
+
+/// Render a shared page, or a password form when the link asks for one
+async fn render_shared_html(
+    pool: &sqlx::PgPool,
+    token: &str,
+    password: Option<&str>,
+    note_id: Option<&str>,
+) -> Result<Response> {
+    match open_link(pool, token, password, note_id).await {
+        Ok((link, shared)) => Ok(shared_html(&link, shared).into_response()),
+        Err(AppError::Auth(message)) => {
+            let form = format!(
+                "<p>{}</p>\n<form method=\"get\">\n<input type=\"password\" name=\"password\" autofocus>\n\
+                 <button type=\"submit\">Open</button>\n</form>\n",
+                escape_html(&message)
+            );
+            Ok((StatusCode::UNAUTHORIZED, html_page("Password required", &form)).into_response())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Read what a share link points to, without logging in
+pub async fn get_shared(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<SharePasswordQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Value>> {
+    let password = share_password(&headers, &query);
+    let (link, shared) = open_link(state.pool.as_ref(), &token, password.as_deref(), None).await?;
+    Ok(Json(shared_json(&link, shared)))
+}
+
+/// What a share link points to, rendered as an HTML page
+pub async fn get_shared_html(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<SharePasswordQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let password = share_password(&headers, &query);
+    render_shared_html(state.pool.as_ref(), &token, password.as_deref(), None).await
+}
+
+/// Read a note inside a database shared by a link
+pub async fn get_shared_note(
+    State(state): State<AppState>,
+    Path((token, note_id)): Path<(String, String)>,
+    Query(query): Query<SharePasswordQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Value>> {
+    let password = share_password(&headers, &query);
+    let (link, shared) = open_link(state.pool.as_ref(), &token, password.as_deref(), Some(&note_id)).await?;
+    Ok(Json(shared_json(&link, shared)))
+}
+
+/// A note inside a database shared by a link, rendered as an HTML page
+pub async fn get_shared_note_html(
+    State(state): State<AppState>,
+    Path((token, note_id)): Path<(String, String)>,
+    Query(query): Query<SharePasswordQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let password = share_password(&headers, &query);
+    render_shared_html(state.pool.as_ref(), &token, password.as_deref(), Some(&note_id)).await
+}
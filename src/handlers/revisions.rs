@@ -0,0 +1,469 @@
+use axum::{extract::State, Extension, Json};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_nav, authorize_note, fetch_navs, field_clocks, nav_batches, sync_nav_links, sync_nav_index, Access,
+    AppState, NAV_FIELDS,
+};
+
+const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+const REVISION_COLUMNS: &str = "id, nav_id, note_id, database_id, account_id, op, parid, \
+     same_deep_order, content, properties, is_display, is_delete, created_at";
+
+/// Record the current state of a nav as a new revision, unless it is
+/// unchanged since the nav's last revision
+pub async fn record_nav_revision(
+    conn: &mut sqlx::PgConnection,
+    nav_id: Uuid,
+    account_id: i64,
+    op: &str,
+) -> Result<()> {
+    record_nav_revisions(conn, &[nav_id], account_id, op).await
+}
+
+/// Record revisions for many navs changed by one operation
+pub async fn record_nav_revisions(
+    conn: &mut sqlx::PgConnection,
+    nav_ids: &[Uuid],
+    account_id: i64,
+    op: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO hulunote_nav_revisions (nav_id, note_id, database_id, account_id, op, parid,
+                                            same_deep_order, content, properties, is_display, is_delete)
+        SELECT v.id, v.note_id, v.database_id, $2, $3, v.parid,
+               v.same_deep_order, v.content, v.properties, v.is_display, v.is_delete
+        FROM hulunote_navs v
+        WHERE v.id = ANY($1)
+        AND NOT EXISTS (
+            SELECT 1
+            FROM (
+                SELECT * FROM hulunote_nav_revisions r
+                WHERE r.nav_id = v.id
+                ORDER BY r.id DESC
+                LIMIT 1
+            ) last
+            WHERE (last.note_id, last.database_id, last.parid, last.same_deep_order,
+                   last.content, last.properties, last.is_display, last.is_delete)
+                = (v.note_id, v.database_id, v.parid, v.same_deep_order,
+                   v.content, v.properties, v.is_display, v.is_delete)
+        )
+        "#,
+    )
+    .bind(nav_ids)
+    .bind(account_id)
+    .bind(op)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Fields that differ between two states of a nav
+fn changed_fields(before: &NavRevision, after: &NavRevision) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if before.note_id != after.note_id {
+        fields.push("note-id");
+    }
+    if before.content != after.content {
+        fields.push("content");
+    }
+    if before.parid != after.parid {
+        fields.push("parid");
+    }
+    if before.same_deep_order != after.same_deep_order {
+        fields.push("same-deep-order");
+    }
+    if before.properties != after.properties {
+        fields.push("properties");
+    }
+    if before.is_display != after.is_display {
+        fields.push("is-display");
+    }
+    if before.is_delete != after.is_delete {
+        fields.push("is-delete");
+    }
+    fields
+}
+
+/// Convert a `backend-ts` style timestamp (milliseconds) to a time
+fn from_millis(ms: i64) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(ms)
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid timestamp: {}", ms)))
+}
+
+/// The latest revision of each nav of a note at or before `at`
+async fn note_state_at(
+    pool: &sqlx::PgPool,
+    note_id: &str,
+    at: DateTime<Utc>,
+) -> Result<HashMap<Uuid, NavRevision>> {
+    let revisions: Vec<NavRevision> = sqlx::query_as(&format!(
+        r#"
+        SELECT * FROM (
+            SELECT DISTINCT ON (nav_id) {}
+            FROM hulunote_nav_revisions
+            WHERE nav_id IN (SELECT nav_id FROM hulunote_nav_revisions WHERE note_id = $1)
+            AND created_at <= $2
+            ORDER BY nav_id, id DESC
+        ) latest
+        -- Navs moved to another note by then are no longer part of this one
+        WHERE note_id = $1
+        "#,
+        REVISION_COLUMNS
+    ))
+    .bind(note_id)
+    .bind(at)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(revisions.into_iter().map(|r| (r.nav_id, r)).collect())
+}
+
+/// Bring a nav back to the state in a revision. Returns the note and
+/// database the nav was in before, or nothing when the nav is already in
+/// that state.
+async fn apply_revision(
+    tx: &mut sqlx::PgConnection,
+    revision: &NavRevision,
+    account_id: i64,
+) -> Result<Option<(String, String)>> {
+    let updated: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+        r#"
+        WITH before AS (SELECT note_id, database_id FROM hulunote_navs WHERE id = $1)
+        INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id,
+                                   properties, is_display, is_delete, deleted_at, last_account_id, field_clocks)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, CASE WHEN $10 THEN NOW() END, $11, $12)
+        ON CONFLICT (id) DO UPDATE
+        SET note_id = EXCLUDED.note_id,
+            database_id = EXCLUDED.database_id,
+            parid = EXCLUDED.parid,
+            same_deep_order = EXCLUDED.same_deep_order,
+            content = EXCLUDED.content,
+            properties = EXCLUDED.properties,
+            is_display = EXCLUDED.is_display,
+            is_delete = EXCLUDED.is_delete,
+            deleted_at = CASE WHEN EXCLUDED.is_delete THEN COALESCE(hulunote_navs.deleted_at, NOW()) END,
+            last_account_id = EXCLUDED.last_account_id,
+            field_clocks = hulunote_navs.field_clocks || EXCLUDED.field_clocks,
+            updated_at = NOW()
+        WHERE (hulunote_navs.note_id, hulunote_navs.database_id, hulunote_navs.parid,
+               hulunote_navs.same_deep_order, hulunote_navs.content, hulunote_navs.properties,
+               hulunote_navs.is_display, hulunote_navs.is_delete)
+            IS DISTINCT FROM
+              (EXCLUDED.note_id, EXCLUDED.database_id, EXCLUDED.parid,
+               EXCLUDED.same_deep_order, EXCLUDED.content, EXCLUDED.properties,
+               EXCLUDED.is_display, EXCLUDED.is_delete)
+        RETURNING (SELECT note_id FROM before), (SELECT database_id FROM before)
+        "#,
+    )
+    .bind(revision.nav_id)
+    .bind(&revision.parid)
+    .bind(revision.same_deep_order)
+    .bind(&revision.content)
+    .bind(revision.account_id)
+    .bind(&revision.note_id)
+    .bind(&revision.database_id)
+    .bind(&revision.properties)
+    .bind(revision.is_display)
+    .bind(revision.is_delete)
+    .bind(account_id)
+    .bind(field_clocks(&NAV_FIELDS, Utc::now().timestamp_millis()))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((note_id, database_id)) = updated else {
+        return Ok(None);
+    };
+
+    sync_nav_links(tx, revision.nav_id, &revision.note_id, &revision.database_id, &revision.content).await?;
+    sync_nav_index(tx, revision.nav_id, &revision.content, &revision.properties).await?;
+    record_nav_revision(tx, revision.nav_id, account_id, "restore").await?;
+    Ok(Some((
+        note_id.unwrap_or_else(|| revision.note_id.clone()),
+        database_id.unwrap_or_else(|| revision.database_id.clone()),
+    )))
+}
+
+/// Send restored navs to their collaborators
+async fn broadcast_restored(
+    state: &AppState,
+    navs: &[HulunoteNav],
+    previous: &HashMap<Uuid, (String, String)>,
+    backend_ts: i64,
+) {
+    for event in nav_batches(navs, previous, backend_ts) {
+        state.ws_broadcaster.broadcast_to_collaborators(state.pool.as_ref(), event).await;
+    }
+}
+
+/// List the changes made to a note's navs, newest first
+pub async fn get_note_history(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetNoteHistoryRequest>,
+) -> Result<Json<Value>> {
+    let note_uuid = Uuid::parse_str(&req.note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+    let nav_uuid = req
+        .nav_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+
+    authorize_note(state.pool.as_ref(), account_id, note_uuid, Access::Read).await?;
+
+    let page = req.page.unwrap_or(1).max(1);
+    let size = req.size.unwrap_or(50).clamp(1, 500);
+    let offset = (page - 1) * size;
+
+    let count: (i64,) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*) FROM hulunote_nav_revisions
+        WHERE note_id = $1 AND ($2::uuid IS NULL OR nav_id = $2)
+        "#,
+    )
+    .bind(&req.note_id)
+    .bind(nav_uuid)
+    .fetch_one(state.pool.as_ref())
+    .await?;
+    let all_pages = (count.0 as f64 / size as f64).ceil() as i64;
+
+    // Each revision with the id of the nav's revision before it
+    let rows: Vec<(i64, Option<i64>)> = sqlx::query_as(
+        r#"
+        SELECT id, previous_id
+        FROM (
+            SELECT r.id, r.note_id, r.nav_id,
+                   LAG(r.id) OVER (PARTITION BY r.nav_id ORDER BY r.id) AS previous_id
+            FROM hulunote_nav_revisions r
+            WHERE r.nav_id IN (
+                SELECT nav_id FROM hulunote_nav_revisions
+                WHERE note_id = $1 AND ($2::uuid IS NULL OR nav_id = $2)
+            )
+        ) h
+        WHERE h.note_id = $1 AND ($2::uuid IS NULL OR h.nav_id = $2)
+        ORDER BY h.id DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(&req.note_id)
+    .bind(nav_uuid)
+    .bind(size)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await?;
+
+    let ids: Vec<i64> = rows
+        .iter()
+        .flat_map(|(id, previous)| std::iter::once(*id).chain(*previous))
+        .collect();
+    let revisions: HashMap<i64, NavRevision> = sqlx::query_as::<_, NavRevision>(&format!(
+        "SELECT {} FROM hulunote_nav_revisions WHERE id = ANY($1)",
+        REVISION_COLUMNS
+    ))
+    .bind(&ids)
+    .fetch_all(state.pool.as_ref())
+    .await?
+    .into_iter()
+    .map(|r| (r.id, r))
+    .collect();
+
+    let history: Vec<Value> = rows
+        .iter()
+        .filter_map(|(id, previous)| {
+            let revision = revisions.get(id)?;
+            let changed = match previous.and_then(|p| revisions.get(&p)) {
+                Some(before) => changed_fields(before, revision),
+                None => vec!["created"],
+            };
+            Some(json!({ "revision": revision, "changed": changed }))
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "revisions": history,
+        "all-pages": all_pages
+    })))
+}
+
+/// Compare a note's navs at two points in time
+pub async fn diff_note(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<DiffNoteRequest>,
+) -> Result<Json<Value>> {
+    let note_uuid = Uuid::parse_str(&req.note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+
+    authorize_note(state.pool.as_ref(), account_id, note_uuid, Access::Read).await?;
+
+    let from = from_millis(req.from)?;
+    let to = match req.to {
+        Some(ms) => from_millis(ms)?,
+        None => Utc::now(),
+    };
+
+    let before = note_state_at(state.pool.as_ref(), &req.note_id, from).await?;
+    let after = note_state_at(state.pool.as_ref(), &req.note_id, to).await?;
+
+    let visible = |state: &HashMap<Uuid, NavRevision>, id: &Uuid| {
+        state.get(id).filter(|r| !r.is_delete && r.parid != ROOT_NAV_ID).cloned()
+    };
+
+    let nav_ids: HashSet<Uuid> = before.keys().chain(after.keys()).copied().collect();
+    let mut changes: Vec<(i64, Value)> = Vec::new();
+    for id in nav_ids {
+        let change = match (visible(&before, &id), visible(&after, &id)) {
+            (None, Some(new)) => (new.id, json!({ "nav-id": id, "change": "added", "before": null, "after": new })),
+            (Some(old), None) => (old.id, json!({ "nav-id": id, "change": "removed", "before": old, "after": null })),
+            (Some(old), Some(new)) => {
+                let fields = changed_fields(&old, &new);
+                if fields.is_empty() {
+                    continue;
+                }
+                (new.id, json!({ "nav-id": id, "change": "modified", "fields": fields, "before": old, "after": new }))
+            }
+            (None, None) => continue,
+        };
+        changes.push(change);
+    }
+    changes.sort_by_key(|(revision_id, _)| *revision_id);
+
+    Ok(Json(json!({
+        "from": from.timestamp_millis(),
+        "to": to.timestamp_millis(),
+        "changes": changes.into_iter().map(|(_, c)| c).collect::<Vec<_>>()
+    })))
+}
+
+/// Restore every nav of a note to its state at a point in time.
+/// Navs created after that time are deleted.
+pub async fn restore_note(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<RestoreNoteRequest>,
+) -> Result<Json<Value>> {
+    let note_uuid = Uuid::parse_str(&req.note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+
+    authorize_note(state.pool.as_ref(), account_id, note_uuid, Access::Write).await?;
+
+    let at = from_millis(req.at)?;
+    let past = note_state_at(state.pool.as_ref(), &req.note_id, at).await?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let mut previous: HashMap<Uuid, (String, String)> = HashMap::new();
+    for revision in past.values().filter(|r| r.parid != ROOT_NAV_ID) {
+        if let Some(location) = apply_revision(&mut tx, revision, account_id).await? {
+            previous.insert(revision.nav_id, location);
+        }
+    }
+    let restored = previous.len();
+
+    // Navs that did not exist yet at that time
+    let newer: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        UPDATE hulunote_navs
+        SET is_delete = true, deleted_at = NOW(), updated_at = NOW()
+        WHERE note_id = $1 AND parid <> $2 AND is_delete = false AND NOT (id = ANY($3))
+        RETURNING id
+        "#,
+    )
+    .bind(&req.note_id)
+    .bind(ROOT_NAV_ID)
+    .bind(past.keys().copied().collect::<Vec<_>>())
+    .fetch_all(&mut *tx)
+    .await?;
+    let newer: Vec<Uuid> = newer.into_iter().map(|(id,)| id).collect();
+    record_nav_revisions(&mut tx, &newer, account_id, "restore").await?;
+
+    let changed: Vec<Uuid> = previous.keys().copied().chain(newer.iter().copied()).collect();
+    let navs = fetch_navs(&mut tx, &changed).await?;
+    tx.commit().await?;
+
+    broadcast_restored(&state, &navs, &previous, Utc::now().timestamp_millis()).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "restored-navs": restored + newer.len()
+    })))
+}
+
+/// Restore a single nav to an earlier revision
+pub async fn restore_nav(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<RestoreNavRequest>,
+) -> Result<Json<Value>> {
+    let nav_uuid = Uuid::parse_str(&req.nav_id)
+        .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+
+    let (note_id, _) = authorize_nav(state.pool.as_ref(), account_id, nav_uuid, Access::Write).await?;
+
+    let revision: Option<NavRevision> = match (req.revision_id, req.at) {
+        (Some(revision_id), _) => {
+            sqlx::query_as(&format!(
+                "SELECT {} FROM hulunote_nav_revisions WHERE id = $1 AND nav_id = $2",
+                REVISION_COLUMNS
+            ))
+            .bind(revision_id)
+            .bind(nav_uuid)
+            .fetch_optional(state.pool.as_ref())
+            .await?
+        }
+        (None, Some(at)) => {
+            sqlx::query_as(&format!(
+                r#"
+                SELECT {} FROM hulunote_nav_revisions
+                WHERE nav_id = $1 AND created_at <= $2
+                ORDER BY id DESC
+                LIMIT 1
+                "#,
+                REVISION_COLUMNS
+            ))
+            .bind(nav_uuid)
+            .bind(from_millis(at)?)
+            .fetch_optional(state.pool.as_ref())
+            .await?
+        }
+        (None, None) => {
+            return Err(AppError::BadRequest("revision-id or at is required".to_string()));
+        }
+    };
+    let revision = revision.ok_or_else(|| AppError::NotFound("Revision not found".to_string()))?;
+
+    // A revision from before a move puts the nav back in its old note
+    if revision.note_id != note_id {
+        let old_note = Uuid::parse_str(&revision.note_id)
+            .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+        authorize_note(state.pool.as_ref(), account_id, old_note, Access::Write).await?;
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let previous = apply_revision(&mut tx, &revision, account_id).await?;
+    let navs = fetch_navs(&mut tx, &[nav_uuid]).await?;
+    tx.commit().await?;
+
+    let restored = previous.is_some();
+    if let Some(location) = previous {
+        let previous = HashMap::from([(nav_uuid, location)]);
+        broadcast_restored(&state, &navs, &previous, Utc::now().timestamp_millis()).await;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "restored": restored,
+        "revision-id": revision.id
+    })))
+}
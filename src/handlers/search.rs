@@ -0,0 +1,144 @@
+use axum::{extract::State, Extension, Json};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{get_database_id, get_nav_paths, member_database_ids, Access, AppState};
+
+/// Options passed to ts_headline for highlighted snippets
+const HEADLINE_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15, MaxFragments=2";
+
+/// SQL for `column` with HTML special characters escaped. ts_headline keeps
+/// tags found in its input, so snippets are highlighted from escaped text to
+/// leave `<mark>` as their only markup.
+fn escaped_html(column: &str) -> String {
+    format!(
+        r#"replace(replace(replace(replace(replace({}, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '"', '&quot;'), '''', '&#39;')"#,
+        column
+    )
+}
+
+/// Full-text search over note titles and nav content
+pub async fn search(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<SearchRequest>,
+) -> Result<Json<Value>> {
+    let query = req.query.trim();
+    if query.is_empty() {
+        return Err(AppError::BadRequest("Search query required".to_string()));
+    }
+
+    // Search one database if asked, otherwise every database the account can open
+    // (database_id columns are VARCHAR, so compare as strings)
+    let database_ids: Vec<String> = match get_database_id(
+        state.pool.as_ref(),
+        account_id,
+        req.database_id.as_deref(),
+        req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
+    )
+    .await?
+    {
+        Some(id) => vec![id.to_string()],
+        None if req.database_id.is_some() || req.database_name.is_some() || req.database.is_some() => {
+            return Err(AppError::BadRequest("Database not found".to_string()));
+        }
+        None => member_database_ids(state.pool.as_ref(), account_id).await?,
+    };
+
+    let page = req.page.unwrap_or(1).max(1);
+    let size = req.size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    // Get total count
+    let count: (i64,) = sqlx::query_as(
+        r#"
+        WITH q AS (SELECT websearch_to_tsquery('simple', $1) AS query)
+        SELECT
+            (SELECT COUNT(*) FROM hulunote_notes n, q
+             WHERE n.title_tsv @@ q.query AND n.is_delete = false
+             AND n.database_id = ANY($2))
+          + (SELECT COUNT(*) FROM hulunote_navs v
+             JOIN hulunote_notes n ON n.id::text = v.note_id, q
+             WHERE v.content_tsv @@ q.query AND v.is_delete = false AND n.is_delete = false
+             AND v.parid <> '00000000-0000-0000-0000-000000000000'
+             AND v.database_id = ANY($2))
+        "#,
+    )
+    .bind(query)
+    .bind(&database_ids)
+    .fetch_one(state.pool.as_ref())
+    .await?;
+
+    let all_pages = (count.0 as f64 / size as f64).ceil() as i64;
+
+    // Get hits, best match first
+    let hits_query = format!(
+        r#"
+        WITH q AS (SELECT websearch_to_tsquery('simple', $1) AS query)
+        SELECT kind, note_id, nav_id, database_id, title, snippet, rank
+        FROM (
+            SELECT 'note' AS kind, n.id::text AS note_id, NULL::uuid AS nav_id,
+                   n.database_id, n.title,
+                   ts_headline('simple', {}, q.query, $3) AS snippet,
+                   ts_rank(n.title_tsv, q.query) AS rank, n.updated_at
+            FROM hulunote_notes n, q
+            WHERE n.title_tsv @@ q.query AND n.is_delete = false
+            AND n.database_id = ANY($2)
+            UNION ALL
+            SELECT 'nav', v.note_id, v.id, v.database_id, n.title,
+                   ts_headline('simple', {}, q.query, $3),
+                   ts_rank(v.content_tsv, q.query), v.updated_at
+            FROM hulunote_navs v
+            JOIN hulunote_notes n ON n.id::text = v.note_id, q
+            WHERE v.content_tsv @@ q.query AND v.is_delete = false AND n.is_delete = false
+            AND v.parid <> '00000000-0000-0000-0000-000000000000'
+            AND v.database_id = ANY($2)
+        ) hits
+        ORDER BY rank DESC, updated_at DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        escaped_html("n.title"),
+        escaped_html("v.content")
+    );
+    let hits: Vec<SearchHit> = sqlx::query_as(&hits_query)
+        .bind(query)
+        .bind(&database_ids)
+        .bind(HEADLINE_OPTIONS)
+        .bind(size)
+        .bind(offset)
+        .fetch_all(state.pool.as_ref())
+        .await?;
+
+    // Attach the ancestor path of every nav hit so the client can expand to it
+    let nav_ids: Vec<Uuid> = hits.iter().filter_map(|h| h.nav_id).collect();
+    let mut paths = get_nav_paths(state.pool.as_ref(), &nav_ids).await?;
+
+    let results: Vec<Value> = hits
+        .into_iter()
+        .map(|hit| {
+            let path = hit
+                .nav_id
+                .and_then(|id| paths.remove(&id))
+                .unwrap_or_default();
+            json!({
+                "type": hit.kind,
+                "note-id": hit.note_id,
+                "nav-id": hit.nav_id.map(|id| id.to_string()),
+                "database-id": hit.database_id,
+                "title": hit.title,
+                "snippet": hit.snippet,
+                "rank": hit.rank,
+                "path": path
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "results": results,
+        "all-pages": all_pages
+    })))
+}
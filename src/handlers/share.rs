@@ -0,0 +1,196 @@
+use axum::{extract::State, Extension, Json};
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::Utc;
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{authorize_database, authorize_nav, authorize_note, Access, AppState};
+
+/// Length of a share token: 43 alphanumeric characters carry about 256 bits
+const TOKEN_LENGTH: usize = 43;
+
+fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Check that an account may share a target: editors can share notes and
+/// navs, but only owners can share a whole database
+pub(crate) async fn authorize_share_target(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    target: ShareTarget,
+    id: Uuid,
+) -> Result<()> {
+    match target {
+        ShareTarget::Note => authorize_note(pool, account_id, id, Access::Write).await.map(|_| ()),
+        ShareTarget::Nav => authorize_nav(pool, account_id, id, Access::Write).await.map(|_| ()),
+        ShareTarget::Database => authorize_database(pool, account_id, id, Access::Manage).await,
+    }
+}
+
+/// The single target named in a request, checked to be writable by the
+/// caller and not in the trash
+async fn share_target(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    note_id: Option<&str>,
+    nav_id: Option<&str>,
+    database_id: Option<&str>,
+) -> Result<(ShareTarget, Uuid)> {
+    let (target, id) = match (note_id, nav_id, database_id) {
+        (Some(id), None, None) => (ShareTarget::Note, id),
+        (None, Some(id), None) => (ShareTarget::Nav, id),
+        (None, None, Some(id)) => (ShareTarget::Database, id),
+        _ => {
+            return Err(AppError::BadRequest(
+                "Exactly one of note-id, nav-id or database-id required".to_string(),
+            ))
+        }
+    };
+    let id = Uuid::parse_str(id)
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} ID", target.as_str())))?;
+
+    authorize_share_target(pool, account_id, target, id).await?;
+    let deleted_query = match target {
+        ShareTarget::Note => "SELECT is_delete FROM hulunote_notes WHERE id = $1",
+        ShareTarget::Nav => {
+            r#"
+            SELECT v.is_delete OR COALESCE(n.is_delete, false)
+            FROM hulunote_navs v
+            LEFT JOIN hulunote_notes n ON n.id::text = v.note_id
+            WHERE v.id = $1
+            "#
+        }
+        ShareTarget::Database => "SELECT is_delete FROM hulunote_databases WHERE id = $1",
+    };
+    let (is_delete,): (bool,) = sqlx::query_as(deleted_query).bind(id).fetch_one(pool).await?;
+    if is_delete {
+        return Err(AppError::BadRequest(format!("Cannot share a deleted {}", target.as_str())));
+    }
+
+    Ok((target, id))
+}
+
+/// Create an unguessable link to a note, a nav's subtree or a database
+pub async fn create_share_link(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<CreateShareLinkRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let (target, target_id) = share_target(
+        pool,
+        account_id,
+        req.note_id.as_deref(),
+        req.nav_id.as_deref(),
+        req.database_id.as_deref(),
+    )
+    .await?;
+
+    if req.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(AppError::BadRequest("expires-at must be in the future".to_string()));
+    }
+    let password_hash = match req.password.as_deref() {
+        Some("") => return Err(AppError::BadRequest("Password cannot be empty".to_string())),
+        Some(password) => Some(hash(password, DEFAULT_COST)?),
+        None => None,
+    };
+
+    let link: ShareLink = sqlx::query_as(
+        r#"
+        INSERT INTO hulunote_share_links (token, account_id, target_type, target_id, password_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, token, account_id, target_type, target_id, password_hash, expires_at,
+                  view_count, last_viewed_at, revoked_at, created_at
+        "#,
+    )
+    .bind(generate_token())
+    .bind(account_id)
+    .bind(target.as_str())
+    .bind(target_id.to_string())
+    .bind(password_hash)
+    .bind(req.expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "share-link": ShareLinkInfo::from(link)
+    })))
+}
+
+/// List the caller's share links, newest first, optionally for one target
+pub async fn get_share_link_list(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetShareLinksRequest>,
+) -> Result<Json<Value>> {
+    let target = match (&req.note_id, &req.nav_id, &req.database_id) {
+        (Some(id), None, None) => Some((ShareTarget::Note, id)),
+        (None, Some(id), None) => Some((ShareTarget::Nav, id)),
+        (None, None, Some(id)) => Some((ShareTarget::Database, id)),
+        (None, None, None) => None,
+        _ => {
+            return Err(AppError::BadRequest(
+                "At most one of note-id, nav-id or database-id allowed".to_string(),
+            ))
+        }
+    };
+
+    let links: Vec<ShareLink> = sqlx::query_as(
+        r#"
+        SELECT id, token, account_id, target_type, target_id, password_hash, expires_at,
+               view_count, last_viewed_at, revoked_at, created_at
+        FROM hulunote_share_links
+        WHERE account_id = $1
+        AND ($2::text IS NULL OR (target_type = $2 AND target_id = $3))
+        AND ($4 OR revoked_at IS NULL)
+        ORDER BY created_at DESC, id DESC
+        "#,
+    )
+    .bind(account_id)
+    .bind(target.map(|(t, _)| t.as_str()))
+    .bind(target.map(|(_, id)| id.as_str()))
+    .bind(req.include_revoked)
+    .fetch_all(state.pool.as_ref())
+    .await?;
+
+    Ok(Json(json!({
+        "share-links": links.into_iter().map(ShareLinkInfo::from).collect::<Vec<_>>()
+    })))
+}
+
+/// Revoke one of the caller's share links; it stops resolving immediately
+pub async fn revoke_share_link(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<RevokeShareLinkRequest>,
+) -> Result<Json<Value>> {
+    let link: ShareLink = sqlx::query_as(
+        r#"
+        UPDATE hulunote_share_links
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE id = $1 AND account_id = $2
+        RETURNING id, token, account_id, target_type, target_id, password_hash, expires_at,
+                  view_count, last_viewed_at, revoked_at, created_at
+        "#,
+    )
+    .bind(req.id)
+    .bind(account_id)
+    .fetch_optional(state.pool.as_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
+
+    Ok(Json(json!({
+        "success": true,
+        "share-link": ShareLinkInfo::from(link)
+    })))
+}
@@ -0,0 +1,407 @@
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use serde_json::{json, Value};
+use sqlx::PgConnection;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_nav, authorize_note, broadcast_reordered, check_nav_parent, field_clocks, insert_nav, rebalance_siblings,
+    record_nav_revisions, sync_nav_links, ws::WsEvent, Access, AppState, NavChanges,
+};
+
+const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Where a subtree goes: a parent nav in a note
+pub(crate) struct SubtreeTarget {
+    pub(crate) note_id: String,
+    pub(crate) database_id: String,
+    pub(crate) parid: String,
+}
+
+/// Move a nav and its descendants to another parent, note or database
+pub async fn move_nav(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<NavSubtreeRequest>,
+) -> Result<Json<Value>> {
+    let nav_uuid = Uuid::parse_str(&req.nav_id)
+        .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+
+    let (source_note_id, _) =
+        authorize_nav(state.pool.as_ref(), account_id, nav_uuid, Access::Write).await?;
+    let target = resolve_target(state.pool.as_ref(), account_id, &req, &source_note_id).await?;
+
+    let backend_ts = Utc::now().timestamp_millis();
+    let mut tx = state.pool.begin().await?;
+
+    // Deleted descendants move too, so restoring them later finds their parent
+    let subtree = fetch_subtree(&mut tx, nav_uuid, true).await?;
+    check_subtree_root(&subtree)?;
+    check_nav_parent(&mut tx, &target.note_id, Some(nav_uuid), &target.parid).await?;
+
+    let order = match req.order {
+        Some(order) => order,
+        None => next_order(&mut tx, &target.parid).await?,
+    };
+
+    sqlx::query(
+        "UPDATE hulunote_navs SET parid = $2, same_deep_order = $3, last_account_id = $4, field_clocks = field_clocks || $5, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(nav_uuid)
+    .bind(&target.parid)
+    .bind(order)
+    .bind(account_id)
+    .bind(field_clocks(&["parid", "same_deep_order"], Utc::now().timestamp_millis()))
+    .execute(&mut *tx)
+    .await?;
+
+    let ids: Vec<Uuid> = subtree.iter().map(|nav| nav.id).collect();
+
+    if target.note_id != source_note_id {
+        // note_id and database_id are VARCHAR
+        sqlx::query(
+            "UPDATE hulunote_navs SET note_id = $2, database_id = $3, updated_at = NOW() WHERE id = ANY($1)",
+        )
+        .bind(&ids)
+        .bind(&target.note_id)
+        .bind(&target.database_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Links are stored with their source note and resolved per database
+        for nav in &subtree {
+            sync_nav_links(&mut tx, nav.id, &target.note_id, &target.database_id, &nav.content).await?;
+        }
+    }
+
+    record_nav_revisions(&mut tx, &ids, account_id, "update").await?;
+    let reordered = rebalance_siblings(&mut tx, account_id, &target.parid).await?;
+    let moved = fetch_subtree(&mut tx, nav_uuid, true).await?;
+    tx.commit().await?;
+
+    // Clients showing the old note see the navs leave it through their new note-id
+    if target.note_id != source_note_id {
+        let source_database_id = subtree[0].database_id.clone();
+        broadcast_navs(&state, &source_note_id, &source_database_id, backend_ts, &moved).await;
+    }
+    broadcast_navs(&state, &target.note_id, &target.database_id, backend_ts, &moved).await;
+    broadcast_reordered(&state, &target.note_id, &target.database_id, backend_ts, &reordered).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": nav_uuid.to_string(),
+        "moved": ids.len(),
+        "reordered": reordered,
+        "backend-ts": backend_ts
+    })))
+}
+
+/// Copy a nav and its descendants under another parent, with new ids.
+/// Deleted descendants are left out.
+pub async fn copy_nav(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<NavSubtreeRequest>,
+) -> Result<Json<Value>> {
+    let nav_uuid = Uuid::parse_str(&req.nav_id)
+        .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+
+    let (source_note_id, _) =
+        authorize_nav(state.pool.as_ref(), account_id, nav_uuid, Access::Read).await?;
+    let target = resolve_target(state.pool.as_ref(), account_id, &req, &source_note_id).await?;
+
+    let backend_ts = Utc::now().timestamp_millis();
+    let mut tx = state.pool.begin().await?;
+
+    let subtree = fetch_subtree(&mut tx, nav_uuid, false).await?;
+    check_subtree_root(&subtree)?;
+    check_nav_parent(&mut tx, &target.note_id, None, &target.parid).await?;
+
+    let order = match req.order {
+        Some(order) => order,
+        None => next_order(&mut tx, &target.parid).await?,
+    };
+
+    let (mut copies, new_ids) = copy_subtree(&mut tx, account_id, &subtree, &target, order).await?;
+    let reordered = rebalance_siblings(&mut tx, account_id, &target.parid).await?;
+    tx.commit().await?;
+
+    if let Some(order) = reordered.iter().find(|o| o.id == copies[0].id.to_string()) {
+        copies[0].same_deep_order = order.same_deep_order;
+    }
+
+    broadcast_navs(&state, &target.note_id, &target.database_id, backend_ts, &copies).await;
+    broadcast_reordered(&state, &target.note_id, &target.database_id, backend_ts, &reordered).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": copies[0].id.to_string(),
+        "ids": new_ids,
+        "copied": copies.len(),
+        "reordered": reordered,
+        "backend-ts": backend_ts
+    })))
+}
+
+/// Copy a nav and its descendants right after the nav itself
+pub async fn duplicate_nav(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<DuplicateNavRequest>,
+) -> Result<Json<Value>> {
+    let nav_uuid = Uuid::parse_str(&req.nav_id)
+        .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+
+    let (note_id, database_id) =
+        authorize_nav(state.pool.as_ref(), account_id, nav_uuid, Access::Write).await?;
+
+    let backend_ts = Utc::now().timestamp_millis();
+    let mut tx = state.pool.begin().await?;
+
+    let subtree = fetch_subtree(&mut tx, nav_uuid, false).await?;
+    check_subtree_root(&subtree)?;
+    let original = &subtree[0];
+
+    // Halfway to the next sibling, or one past the nav when it is the last
+    let next: Option<(f32,)> = sqlx::query_as(
+        r#"
+        SELECT same_deep_order FROM hulunote_navs
+        WHERE parid = $1 AND is_delete = false AND same_deep_order > $2
+        ORDER BY same_deep_order
+        LIMIT 1
+        "#,
+    )
+    .bind(&original.parid)
+    .bind(original.same_deep_order)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let order = match next {
+        Some((next,)) => (original.same_deep_order + next) / 2.0,
+        None => original.same_deep_order + 1.0,
+    };
+
+    let target = SubtreeTarget {
+        note_id,
+        database_id,
+        parid: original.parid.clone(),
+    };
+    let (mut copies, new_ids) = copy_subtree(&mut tx, account_id, &subtree, &target, order).await?;
+    let reordered = rebalance_siblings(&mut tx, account_id, &target.parid).await?;
+    tx.commit().await?;
+
+    if let Some(order) = reordered.iter().find(|o| o.id == copies[0].id.to_string()) {
+        copies[0].same_deep_order = order.same_deep_order;
+    }
+
+    broadcast_navs(&state, &target.note_id, &target.database_id, backend_ts, &copies).await;
+    broadcast_reordered(&state, &target.note_id, &target.database_id, backend_ts, &reordered).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": copies[0].id.to_string(),
+        "ids": new_ids,
+        "copied": copies.len(),
+        "reordered": reordered,
+        "backend-ts": backend_ts
+    })))
+}
+
+/// Check write access to the target note and fill in the default parent
+async fn resolve_target(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    req: &NavSubtreeRequest,
+    source_note_id: &str,
+) -> Result<SubtreeTarget> {
+    let note_id = req.target_note_id.as_deref().unwrap_or(source_note_id);
+    let note_uuid = Uuid::parse_str(note_id)
+        .map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+
+    let database_id = authorize_note(pool, account_id, note_uuid, Access::Write).await?;
+
+    let (root_nav_id, is_delete): (String, bool) =
+        sqlx::query_as("SELECT root_nav_id, is_delete FROM hulunote_notes WHERE id = $1")
+            .bind(note_uuid)
+            .fetch_one(pool)
+            .await?;
+    if is_delete {
+        return Err(AppError::NotFound("Target note not found".to_string()));
+    }
+
+    Ok(SubtreeTarget {
+        note_id: note_id.to_string(),
+        database_id,
+        parid: req.parid.clone().unwrap_or(root_nav_id),
+    })
+}
+
+/// A nav followed by its descendants, parents before children and siblings
+/// in order
+pub(crate) async fn fetch_subtree(conn: &mut PgConnection, nav_id: Uuid, include_deleted: bool) -> Result<Vec<HulunoteNav>> {
+    let navs: Vec<HulunoteNav> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, note_id, 0 AS depth
+            FROM hulunote_navs
+            WHERE id = $1 AND ($2 OR is_delete = false)
+            UNION ALL
+            SELECT v.id, v.note_id, s.depth + 1
+            FROM subtree s
+            JOIN hulunote_navs v ON v.parid = s.id::text AND v.note_id = s.note_id
+            WHERE ($2 OR v.is_delete = false) AND s.depth < 1000
+        )
+        SELECT v.id, v.parid, v.same_deep_order, v.content, v.account_id, v.last_account_id, v.note_id, v.database_id,
+               v.is_display, v.is_public, v.is_delete, v.properties, v.extra_id, v.created_at, v.updated_at
+        FROM subtree s
+        JOIN hulunote_navs v ON v.id = s.id
+        ORDER BY s.depth, v.same_deep_order
+        "#,
+    )
+    .bind(nav_id)
+    .bind(include_deleted)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(navs)
+}
+
+/// Navs by id, in their current state, siblings in order
+pub(crate) async fn fetch_navs(conn: &mut PgConnection, nav_ids: &[Uuid]) -> Result<Vec<HulunoteNav>> {
+    let navs: Vec<HulunoteNav> = sqlx::query_as(
+        r#"
+        SELECT id, parid, same_deep_order, content, account_id, last_account_id, note_id, database_id,
+               is_display, is_public, is_delete, properties, extra_id, created_at, updated_at
+        FROM hulunote_navs
+        WHERE id = ANY($1)
+        ORDER BY note_id, parid, same_deep_order
+        "#,
+    )
+    .bind(nav_ids)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(navs)
+}
+
+/// The nav being moved or copied must exist and cannot be a note's root
+fn check_subtree_root(subtree: &[HulunoteNav]) -> Result<()> {
+    match subtree.first() {
+        None => Err(AppError::NotFound("Nav not found".to_string())),
+        Some(nav) if nav.is_delete => Err(AppError::NotFound("Nav not found".to_string())),
+        Some(nav) if nav.parid == ROOT_NAV_ID => {
+            Err(AppError::BadRequest("The root nav of a note cannot be moved or copied".to_string()))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Order that puts a new child after the existing children of a parent
+pub(crate) async fn next_order(conn: &mut PgConnection, parid: &str) -> Result<f32> {
+    let (order,): (f32,) = sqlx::query_as(
+        "SELECT COALESCE(MAX(same_deep_order) + 1, 0)::real FROM hulunote_navs WHERE parid = $1 AND is_delete = false",
+    )
+    .bind(parid)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(order)
+}
+
+/// Insert copies of a subtree with fresh ids. The copy of the subtree's root
+/// goes under the target parent at `order`; descendants keep their order.
+/// Returns the copies and a map from old to new ids.
+pub(crate) async fn copy_subtree(
+    conn: &mut PgConnection,
+    account_id: i64,
+    subtree: &[HulunoteNav],
+    target: &SubtreeTarget,
+    order: f32,
+) -> Result<(Vec<HulunoteNav>, HashMap<String, String>)> {
+    let mut new_ids: HashMap<String, String> = HashMap::new();
+    let mut copies = Vec::with_capacity(subtree.len());
+
+    for (i, nav) in subtree.iter().enumerate() {
+        let (parid, order) = if i == 0 {
+            (target.parid.clone(), order)
+        } else {
+            // Parents come first, so they already have their new id
+            (new_ids[&nav.parid].clone(), nav.same_deep_order)
+        };
+
+        let changes = NavChanges {
+            parid: Some(&parid),
+            order: Some(order),
+            content: Some(&nav.content),
+            properties: Some(&nav.properties),
+            is_display: Some(nav.is_display),
+            is_delete: None,
+            is_public: Some(nav.is_public),
+            clock: None,
+        };
+        let copy = insert_nav(conn, account_id, Uuid::new_v4(), &target.note_id, &target.database_id, &changes)
+            .await?;
+
+        new_ids.insert(nav.id.to_string(), copy.id.to_string());
+        copies.push(copy);
+    }
+
+    Ok((copies, new_ids))
+}
+
+pub(crate) async fn broadcast_navs(
+    state: &AppState,
+    note_id: &str,
+    database_id: &str,
+    backend_ts: i64,
+    navs: &[HulunoteNav],
+) {
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(
+            state.pool.as_ref(),
+            WsEvent::NavsBatch {
+                note_id: note_id.to_string(),
+                database_id: database_id.to_string(),
+                backend_ts,
+                navs: navs.iter().cloned().map(NavInfo::from).collect(),
+            },
+        )
+        .await;
+}
+
+/// One `NavsBatch` per note for navs changed in place: each nav goes to the
+/// note it is in now and, if it came from another note, to the one it left,
+/// whose clients see it leave through its new note-id
+pub(crate) fn nav_batches(
+    navs: &[HulunoteNav],
+    previous: &HashMap<Uuid, (String, String)>,
+    backend_ts: i64,
+) -> Vec<WsEvent> {
+    let mut batches: Vec<WsEvent> = Vec::new();
+    for nav in navs {
+        let mut notes = vec![(&nav.note_id, &nav.database_id)];
+        if let Some((note_id, database_id)) = previous.get(&nav.id).filter(|(note_id, _)| *note_id != nav.note_id) {
+            notes.push((note_id, database_id));
+        }
+        for (note_id, database_id) in notes {
+            let batch = batches.iter_mut().find_map(|event| match event {
+                WsEvent::NavsBatch { note_id: id, navs, .. } if id == note_id => Some(navs),
+                _ => None,
+            });
+            match batch {
+                Some(batch) => batch.push(NavInfo::from(nav.clone())),
+                None => batches.push(WsEvent::NavsBatch {
+                    note_id: note_id.clone(),
+                    database_id: database_id.clone(),
+                    backend_ts,
+                    navs: vec![NavInfo::from(nav.clone())],
+                }),
+            }
+        }
+    }
+    batches
+}
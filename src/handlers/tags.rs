@@ -0,0 +1,301 @@
+use axum::{extract::State, Extension, Json};
+use serde_json::{json, Value};
+use sqlx::{Postgres, QueryBuilder};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_note, get_database_id, get_nav_paths, member_database_ids, parse_property_line, property_value,
+    sync_nav_task, Access, AppState,
+};
+
+/// Property whose comma-separated values are indexed as tags
+const TAGS_PROPERTY: &str = "tags";
+
+/// Parse the `#tag` and `#[[multi word tag]]` tags in nav content.
+///
+/// A tag starts after whitespace or at the start of the content, so
+/// `page#anchor` and `## heading` are not tags. Duplicates differing only in
+/// case are returned once, spelled as they first appear.
+pub fn parse_tags(content: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut previous: Option<char> = None;
+
+    for (i, c) in content.char_indices() {
+        let starts_tag = c == '#' && previous.is_none_or(char::is_whitespace);
+        previous = Some(c);
+        if !starts_tag {
+            continue;
+        }
+
+        let rest = &content[i + 1..];
+        let tag = if let Some(bracketed) = rest.strip_prefix("[[") {
+            match bracketed.find("]]") {
+                Some(end) if !bracketed[..end].contains(['[', ']']) => bracketed[..end].trim(),
+                _ => continue,
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '/')))
+                .unwrap_or(rest.len());
+            &rest[..end]
+        };
+
+        push_tag(&mut tags, tag);
+    }
+
+    tags
+}
+
+fn push_tag(tags: &mut Vec<String>, tag: &str) {
+    if !tag.is_empty() && !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+        tags.push(tag.to_string());
+    }
+}
+
+/// Parse nav properties written as `key:: value` lines or as a JSON object.
+/// Keys are lowercased; when a key repeats, the last value wins. Empty and
+/// null values are left out.
+pub fn parse_properties(properties: &str) -> Vec<(String, String)> {
+    let mut parsed: Vec<(String, String)> = Vec::new();
+    let mut push = |key: &str, value: String| {
+        let key = key.to_lowercase();
+        parsed.retain(|(k, _)| *k != key);
+        if !value.is_empty() {
+            parsed.push((key, value));
+        }
+    };
+
+    let trimmed = properties.trim();
+    if trimmed.starts_with('{') {
+        if let Ok(object) = serde_json::from_str::<serde_json::Map<String, Value>>(trimmed) {
+            for (key, value) in object {
+                match value {
+                    Value::Null => {}
+                    Value::String(value) => push(&key, value),
+                    value => push(&key, value.to_string()),
+                }
+            }
+            return parsed;
+        }
+    }
+
+    for line in properties.lines() {
+        if let Some((key, value)) = parse_property_line(line) {
+            push(key, property_value(value));
+        }
+    }
+    parsed
+}
+
+/// Replace the tags, properties and task indexed for a nav with the ones in
+/// its content and properties
+pub async fn sync_nav_index(
+    conn: &mut sqlx::PgConnection,
+    nav_id: Uuid,
+    content: &str,
+    properties: &str,
+) -> Result<()> {
+    sqlx::query("DELETE FROM hulunote_nav_tags WHERE nav_id = $1")
+        .bind(nav_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM hulunote_nav_properties WHERE nav_id = $1")
+        .bind(nav_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let properties = parse_properties(properties);
+    sync_nav_task(conn, nav_id, content, &properties).await?;
+
+    let mut tags = parse_tags(content);
+    if let Some((_, value)) = properties.iter().find(|(key, _)| key == TAGS_PROPERTY) {
+        for tag in value.split(',') {
+            let tag = tag.trim().trim_start_matches('#');
+            push_tag(&mut tags, tag.strip_prefix("[[").and_then(|t| t.strip_suffix("]]")).unwrap_or(tag).trim());
+        }
+    }
+
+    for tag in tags {
+        sqlx::query("INSERT INTO hulunote_nav_tags (nav_id, tag) VALUES ($1, $2)")
+            .bind(nav_id)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    for (key, value) in properties {
+        let number = value.trim().parse::<f64>().ok().filter(|n| n.is_finite());
+        sqlx::query(
+            "INSERT INTO hulunote_nav_properties (nav_id, key, value, number_value) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(nav_id)
+        .bind(key)
+        .bind(value)
+        .bind(number)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Databases searched by a query: the note's database when a note is
+/// given, the requested database, or every database the account can open
+pub(crate) async fn query_scope(
+    pool: &sqlx::PgPool,
+    account_id: i64,
+    note_id: Option<&str>,
+    database_id: Option<&str>,
+    database_name: Option<&str>,
+) -> Result<Vec<String>> {
+    if let Some(note_id) = note_id {
+        let note_uuid = Uuid::parse_str(note_id)
+            .map_err(|_| AppError::BadRequest("Invalid note ID".to_string()))?;
+        return Ok(vec![authorize_note(pool, account_id, note_uuid, Access::Read).await?]);
+    }
+
+    match get_database_id(pool, account_id, database_id, database_name, Access::Read).await? {
+        Some(id) => Ok(vec![id.to_string()]),
+        None if database_id.is_some() || database_name.is_some() => {
+            Err(AppError::BadRequest("Database not found".to_string()))
+        }
+        None => member_database_ids(pool, account_id).await,
+    }
+}
+
+/// Add the `WHERE` clause shared by the count and the page of `query_navs`
+fn push_query_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    database_ids: &[String],
+    note_id: Option<&str>,
+    req: &QueryNavsRequest,
+) -> Result<()> {
+    builder
+        .push(" WHERE v.is_delete = false AND n.is_delete = false AND v.database_id = ANY(")
+        .push_bind(database_ids.to_vec())
+        .push(")");
+    if let Some(note_id) = note_id {
+        builder.push(" AND v.note_id = ").push_bind(note_id.to_string());
+    }
+
+    for tag in &req.tags {
+        let tag = tag.trim().trim_start_matches('#');
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM hulunote_nav_tags t WHERE t.nav_id = v.id AND lower(t.tag) = lower(")
+            .push_bind(tag.to_string())
+            .push("))");
+    }
+
+    for filter in &req.properties {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM hulunote_nav_properties p WHERE p.nav_id = v.id AND p.key = ")
+            .push_bind(filter.key.trim().to_lowercase());
+        if let Some(value) = &filter.value {
+            builder.push(" AND p.value = ").push_bind(value.clone());
+        }
+        for (op, bound) in [(">", &filter.gt), (">=", &filter.gte), ("<", &filter.lt), ("<=", &filter.lte)] {
+            match bound {
+                None => {}
+                Some(Value::Number(n)) => {
+                    builder.push(format!(" AND p.number_value {} ", op)).push_bind(n.as_f64());
+                }
+                Some(Value::String(s)) => {
+                    builder.push(format!(" AND p.value {} ", op)).push_bind(s.clone());
+                }
+                Some(_) => {
+                    return Err(AppError::BadRequest(format!(
+                        "Range bounds of property '{}' must be numbers or strings",
+                        filter.key
+                    )));
+                }
+            }
+        }
+        builder.push(")");
+    }
+
+    Ok(())
+}
+
+/// Find navs by tag and property, within a note, a database or every
+/// database of the account
+pub async fn query_navs(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<QueryNavsRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    if req.tags.is_empty() && req.properties.is_empty() {
+        return Err(AppError::BadRequest("At least one tag or property filter required".to_string()));
+    }
+
+    let database_ids = query_scope(
+        pool,
+        account_id,
+        req.note_id.as_deref(),
+        req.database_id.as_deref(),
+        req.database_name.as_deref().or(req.database.as_deref()),
+    )
+    .await?;
+
+    let page = req.page.unwrap_or(1).max(1);
+    let size = req.size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    let mut count_query = QueryBuilder::<Postgres>::new(
+        "SELECT COUNT(*) FROM hulunote_navs v JOIN hulunote_notes n ON n.id::text = v.note_id",
+    );
+    push_query_filters(&mut count_query, &database_ids, req.note_id.as_deref(), &req)?;
+    let (count,): (i64,) = count_query.build_query_as().fetch_one(pool).await?;
+    let all_pages = (count as f64 / size as f64).ceil() as i64;
+
+    let mut nav_query = QueryBuilder::<Postgres>::new(
+        r#"
+        SELECT v.id, v.parid, v.same_deep_order, v.content, v.account_id, v.last_account_id, v.note_id, v.database_id,
+               v.is_display, v.is_public, v.is_delete, v.properties, v.extra_id, v.created_at, v.updated_at
+        FROM hulunote_navs v
+        JOIN hulunote_notes n ON n.id::text = v.note_id
+        "#,
+    );
+    push_query_filters(&mut nav_query, &database_ids, req.note_id.as_deref(), &req)?;
+    nav_query
+        .push(" ORDER BY v.updated_at DESC, v.id LIMIT ")
+        .push_bind(size)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    let navs: Vec<HulunoteNav> = nav_query.build_query_as().fetch_all(pool).await?;
+
+    let note_ids: Vec<String> = navs.iter().map(|n| n.note_id.clone()).collect();
+    let titles: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+        "SELECT id::text, title FROM hulunote_notes WHERE id::text = ANY($1)"
+    )
+    .bind(&note_ids)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let nav_ids: Vec<Uuid> = navs.iter().map(|n| n.id).collect();
+    let mut paths = get_nav_paths(pool, &nav_ids).await?;
+
+    let results: Vec<Value> = navs
+        .into_iter()
+        .map(|nav| {
+            let path = paths.remove(&nav.id).unwrap_or_default();
+            json!({
+                "note-id": nav.note_id.clone(),
+                "note-title": titles.get(&nav.note_id).cloned().unwrap_or_default(),
+                "path": path,
+                "nav": NavInfo::from(nav)
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "results": results,
+        "all-pages": all_pages
+    })))
+}
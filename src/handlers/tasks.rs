@@ -0,0 +1,326 @@
+use axum::{extract::State, Extension, Json};
+use chrono::{NaiveDate, Utc};
+use serde_json::{json, Value};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::ops::Range;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_nav, get_nav_paths, nav_changed_event, parse_daily_title, parse_date, query_scope, update_nav, Access,
+    AppState, NavChanges,
+};
+
+/// Markers that start a task, and the status they stand for. `LATER` and
+/// `NOW` are Logseq's spellings of TODO and DOING.
+const TASK_MARKERS: [(&str, TaskStatus); 7] = [
+    ("TODO", TaskStatus::Todo),
+    ("LATER", TaskStatus::Todo),
+    ("DOING", TaskStatus::Doing),
+    ("NOW", TaskStatus::Doing),
+    ("DONE", TaskStatus::Done),
+    ("CANCELED", TaskStatus::Canceled),
+    ("CANCELLED", TaskStatus::Canceled),
+];
+
+impl std::str::FromStr for TaskStatus {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "todo" => Ok(Self::Todo),
+            "doing" => Ok(Self::Doing),
+            "done" => Ok(Self::Done),
+            "canceled" => Ok(Self::Canceled),
+            other => Err(AppError::Internal(format!("Unknown task status '{}'", other))),
+        }
+    }
+}
+
+/// The task marker at the start of nav content, as plain `TODO` or as Roam's
+/// `{{[[TODO]]}}`, and its byte range in the content
+fn find_marker(content: &str) -> Option<(TaskStatus, Range<usize>)> {
+    let start = content.len() - content.trim_start().len();
+    let rest = &content[start..];
+    let (inner, wrapped) = match rest.strip_prefix("{{[[") {
+        Some(inner) => (inner, true),
+        None => (rest, false),
+    };
+
+    TASK_MARKERS.iter().find_map(|(word, status)| {
+        let after = inner.strip_prefix(word)?;
+        let after = if wrapped { after.strip_prefix("]]}}")? } else { after };
+        if after.is_empty() || after.starts_with(char::is_whitespace) {
+            Some((*status, start..content.len() - after.len()))
+        } else {
+            None
+        }
+    })
+}
+
+/// Date written after `DEADLINE:` or `SCHEDULED:`, as in `DEADLINE: <2026-10-17 Sat>`
+fn content_date(content: &str, keyword: &str) -> Option<NaiveDate> {
+    let (_, after) = content.split_once(keyword)?;
+    let after = after.trim_start();
+    let after = after.strip_prefix('<').unwrap_or(after);
+    NaiveDate::parse_from_str(after.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Date in a `due::` or `scheduled::` property: `2026-10-17`, or a link to a
+/// daily note such as `[[October 17th, 2026]]`
+fn property_date(properties: &[(String, String)], keys: &[&str]) -> Option<NaiveDate> {
+    let (_, value) = properties.iter().find(|(key, _)| keys.contains(&key.as_str()))?;
+    let value = value.trim();
+    let value = value
+        .strip_prefix("[[")
+        .and_then(|v| v.strip_suffix("]]"))
+        .unwrap_or(value);
+    parse_daily_title(value)
+}
+
+/// Parse the task in a nav: its content must start with a task marker.
+/// `properties` are the nav's parsed properties.
+pub fn parse_task(content: &str, properties: &[(String, String)]) -> Option<NavTask> {
+    let (status, _) = find_marker(content)?;
+
+    let priority = content.match_indices("[#").find_map(|(i, _)| {
+        let rest = &content[i + 2..];
+        let priority = rest.chars().next()?;
+        (matches!(priority, 'A'..='C') && rest[1..].starts_with(']')).then(|| priority.to_string())
+    });
+
+    Some(NavTask {
+        status,
+        priority,
+        due_date: content_date(content, "DEADLINE:").or_else(|| property_date(properties, &["due", "deadline"])),
+        scheduled_date: content_date(content, "SCHEDULED:").or_else(|| property_date(properties, &["scheduled"])),
+    })
+}
+
+/// Rewrite nav content to start with the marker of `status`, keeping Roam's
+/// `{{[[...]]}}` wrapping; content without a marker gets one prepended
+pub fn set_task_marker(content: &str, status: TaskStatus) -> String {
+    match find_marker(content) {
+        Some((_, range)) => {
+            let marker = if content[range.clone()].starts_with("{{[[") {
+                format!("{{{{[[{}]]}}}}", status.marker())
+            } else {
+                status.marker().to_string()
+            };
+            format!("{}{}{}", &content[..range.start], marker, &content[range.end..])
+        }
+        None if content.is_empty() => status.marker().to_string(),
+        None => format!("{} {}", status.marker(), content),
+    }
+}
+
+/// Replace the task indexed for a nav
+pub(crate) async fn sync_nav_task(
+    conn: &mut sqlx::PgConnection,
+    nav_id: Uuid,
+    content: &str,
+    properties: &[(String, String)],
+) -> Result<()> {
+    sqlx::query("DELETE FROM hulunote_nav_tasks WHERE nav_id = $1")
+        .bind(nav_id)
+        .execute(&mut *conn)
+        .await?;
+
+    if let Some(task) = parse_task(content, properties) {
+        sqlx::query(
+            r#"
+            INSERT INTO hulunote_nav_tasks (nav_id, status, priority, due_date, scheduled_date)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(nav_id)
+        .bind(task.status.as_str())
+        .bind(&task.priority)
+        .bind(task.due_date)
+        .bind(task.scheduled_date)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct TaskRow {
+    #[sqlx(flatten)]
+    nav: HulunoteNav,
+    status: String,
+    priority: Option<String>,
+    due_date: Option<NaiveDate>,
+    scheduled_date: Option<NaiveDate>,
+}
+
+/// List tasks across the notes of a database, open ones by default, with
+/// the earliest due first
+pub async fn get_tasks(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetTasksRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let database_ids = query_scope(
+        pool,
+        account_id,
+        req.note_id.as_deref(),
+        req.database_id.as_deref(),
+        req.database_name.as_deref().or(req.database.as_deref()),
+    )
+    .await?;
+
+    let statuses: Vec<&str> = req
+        .status
+        .as_deref()
+        .unwrap_or(&[TaskStatus::Todo, TaskStatus::Doing])
+        .iter()
+        .map(|status| status.as_str())
+        .collect();
+    let due_from = req.due_from.as_deref().map(|d| parse_date(d, "due-from")).transpose()?;
+    let due_to = req.due_to.as_deref().map(|d| parse_date(d, "due-to")).transpose()?;
+
+    let page = req.page.unwrap_or(1).max(1);
+    let size = req.size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    // The sort column is chosen from a fixed set, never from user input
+    let order_by = match req.sort {
+        TaskSort::Due => "t.due_date ASC NULLS LAST, t.scheduled_date ASC NULLS LAST, v.updated_at DESC",
+        TaskSort::Scheduled => "t.scheduled_date ASC NULLS LAST, t.due_date ASC NULLS LAST, v.updated_at DESC",
+        TaskSort::Priority => "t.priority ASC NULLS LAST, t.due_date ASC NULLS LAST, v.updated_at DESC",
+        TaskSort::Updated => "v.updated_at DESC",
+    };
+    let filters = r#"
+        FROM hulunote_nav_tasks t
+        JOIN hulunote_navs v ON v.id = t.nav_id
+        JOIN hulunote_notes n ON n.id::text = v.note_id
+        WHERE v.is_delete = false AND n.is_delete = false
+        AND v.database_id = ANY($1)
+        AND ($2::text IS NULL OR v.note_id = $2)
+        AND t.status = ANY($3)
+        AND ($4::date IS NULL OR t.due_date >= $4)
+        AND ($5::date IS NULL OR t.due_date <= $5)
+    "#;
+
+    let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) {}", filters))
+        .bind(&database_ids)
+        .bind(&req.note_id)
+        .bind(&statuses)
+        .bind(due_from)
+        .bind(due_to)
+        .fetch_one(pool)
+        .await?;
+    let all_pages = (count as f64 / size as f64).ceil() as i64;
+
+    let rows: Vec<TaskRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT v.id, v.parid, v.same_deep_order, v.content, v.account_id, v.last_account_id, v.note_id, v.database_id,
+               v.is_display, v.is_public, v.is_delete, v.properties, v.extra_id, v.created_at, v.updated_at,
+               t.status, t.priority, t.due_date, t.scheduled_date
+        {}
+        ORDER BY {}, v.id
+        LIMIT $6 OFFSET $7
+        "#,
+        filters, order_by
+    ))
+    .bind(&database_ids)
+    .bind(&req.note_id)
+    .bind(&statuses)
+    .bind(due_from)
+    .bind(due_to)
+    .bind(size)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let note_ids: Vec<String> = rows.iter().map(|r| r.nav.note_id.clone()).collect();
+    let titles: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+        "SELECT id::text, title FROM hulunote_notes WHERE id::text = ANY($1)"
+    )
+    .bind(&note_ids)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let nav_ids: Vec<Uuid> = rows.iter().map(|r| r.nav.id).collect();
+    let mut paths = get_nav_paths(pool, &nav_ids).await?;
+
+    let mut tasks = Vec::with_capacity(rows.len());
+    for row in rows {
+        let task = NavTask {
+            status: row.status.parse()?,
+            priority: row.priority,
+            due_date: row.due_date,
+            scheduled_date: row.scheduled_date,
+        };
+        tasks.push(json!({
+            "note-id": row.nav.note_id.clone(),
+            "note-title": titles.get(&row.nav.note_id).cloned().unwrap_or_default(),
+            "path": paths.remove(&row.nav.id).unwrap_or_default(),
+            "task": task,
+            "nav": NavInfo::from(row.nav)
+        }));
+    }
+
+    Ok(Json(json!({
+        "tasks": tasks,
+        "all-pages": all_pages
+    })))
+}
+
+/// Change a task's status by rewriting the marker at the start of its content
+pub async fn toggle_task(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<ToggleTaskRequest>,
+) -> Result<Json<Value>> {
+    let nav_uuid = Uuid::parse_str(&req.nav_id)
+        .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+    let (note_id, database_id) =
+        authorize_nav(state.pool.as_ref(), account_id, nav_uuid, Access::Write).await?;
+
+    let backend_ts = Utc::now().timestamp_millis();
+    let mut tx = state.pool.begin().await?;
+
+    let (content, is_delete): (String, bool) =
+        sqlx::query_as("SELECT content, is_delete FROM hulunote_navs WHERE id = $1 FOR UPDATE")
+            .bind(nav_uuid)
+            .fetch_one(&mut *tx)
+            .await?;
+    if is_delete {
+        return Err(AppError::NotFound("Nav not found".to_string()));
+    }
+
+    let status = match (req.status, find_marker(&content)) {
+        (Some(status), _) => status,
+        (None, Some((TaskStatus::Done, _))) => TaskStatus::Todo,
+        (None, _) => TaskStatus::Done,
+    };
+    let content = set_task_marker(&content, status);
+
+    let changes = NavChanges {
+        content: Some(&content),
+        ..Default::default()
+    };
+    let nav = update_nav(&mut tx, account_id, nav_uuid, &note_id, &database_id, &changes, "update").await?;
+    tx.commit().await?;
+
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(state.pool.as_ref(), nav_changed_event(&nav, &changes, backend_ts))
+        .await;
+
+    Ok(Json(json!({
+        "success": true,
+        "status": status,
+        "nav": NavInfo::from(nav),
+        "backend-ts": backend_ts
+    })))
+}
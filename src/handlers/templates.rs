@@ -0,0 +1,276 @@
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_database, authorize_nav, authorize_note, broadcast_navs, broadcast_reordered, check_nav_parent,
+    copy_subtree, daily_preferences, fetch_subtree, get_database_id, insert_note, next_order, parse_date,
+    rebalance_siblings, today_in, ws::WsEvent, Access, AppState, SubtreeTarget,
+};
+
+/// Where an instantiated template goes
+enum Destination {
+    /// Under an existing nav of a note
+    Nav(SubtreeTarget, HulunoteNote),
+    /// Into a note created for it
+    NewNote { database_id: String, title: String },
+}
+
+/// Substitute `{{name}}` variables in `text`; unknown variables are left as they are
+pub fn render_template(text: &str, variables: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        let end = start + 2 + len + 2;
+        rendered.push_str(&rest[..start]);
+        match variables.get(rest[start + 2..end - 2].trim()) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Get template notes
+pub async fn get_template_note_list(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetNoteListRequest>,
+) -> Result<Json<Value>> {
+    let database_id = get_database_id(
+        state.pool.as_ref(),
+        account_id,
+        req.database_id.as_deref(),
+        req.database_name.as_deref().or(req.database.as_deref()),
+        Access::Read,
+    )
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?;
+
+    let notes: Vec<HulunoteNote> = sqlx::query_as(
+        r#"
+        SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
+        FROM hulunote_notes
+        WHERE database_id = $1 AND is_delete = false AND is_template = true
+        ORDER BY title
+        "#,
+    )
+    .bind(database_id.to_string())
+    .fetch_all(state.pool.as_ref())
+    .await?;
+
+    let note_list: Vec<NoteInfo> = notes.into_iter().map(NoteInfo::from).collect();
+
+    Ok(Json(json!({
+        "note-list": note_list
+    })))
+}
+
+/// Copy the navs of a template note under a nav, or into a new note,
+/// substituting `{{date}}`, `{{title}}`, `{{user}}` and the request's own
+/// variables in their content
+pub async fn instantiate_template(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<InstantiateTemplateRequest>,
+) -> Result<Json<Value>> {
+    let pool = state.pool.as_ref();
+    let template_uuid = Uuid::parse_str(&req.template_id)
+        .map_err(|_| AppError::BadRequest("Invalid template ID".to_string()))?;
+
+    let template_database_id = authorize_note(pool, account_id, template_uuid, Access::Read).await?;
+    let template = fetch_note(pool, template_uuid).await?;
+    if template.is_delete {
+        return Err(AppError::NotFound("Template not found".to_string()));
+    }
+    if !template.is_template {
+        return Err(AppError::BadRequest("Note is not a template".to_string()));
+    }
+
+    let (timezone, date_format) = daily_preferences(pool, account_id).await?;
+    let date = match req.date.as_deref() {
+        Some(date) => parse_date(date, "date")?,
+        None => today_in(pool, &timezone).await?,
+    };
+    let (user,): (String,) =
+        sqlx::query_as("SELECT COALESCE(NULLIF(nickname, ''), username) FROM accounts WHERE id = $1")
+            .bind(account_id)
+            .fetch_one(pool)
+            .await?;
+
+    let mut variables = req.variables.clone();
+    variables.insert("date".to_string(), date.format(&date_format).to_string());
+    variables.insert("user".to_string(), user);
+
+    let backend_ts = Utc::now().timestamp_millis();
+
+    let destination = match req.parid.as_deref() {
+        Some(parid) => {
+            let nav_uuid = Uuid::parse_str(parid)
+                .map_err(|_| AppError::BadRequest("Invalid nav ID".to_string()))?;
+            let (note_id, database_id) = authorize_nav(pool, account_id, nav_uuid, Access::Write).await?;
+            let note_uuid = Uuid::parse_str(&note_id)
+                .map_err(|_| AppError::NotFound("Target note not found".to_string()))?;
+            let note = fetch_note(pool, note_uuid).await?;
+            if note.is_delete {
+                return Err(AppError::NotFound("Target note not found".to_string()));
+            }
+
+            let target = SubtreeTarget {
+                note_id,
+                database_id,
+                parid: parid.to_string(),
+            };
+            Destination::Nav(target, note)
+        }
+        None => {
+            let database_id = if req.database_id.is_some() || req.database_name.is_some() || req.database.is_some() {
+                get_database_id(
+                    pool,
+                    account_id,
+                    req.database_id.as_deref(),
+                    req.database_name.as_deref().or(req.database.as_deref()),
+                    Access::Write,
+                )
+                .await?
+                .ok_or_else(|| AppError::BadRequest("Database not found".to_string()))?
+            } else {
+                let database_id = Uuid::parse_str(&template_database_id)
+                    .map_err(|_| AppError::BadRequest("Database not found".to_string()))?;
+                authorize_database(pool, account_id, database_id, Access::Write).await?;
+                database_id
+            };
+
+            let title = match req.title.as_deref() {
+                Some(title) => title.to_string(),
+                None => render_template(&template.title, &variables),
+            };
+            if title.trim().is_empty() {
+                return Err(AppError::BadRequest("Note title cannot be empty".to_string()));
+            }
+
+            Destination::NewNote {
+                database_id: database_id.to_string(),
+                title,
+            }
+        }
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let (target, target_note, created) = match destination {
+        Destination::Nav(target, note) => {
+            check_nav_parent(&mut tx, &target.note_id, None, &target.parid).await?;
+            (target, note, false)
+        }
+        Destination::NewNote { database_id, title } => {
+            let note = insert_note(&mut tx, account_id, Uuid::new_v4(), Uuid::new_v4(), &database_id, &title)
+                .await?
+                .ok_or_else(|| AppError::BadRequest(format!("A note titled '{}' already exists", title)))?;
+            let target = SubtreeTarget {
+                note_id: note.id.to_string(),
+                database_id,
+                parid: note.root_nav_id.clone(),
+            };
+            (target, note, true)
+        }
+    };
+    variables.insert("title".to_string(), target_note.title.clone());
+
+    // The template's top-level navs, each copied with its descendants.
+    // In a new note they keep their order; under a nav they follow its children.
+    let tops: Vec<(Uuid, f32)> = sqlx::query_as(
+        r#"
+        SELECT id, same_deep_order FROM hulunote_navs
+        WHERE parid = $1 AND note_id = $2 AND is_delete = false
+        ORDER BY same_deep_order, created_at
+        "#,
+    )
+    .bind(&template.root_nav_id)
+    .bind(template.id.to_string())
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let first_order = match req.order {
+        Some(order) => order,
+        None if created => 0.0,
+        None => next_order(&mut tx, &target.parid).await?,
+    };
+
+    // Read every subtree before copying any, so that instantiating a template
+    // into itself does not copy the copies
+    let mut subtrees = Vec::with_capacity(tops.len());
+    for (top_id, top_order) in tops {
+        subtrees.push((fetch_subtree(&mut tx, top_id, false).await?, top_order));
+    }
+
+    let mut copies = Vec::new();
+    let mut new_ids = HashMap::new();
+    for (i, (mut subtree, top_order)) in subtrees.into_iter().enumerate() {
+        for nav in &mut subtree {
+            nav.content = render_template(&nav.content, &variables);
+        }
+
+        let order = if created && req.order.is_none() { top_order } else { first_order + i as f32 };
+        let (subtree_copies, subtree_ids) = copy_subtree(&mut tx, account_id, &subtree, &target, order).await?;
+        copies.extend(subtree_copies);
+        new_ids.extend(subtree_ids);
+    }
+
+    let reordered = rebalance_siblings(&mut tx, account_id, &target.parid).await?;
+    tx.commit().await?;
+
+    if created {
+        state
+            .ws_broadcaster
+            .broadcast_to_collaborators(
+                state.pool.as_ref(),
+                WsEvent::NoteCreated {
+                    note_id: target.note_id.clone(),
+                    database_id: target.database_id.clone(),
+                    backend_ts,
+                    title: target_note.title.clone(),
+                    root_nav_id: target_note.root_nav_id.clone(),
+                },
+            )
+            .await;
+    }
+    broadcast_navs(&state, &target.note_id, &target.database_id, backend_ts, &copies).await;
+    broadcast_reordered(&state, &target.note_id, &target.database_id, backend_ts, &reordered).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "note": NoteInfo::from(target_note),
+        "created": created,
+        "ids": new_ids,
+        "copied": copies.len(),
+        "reordered": reordered,
+        "backend-ts": backend_ts
+    })))
+}
+
+async fn fetch_note(pool: &sqlx::PgPool, note_id: Uuid) -> Result<HulunoteNote> {
+    sqlx::query_as(
+        r#"
+        SELECT id, title, database_id, root_nav_id, is_delete, is_public,
+               is_shortcut, is_template, account_id, pv, created_at, updated_at
+        FROM hulunote_notes
+        WHERE id = $1
+        "#,
+    )
+    .bind(note_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Note not found".to_string()))
+}
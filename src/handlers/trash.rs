@@ -0,0 +1,466 @@
+use axum::{extract::State, Extension, Json};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::*;
+
+use super::{
+    authorize_database, authorize_nav, authorize_note, broadcast_navs, fetch_navs, record_nav_revisions, ws::WsEvent,
+    Access, AppState,
+};
+
+const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// List the databases, notes and navs in the caller's trash.
+///
+/// Items deleted along with their parent (notes of a deleted database,
+/// navs of a deleted note or nav) are not listed separately; restoring
+/// the parent brings them back.
+pub async fn get_trash(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<GetTrashRequest>,
+) -> Result<Json<Value>> {
+    let size = req.size.unwrap_or(100).clamp(1, 1000);
+
+    let databases: Vec<TrashDatabase> = sqlx::query_as(
+        r#"
+        SELECT d.id, d.name, d.deleted_at
+        FROM hulunote_databases d
+        WHERE d.is_delete = true
+        AND (d.account_id = $1 OR EXISTS (
+            SELECT 1 FROM database_members m
+            WHERE m.database_id = d.id AND m.account_id = $1 AND m.role = 'owner'
+        ))
+        AND ($2::text IS NULL OR d.id::text = $2)
+        ORDER BY d.deleted_at DESC NULLS LAST
+        LIMIT $3
+        "#,
+    )
+    .bind(account_id)
+    .bind(&req.database_id)
+    .bind(size)
+    .fetch_all(state.pool.as_ref())
+    .await?;
+
+    let notes: Vec<TrashNote> = sqlx::query_as(
+        r#"
+        SELECT n.id, n.title, n.database_id, d.name AS database_name, n.deleted_at
+        FROM hulunote_notes n
+        JOIN hulunote_databases d ON d.id::text = n.database_id
+        WHERE n.is_delete = true
+        AND (d.account_id = $1 OR EXISTS (
+            SELECT 1 FROM database_members m
+            WHERE m.database_id = d.id AND m.account_id = $1 AND m.role IN ('owner', 'editor')
+        ))
+        AND ($2::text IS NULL OR n.database_id = $2)
+        AND NOT (d.is_delete AND d.deleted_at IS NOT DISTINCT FROM n.deleted_at)
+        ORDER BY n.deleted_at DESC NULLS LAST
+        LIMIT $3
+        "#,
+    )
+    .bind(account_id)
+    .bind(&req.database_id)
+    .bind(size)
+    .fetch_all(state.pool.as_ref())
+    .await?;
+
+    let navs: Vec<TrashNav> = sqlx::query_as(
+        r#"
+        SELECT v.id, v.content, v.note_id, n.title AS note_title, v.database_id, v.deleted_at
+        FROM hulunote_navs v
+        JOIN hulunote_notes n ON n.id::text = v.note_id
+        JOIN hulunote_databases d ON d.id::text = v.database_id
+        LEFT JOIN hulunote_navs p ON p.id = v.parid::uuid
+        WHERE v.is_delete = true AND v.parid <> $4
+        AND (d.account_id = $1 OR EXISTS (
+            SELECT 1 FROM database_members m
+            WHERE m.database_id = d.id AND m.account_id = $1 AND m.role IN ('owner', 'editor')
+        ))
+        AND ($2::text IS NULL OR v.database_id = $2)
+        AND NOT (d.is_delete AND d.deleted_at IS NOT DISTINCT FROM v.deleted_at)
+        AND NOT (n.is_delete AND n.deleted_at IS NOT DISTINCT FROM v.deleted_at)
+        AND NOT (COALESCE(p.is_delete, false) AND p.deleted_at IS NOT DISTINCT FROM v.deleted_at)
+        ORDER BY v.deleted_at DESC NULLS LAST
+        LIMIT $3
+        "#,
+    )
+    .bind(account_id)
+    .bind(&req.database_id)
+    .bind(size)
+    .bind(ROOT_NAV_ID)
+    .fetch_all(state.pool.as_ref())
+    .await?;
+
+    Ok(Json(json!({
+        "databases": databases,
+        "notes": notes,
+        "navs": navs
+    })))
+}
+
+fn parse_uuid(id: &str, what: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest(format!("Invalid {} ID", what)))
+}
+
+/// Restore a database, note or nav from the trash together with
+/// everything that was deleted with it
+pub async fn restore_from_trash(
+    State(state): State<AppState>,
+    Extension(account_id): Extension<i64>,
+    Json(req): Json<RestoreFromTrashRequest>,
+) -> Result<Json<Value>> {
+    let (databases, notes, navs) = match (&req.database_id, &req.note_id, &req.nav_id) {
+        (Some(id), None, None) => restore_database(&state, account_id, parse_uuid(id, "database")?).await?,
+        (None, Some(id), None) => restore_note_from_trash(&state, account_id, parse_uuid(id, "note")?).await?,
+        (None, None, Some(id)) => restore_nav_from_trash(&state, account_id, parse_uuid(id, "nav")?).await?,
+        _ => {
+            return Err(AppError::BadRequest(
+                "Exactly one of database-id, note-id or nav-id is required".to_string(),
+            ))
+        }
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "restored-databases": databases,
+        "restored-notes": notes,
+        "restored-navs": navs
+    })))
+}
+
+async fn restore_database(state: &AppState, account_id: i64, database_id: Uuid) -> Result<(usize, usize, usize)> {
+    let pool = state.pool.as_ref();
+    authorize_database(pool, account_id, database_id, Access::Manage).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let row: Option<(String, i64, Option<DateTime<Utc>>)> = sqlx::query_as(
+        "SELECT name, account_id, deleted_at FROM hulunote_databases WHERE id = $1 AND is_delete = true FOR UPDATE",
+    )
+    .bind(database_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let (name, owner_id, deleted_at) =
+        row.ok_or_else(|| AppError::BadRequest("Database is not in the trash".to_string()))?;
+
+    // Names are unique among the creator's live databases
+    let existing: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM hulunote_databases WHERE name = $1 AND account_id = $2 AND is_delete = false",
+    )
+    .bind(&name)
+    .bind(owner_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if existing.is_some() {
+        return Err(AppError::BadRequest(format!("Database '{}' already exists", name)));
+    }
+
+    sqlx::query(
+        "UPDATE hulunote_databases SET is_delete = false, deleted_at = NULL, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(database_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let notes = sqlx::query(
+        r#"
+        UPDATE hulunote_notes SET is_delete = false, deleted_at = NULL, updated_at = NOW()
+        WHERE database_id = $1 AND is_delete = true AND deleted_at IS NOT DISTINCT FROM $2
+        "#,
+    )
+    .bind(database_id.to_string())
+    .bind(deleted_at)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let navs: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        UPDATE hulunote_navs SET is_delete = false, deleted_at = NULL, updated_at = NOW()
+        WHERE database_id = $1 AND is_delete = true AND deleted_at IS NOT DISTINCT FROM $2
+        RETURNING id
+        "#,
+    )
+    .bind(database_id.to_string())
+    .bind(deleted_at)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let nav_ids: Vec<Uuid> = navs.into_iter().map(|(id,)| id).collect();
+    record_nav_revisions(&mut tx, &nav_ids, account_id, "restore").await?;
+
+    tx.commit().await?;
+
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(
+            pool,
+            WsEvent::DatabaseUpdated {
+                database_id: database_id.to_string(),
+                backend_ts: Utc::now().timestamp_millis(),
+                name: None,
+                is_public: None,
+                is_default: None,
+                is_delete: Some(false),
+            },
+        )
+        .await;
+
+    Ok((1, notes as usize, nav_ids.len()))
+}
+
+async fn restore_note_from_trash(state: &AppState, account_id: i64, note_id: Uuid) -> Result<(usize, usize, usize)> {
+    let pool = state.pool.as_ref();
+    let database_id = authorize_note(pool, account_id, note_id, Access::Write).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let database_deleted: Option<(bool,)> =
+        sqlx::query_as("SELECT is_delete FROM hulunote_databases WHERE id = $1::uuid")
+            .bind(&database_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    if database_deleted.is_some_and(|(deleted,)| deleted) {
+        return Err(AppError::BadRequest("Restore the note's database first".to_string()));
+    }
+
+    let deleted_at: Option<(Option<DateTime<Utc>>,)> = sqlx::query_as(
+        "SELECT deleted_at FROM hulunote_notes WHERE id = $1 AND is_delete = true FOR UPDATE",
+    )
+    .bind(note_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let (deleted_at,) =
+        deleted_at.ok_or_else(|| AppError::BadRequest("Note is not in the trash".to_string()))?;
+
+    sqlx::query(
+        "UPDATE hulunote_notes SET is_delete = false, deleted_at = NULL, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(note_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let navs: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        UPDATE hulunote_navs SET is_delete = false, deleted_at = NULL, updated_at = NOW()
+        WHERE note_id = $1 AND is_delete = true AND deleted_at IS NOT DISTINCT FROM $2
+        RETURNING id
+        "#,
+    )
+    .bind(note_id.to_string())
+    .bind(deleted_at)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let nav_ids: Vec<Uuid> = navs.into_iter().map(|(id,)| id).collect();
+    record_nav_revisions(&mut tx, &nav_ids, account_id, "restore").await?;
+
+    tx.commit().await?;
+
+    state
+        .ws_broadcaster
+        .broadcast_to_collaborators(
+            pool,
+            WsEvent::NoteUpdated {
+                note_id: note_id.to_string(),
+                database_id,
+                backend_ts: Utc::now().timestamp_millis(),
+                title: None,
+                is_public: None,
+                is_shortcut: None,
+                is_template: None,
+                is_delete: Some(false),
+                rewritten_navs: Vec::new(),
+            },
+        )
+        .await;
+
+    Ok((0, 1, nav_ids.len()))
+}
+
+async fn restore_nav_from_trash(state: &AppState, account_id: i64, nav_id: Uuid) -> Result<(usize, usize, usize)> {
+    let pool = state.pool.as_ref();
+    let (note_id, database_id) = authorize_nav(pool, account_id, nav_id, Access::Write).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let note_deleted: Option<(bool,)> =
+        sqlx::query_as("SELECT is_delete FROM hulunote_notes WHERE id = $1::uuid")
+            .bind(&note_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    if note_deleted.is_some_and(|(deleted,)| deleted) {
+        return Err(AppError::BadRequest("Restore the nav's note first".to_string()));
+    }
+
+    let deleted_at: Option<(Option<DateTime<Utc>>,)> = sqlx::query_as(
+        "SELECT deleted_at FROM hulunote_navs WHERE id = $1 AND is_delete = true FOR UPDATE",
+    )
+    .bind(nav_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let (deleted_at,) =
+        deleted_at.ok_or_else(|| AppError::BadRequest("Nav is not in the trash".to_string()))?;
+
+    // The nav and the descendants that were deleted with it
+    let navs: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, note_id FROM hulunote_navs WHERE id = $1
+            UNION
+            SELECT c.id, c.note_id FROM hulunote_navs c JOIN subtree s ON c.parid = s.id::text AND c.note_id = s.note_id
+        )
+        UPDATE hulunote_navs SET is_delete = false, deleted_at = NULL, updated_at = NOW()
+        WHERE id IN (SELECT id FROM subtree)
+        AND is_delete = true AND deleted_at IS NOT DISTINCT FROM $2
+        RETURNING id
+        "#,
+    )
+    .bind(nav_id)
+    .bind(deleted_at)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let nav_ids: Vec<Uuid> = navs.into_iter().map(|(id,)| id).collect();
+    record_nav_revisions(&mut tx, &nav_ids, account_id, "restore").await?;
+    let restored = fetch_navs(&mut tx, &nav_ids).await?;
+
+    tx.commit().await?;
+
+    broadcast_navs(state, &note_id, &database_id, Utc::now().timestamp_millis(), &restored).await;
+    Ok((0, 0, nav_ids.len()))
+}
+
+/// Number of items removed by one purge
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PurgeStats {
+    pub databases: usize,
+    pub notes: usize,
+    pub navs: usize,
+}
+
+/// Permanently remove items that have been in the trash longer than
+/// `retention`, with everything under them: the notes of a database, the
+/// navs of a note, the descendants of a nav, and their links, history and
+/// share links.
+pub async fn purge_trash(pool: &PgPool, retention: chrono::Duration) -> Result<PurgeStats> {
+    let cutoff = Utc::now() - retention;
+    let mut tx = pool.begin().await?;
+
+    let databases: Vec<(String,)> = sqlx::query_as(
+        "DELETE FROM hulunote_databases WHERE is_delete = true AND deleted_at < $1 RETURNING id::text",
+    )
+    .bind(cutoff)
+    .fetch_all(&mut *tx)
+    .await?;
+    let database_ids: Vec<String> = databases.into_iter().map(|(id,)| id).collect();
+
+    let notes: Vec<(String,)> = sqlx::query_as(
+        r#"
+        DELETE FROM hulunote_notes
+        WHERE (is_delete = true AND deleted_at < $1) OR database_id = ANY($2)
+        RETURNING id::text
+        "#,
+    )
+    .bind(cutoff)
+    .bind(&database_ids)
+    .fetch_all(&mut *tx)
+    .await?;
+    let note_ids: Vec<String> = notes.into_iter().map(|(id,)| id).collect();
+
+    let navs: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE doomed AS (
+            SELECT id, note_id FROM hulunote_navs
+            WHERE (is_delete = true AND deleted_at < $1)
+            OR note_id = ANY($2)
+            OR database_id = ANY($3)
+            UNION
+            SELECT c.id, c.note_id FROM hulunote_navs c JOIN doomed d ON c.parid = d.id::text AND c.note_id = d.note_id
+        )
+        DELETE FROM hulunote_navs WHERE id IN (SELECT id FROM doomed)
+        RETURNING id
+        "#,
+    )
+    .bind(cutoff)
+    .bind(&note_ids)
+    .bind(&database_ids)
+    .fetch_all(&mut *tx)
+    .await?;
+    let nav_ids: Vec<Uuid> = navs.into_iter().map(|(id,)| id).collect();
+
+    sqlx::query("DELETE FROM hulunote_links WHERE source_nav_id = ANY($1)")
+        .bind(&nav_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM hulunote_nav_tags WHERE nav_id = ANY($1)")
+        .bind(&nav_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM hulunote_nav_properties WHERE nav_id = ANY($1)")
+        .bind(&nav_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM hulunote_nav_tasks WHERE nav_id = ANY($1)")
+        .bind(&nav_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM hulunote_nav_revisions WHERE nav_id = ANY($1)")
+        .bind(&nav_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    let nav_id_strings: Vec<String> = nav_ids.iter().map(Uuid::to_string).collect();
+    sqlx::query(
+        r#"
+        DELETE FROM hulunote_share_links
+        WHERE (target_type = 'database' AND target_id = ANY($1))
+        OR (target_type = 'note' AND target_id = ANY($2))
+        OR (target_type = 'nav' AND target_id = ANY($3))
+        "#,
+    )
+    .bind(&database_ids)
+    .bind(&note_ids)
+    .bind(&nav_id_strings)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM database_members WHERE database_id = ANY($1::uuid[])")
+        .bind(&database_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(PurgeStats {
+        databases: database_ids.len(),
+        notes: note_ids.len(),
+        navs: nav_ids.len(),
+    })
+}
+
+/// Run `purge_trash` in the background every `interval`
+pub fn spawn_trash_purge(pool: PgPool, retention_days: i64, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match purge_trash(&pool, chrono::Duration::days(retention_days)).await {
+                Ok(stats) if stats != PurgeStats::default() => {
+                    tracing::info!(
+                        "Purged trash: {} databases, {} notes, {} navs",
+                        stats.databases,
+                        stats.notes,
+                        stats.navs
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Trash purge failed: {}", e),
+            }
+        }
+    })
+}
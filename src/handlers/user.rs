@@ -1,12 +1,13 @@
 use axum::extract::{Extension, Multipart, State};
 use axum::Json;
+use chrono::Utc;
 use serde_json::{json, Value};
 
 use crate::error::{AppError, Result};
 use crate::middleware::generate_token_with_hours;
 use crate::models::*;
 
-use super::AppState;
+use super::{is_valid_date_format, is_valid_timezone, ws::WsEvent, AppState};
 
 /// Get current user profile
 pub async fn get_profile(
@@ -17,7 +18,8 @@ pub async fn get_profile(
         r#"
         SELECT id, username, nickname, password, mail, avatar, introduction,
                invitation_code, cell_number, oauth_key, need_update_password,
-               is_new_user, expires_at, registration_code, created_at, updated_at
+               is_new_user, expires_at, registration_code, timezone, date_format,
+               created_at, updated_at
         FROM accounts
         WHERE id = $1
         "#,
@@ -31,30 +33,65 @@ pub async fn get_profile(
     })))
 }
 
-/// Update user profile (nickname, introduction)
+/// Update user profile (nickname, introduction, timezone, date format)
 pub async fn update_profile(
     State(state): State<AppState>,
     Extension(account_id): Extension<i64>,
     Json(req): Json<UpdateProfileRequest>,
 ) -> Result<Json<Value>> {
+    if let Some(timezone) = req.timezone.as_deref() {
+        if !is_valid_timezone(state.pool.as_ref(), timezone).await? {
+            return Err(AppError::BadRequest(format!("Unknown timezone '{}'", timezone)));
+        }
+    }
+    if let Some(date_format) = req.date_format.as_deref() {
+        if !is_valid_date_format(date_format) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid date format '{}'; it must include the year, month and day",
+                date_format
+            )));
+        }
+    }
+
     let account: Account = sqlx::query_as(
         r#"
         UPDATE accounts
         SET nickname = COALESCE($2, nickname),
             introduction = COALESCE($3, introduction),
+            timezone = COALESCE($4, timezone),
+            date_format = COALESCE($5, date_format),
             updated_at = now()
         WHERE id = $1
         RETURNING id, username, nickname, password, mail, avatar, introduction,
                   invitation_code, cell_number, oauth_key, need_update_password,
-                  is_new_user, expires_at, registration_code, created_at, updated_at
+                  is_new_user, expires_at, registration_code, timezone, date_format,
+                  created_at, updated_at
         "#,
     )
     .bind(account_id)
     .bind(&req.nickname)
     .bind(&req.introduction)
+    .bind(&req.timezone)
+    .bind(&req.date_format)
     .fetch_one(state.pool.as_ref())
     .await?;
 
+    state
+        .ws_broadcaster
+        .broadcast(
+            state.pool.as_ref(),
+            account_id,
+            WsEvent::ProfileUpdated {
+                backend_ts: Utc::now().timestamp_millis(),
+                nickname: req.nickname,
+                introduction: req.introduction,
+                timezone: req.timezone,
+                date_format: req.date_format,
+                avatar: None,
+            },
+        )
+        .await;
+
     Ok(Json(json!({
         "profile": AccountInfo::from(account)
     })))
@@ -131,7 +168,8 @@ pub async fn upload_avatar(
         WHERE id = $1
         RETURNING id, username, nickname, password, mail, avatar, introduction,
                   invitation_code, cell_number, oauth_key, need_update_password,
-                  is_new_user, expires_at, registration_code, created_at, updated_at
+                  is_new_user, expires_at, registration_code, timezone, date_format,
+                  created_at, updated_at
         "#,
     )
     .bind(account_id)
@@ -139,6 +177,22 @@ pub async fn upload_avatar(
     .fetch_one(state.pool.as_ref())
     .await?;
 
+    state
+        .ws_broadcaster
+        .broadcast(
+            state.pool.as_ref(),
+            account_id,
+            WsEvent::ProfileUpdated {
+                backend_ts: Utc::now().timestamp_millis(),
+                nickname: None,
+                introduction: None,
+                timezone: None,
+                date_format: None,
+                avatar: Some(avatar_url.clone()),
+            },
+        )
+        .await;
+
     Ok(Json(json!({
         "profile": AccountInfo::from(account),
         "avatar_url": avatar_url
@@ -155,7 +209,8 @@ pub async fn generate_user_token(
         r#"
         SELECT id, username, nickname, password, mail, avatar, introduction,
                invitation_code, cell_number, oauth_key, need_update_password,
-               is_new_user, expires_at, registration_code, created_at, updated_at
+               is_new_user, expires_at, registration_code, timezone, date_format,
+               created_at, updated_at
         FROM accounts
         WHERE id = $1
         "#,
@@ -5,29 +5,152 @@ use axum::{
     },
     response::Response,
 };
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::collections::HashMap;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
 
 use crate::config::Config;
-use crate::models::Claims;
+use crate::error::{AppError, Result};
+use crate::models::{Claims, DatabaseInfo, NavContent, NavInfo, NavOrder, WsNavOp, WsPresence, WsResume, WsSubscription};
 
-/// Event types broadcast over WebSocket
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::{apply_nav_op, authorize_note, database_account_ids, database_role, Access, AppState};
+
+/// Most events replayed at once; a client further behind is told to resync
+const REPLAY_LIMIT: i64 = 1000;
+
+/// How long a connection stays in a note's presence without a heartbeat
+pub const PRESENCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A connection that has a note open
+#[derive(Debug, Clone, Serialize)]
+pub struct Viewer {
+    #[serde(rename = "connection-id")]
+    pub connection_id: u64,
+    #[serde(rename = "account-id")]
+    pub account_id: i64,
+    pub username: String,
+    /// The nav being edited, if any
+    #[serde(rename = "nav-id")]
+    pub nav_id: Option<String>,
+}
+
+/// Where a connection is, as tracked by the broadcaster
+#[derive(Debug)]
+struct Presence {
+    viewer: Viewer,
+    note_id: String,
+    database_id: String,
+    last_seen: Instant,
+}
+
+/// Event types broadcast over WebSocket. Every mutation sends one event
+/// with the fields it changed (unchanged fields are left out) and the
+/// `backend-ts` it happened at.
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type")]
 pub enum WsEvent {
+    #[serde(rename = "database_created")]
+    DatabaseCreated {
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        database: DatabaseInfo,
+    },
+    #[serde(rename = "database_updated")]
+    DatabaseUpdated {
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(rename = "is-public", skip_serializing_if = "Option::is_none")]
+        is_public: Option<bool>,
+        #[serde(rename = "is-default", skip_serializing_if = "Option::is_none")]
+        is_default: Option<bool>,
+        /// Only ever `false`: a database moved back out of the trash
+        #[serde(rename = "is-delete", skip_serializing_if = "Option::is_none")]
+        is_delete: Option<bool>,
+    },
+    /// The database and everything in it moved to the trash
+    #[serde(rename = "database_deleted")]
+    DatabaseDeleted {
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+    },
     #[serde(rename = "note_created")]
     NoteCreated {
         #[serde(rename = "note-id")]
         note_id: String,
         #[serde(rename = "database-id")]
         database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
         title: String,
         #[serde(rename = "root-nav-id")]
         root_nav_id: String,
     },
+    #[serde(rename = "note_updated")]
+    NoteUpdated {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(rename = "is-public", skip_serializing_if = "Option::is_none")]
+        is_public: Option<bool>,
+        #[serde(rename = "is-shortcut", skip_serializing_if = "Option::is_none")]
+        is_shortcut: Option<bool>,
+        #[serde(rename = "is-template", skip_serializing_if = "Option::is_none")]
+        is_template: Option<bool>,
+        /// Only ever `false`: a note moved back out of the trash
+        #[serde(rename = "is-delete", skip_serializing_if = "Option::is_none")]
+        is_delete: Option<bool>,
+        /// Navs whose `[[Old Title]]` references were rewritten by a rename
+        #[serde(rename = "rewritten-navs", skip_serializing_if = "Vec::is_empty")]
+        rewritten_navs: Vec<NavContent>,
+    },
+    #[serde(rename = "note_deleted")]
+    NoteDeleted {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+    },
+    /// Notes added by one import, as listed in its response
+    #[serde(rename = "notes_imported")]
+    NotesImported {
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        notes: Vec<Value>,
+    },
+    #[serde(rename = "nav_created")]
+    NavCreated {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        nav: NavInfo,
+    },
     #[serde(rename = "nav_updated")]
     NavUpdated {
         #[serde(rename = "nav-id")]
@@ -36,26 +159,218 @@ pub enum WsEvent {
         note_id: String,
         #[serde(rename = "database-id")]
         database_id: String,
-        content: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parid: Option<String>,
+        #[serde(rename = "same-deep-order", skip_serializing_if = "Option::is_none")]
+        same_deep_order: Option<f32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        properties: Option<String>,
+        #[serde(rename = "is-display", skip_serializing_if = "Option::is_none")]
+        is_display: Option<bool>,
+        #[serde(rename = "is-public", skip_serializing_if = "Option::is_none")]
+        is_public: Option<bool>,
+        /// Only ever `false`: a nav moved back out of the trash
+        #[serde(rename = "is-delete", skip_serializing_if = "Option::is_none")]
+        is_delete: Option<bool>,
+    },
+    #[serde(rename = "nav_deleted")]
+    NavDeleted {
+        #[serde(rename = "nav-id")]
+        nav_id: String,
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+    },
+    /// Every nav changed by one batch, move or copy, in their final state
+    #[serde(rename = "navs_batch")]
+    NavsBatch {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        navs: Vec<NavInfo>,
+    },
+    /// Siblings renumbered because their orders got too close to insert between
+    #[serde(rename = "navs_reordered")]
+    NavsReordered {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        navs: Vec<NavOrder>,
     },
+    /// Sent only to the account's own connections
+    #[serde(rename = "profile_updated")]
+    ProfileUpdated {
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        nickname: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        introduction: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+        #[serde(rename = "date-format", skip_serializing_if = "Option::is_none")]
+        date_format: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        avatar: Option<String>,
+    },
+    /// Someone opened the note. Presence events are only sent to the other
+    /// connections that have the note open, and are not replayed.
+    #[serde(rename = "presence_joined")]
+    PresenceJoined {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        viewer: Viewer,
+    },
+    /// Someone moved to editing another nav, or stopped editing
+    #[serde(rename = "presence_cursor")]
+    PresenceCursor {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        #[serde(rename = "connection-id")]
+        connection_id: u64,
+        #[serde(rename = "account-id")]
+        account_id: i64,
+        #[serde(rename = "nav-id")]
+        nav_id: Option<String>,
+    },
+    /// Someone closed the note, disconnected or stopped sending heartbeats
+    #[serde(rename = "presence_left")]
+    PresenceLeft {
+        #[serde(rename = "note-id")]
+        note_id: String,
+        #[serde(rename = "database-id")]
+        database_id: String,
+        #[serde(rename = "backend-ts")]
+        backend_ts: i64,
+        #[serde(rename = "connection-id")]
+        connection_id: u64,
+        #[serde(rename = "account-id")]
+        account_id: i64,
+    },
+}
+
+impl WsEvent {
+    /// The database the event happened in, if any
+    pub fn database_id(&self) -> Option<&str> {
+        match self {
+            WsEvent::DatabaseCreated { database_id, .. }
+            | WsEvent::DatabaseUpdated { database_id, .. }
+            | WsEvent::DatabaseDeleted { database_id, .. }
+            | WsEvent::NoteCreated { database_id, .. }
+            | WsEvent::NoteUpdated { database_id, .. }
+            | WsEvent::NoteDeleted { database_id, .. }
+            | WsEvent::NotesImported { database_id, .. }
+            | WsEvent::NavCreated { database_id, .. }
+            | WsEvent::NavUpdated { database_id, .. }
+            | WsEvent::NavDeleted { database_id, .. }
+            | WsEvent::NavsBatch { database_id, .. }
+            | WsEvent::NavsReordered { database_id, .. }
+            | WsEvent::PresenceJoined { database_id, .. }
+            | WsEvent::PresenceCursor { database_id, .. }
+            | WsEvent::PresenceLeft { database_id, .. } => Some(database_id),
+            WsEvent::ProfileUpdated { .. } => None,
+        }
+    }
+
+    /// The note the event happened in, if any
+    pub fn note_id(&self) -> Option<&str> {
+        match self {
+            WsEvent::NoteCreated { note_id, .. }
+            | WsEvent::NoteUpdated { note_id, .. }
+            | WsEvent::NoteDeleted { note_id, .. }
+            | WsEvent::NavCreated { note_id, .. }
+            | WsEvent::NavUpdated { note_id, .. }
+            | WsEvent::NavDeleted { note_id, .. }
+            | WsEvent::NavsBatch { note_id, .. }
+            | WsEvent::NavsReordered { note_id, .. }
+            | WsEvent::PresenceJoined { note_id, .. }
+            | WsEvent::PresenceCursor { note_id, .. }
+            | WsEvent::PresenceLeft { note_id, .. } => Some(note_id),
+            _ => None,
+        }
+    }
+}
+
+/// Messages a client may send over the WebSocket
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum WsClientMessage {
+    /// Create, update, move or delete one nav. Answered with a
+    /// `nav_op_ack` and fanned out to collaborators.
+    #[serde(rename = "nav_op")]
+    NavOp(WsNavOp),
+    /// Only receive events of these databases and notes (and the account's
+    /// own profile events)
+    #[serde(rename = "subscribe")]
+    Subscribe(WsSubscription),
+    #[serde(rename = "unsubscribe")]
+    Unsubscribe(WsSubscription),
+    /// Replay the events missed since a `seq` or `backend-ts`
+    #[serde(rename = "resume")]
+    Resume(WsResume),
+    /// Announce the open note and the nav being edited. Resent as a
+    /// heartbeat; any other message counts as one too.
+    #[serde(rename = "presence")]
+    Presence(WsPresence),
+    /// Close the open note
+    #[serde(rename = "leave")]
+    Leave,
+    #[serde(rename = "ping")]
+    Ping,
+}
+
+/// An event as sent to the connections of one account
+#[derive(Debug, Clone)]
+pub struct WsMessage {
+    /// Position in the event log, `None` if the event could not be logged
+    pub seq: Option<i64>,
+    pub database_id: Option<String>,
+    pub note_id: Option<String>,
+    /// The event as JSON, with its `seq`
+    pub text: String,
+    /// Set on presence events: the connection they came from. They only go
+    /// to the other connections that have the note open.
+    pub from_connection: Option<u64>,
 }
 
 /// Manages WebSocket connections per account
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct WsBroadcaster {
     /// Map of account_id -> broadcast sender
-    channels: Arc<RwLock<HashMap<i64, broadcast::Sender<String>>>>,
+    channels: Arc<RwLock<HashMap<i64, broadcast::Sender<WsMessage>>>>,
+    /// Map of connection id -> the note it has open
+    presence: Arc<RwLock<HashMap<u64, Presence>>>,
+    next_connection_id: Arc<AtomicU64>,
 }
 
 impl WsBroadcaster {
     pub fn new() -> Self {
-        Self {
-            channels: Arc::new(RwLock::new(HashMap::new())),
-        }
+        Self::default()
     }
 
     /// Get or create a broadcast channel for an account
-    pub async fn get_sender(&self, account_id: i64) -> broadcast::Sender<String> {
+    pub async fn get_sender(&self, account_id: i64) -> broadcast::Sender<WsMessage> {
         let channels = self.channels.read().await;
         if let Some(sender) = channels.get(&account_id) {
             return sender.clone();
@@ -72,15 +387,634 @@ impl WsBroadcaster {
         tx
     }
 
-    /// Broadcast an event to all WebSocket connections for an account
-    pub async fn broadcast(&self, account_id: i64, event: WsEvent) {
+    /// Subscribe to an account's events. Returns the receiver and the last
+    /// logged `seq`; the receiver gets every event sent after it subscribed,
+    /// including ones logged just before that `seq`.
+    pub async fn connect(&self, pool: &PgPool, account_id: i64) -> Result<(broadcast::Receiver<WsMessage>, i64)> {
+        let receiver = self.get_sender(account_id).await.subscribe();
+        Ok((receiver, latest_seq(pool).await?))
+    }
+
+    /// Send an event to the connections of one account only
+    pub async fn broadcast(&self, pool: &PgPool, account_id: i64, event: WsEvent) {
+        self.publish(pool, Some(account_id), vec![account_id], event).await;
+    }
+
+    /// Broadcast an event to every account its database is shared with
+    pub async fn broadcast_to_collaborators(&self, pool: &PgPool, event: WsEvent) {
+        let Some(database_id) = event.database_id() else {
+            return;
+        };
+        let account_ids = match database_account_ids(pool, database_id).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                tracing::warn!("Cannot find collaborators of database {}: {}", database_id, e);
+                return;
+            }
+        };
+        self.publish(pool, None, account_ids, event).await;
+    }
+
+    /// Log an event, number it and send it to the given accounts. Events
+    /// published at the same time may be sent slightly out of `seq` order;
+    /// connections drop the ones they already sent by `seq`.
+    async fn publish(&self, pool: &PgPool, account_id: Option<i64>, recipients: Vec<i64>, event: WsEvent) {
+        let mut payload = serde_json::to_value(&event).unwrap_or_default();
+        let database_id = event.database_id().map(str::to_string);
+        let note_id = event.note_id().map(str::to_string);
+        let backend_ts = payload["backend-ts"].as_i64().unwrap_or_else(|| Utc::now().timestamp_millis());
+
+        let logged = sqlx::query_scalar(
+            r#"
+            INSERT INTO ws_events (database_id, note_id, account_id, backend_ts, payload)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING seq
+            "#,
+        )
+        .bind(&database_id)
+        .bind(&note_id)
+        .bind(account_id)
+        .bind(backend_ts)
+        .bind(&payload)
+        .fetch_one(pool)
+        .await;
+        let seq = match logged {
+            Ok(seq) => {
+                payload["seq"] = json!(seq);
+                Some(seq)
+            }
+            Err(e) => {
+                // Still deliver it live; it just cannot be replayed
+                tracing::warn!("Cannot log WebSocket event: {}", e);
+                None
+            }
+        };
+
+        let msg = WsMessage {
+            seq,
+            database_id,
+            note_id,
+            text: payload.to_string(),
+            from_connection: None,
+        };
         let channels = self.channels.read().await;
-        if let Some(sender) = channels.get(&account_id) {
-            let msg = serde_json::to_string(&event).unwrap_or_default();
-            // Ignore send errors (no active receivers)
-            let _ = sender.send(msg);
+        for account_id in recipients {
+            if let Some(sender) = channels.get(&account_id) {
+                // Ignore send errors (no active receivers)
+                let _ = sender.send(msg.clone());
+            }
+        }
+    }
+
+    fn connection_id(&self) -> u64 {
+        self.next_connection_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Record the note a connection has open and the nav it is editing,
+    /// tell the note's other viewers what changed, and return them
+    pub async fn set_presence(&self, viewer: Viewer, note_id: &str, database_id: &str) -> Vec<Viewer> {
+        let connection_id = viewer.connection_id;
+        let entry = Presence {
+            viewer: viewer.clone(),
+            note_id: note_id.to_string(),
+            database_id: database_id.to_string(),
+            last_seen: Instant::now(),
+        };
+        let (previous, others) = {
+            let mut presence = self.presence.write().await;
+            let previous = presence.insert(connection_id, entry);
+            let others: Vec<Viewer> = presence
+                .values()
+                .filter(|p| p.note_id == note_id && p.viewer.connection_id != connection_id)
+                .map(|p| p.viewer.clone())
+                .collect();
+            (previous, others)
+        };
+
+        let backend_ts = Utc::now().timestamp_millis();
+        match previous {
+            Some(previous) if previous.note_id == note_id => {
+                if previous.viewer.nav_id != viewer.nav_id {
+                    let event = WsEvent::PresenceCursor {
+                        note_id: note_id.to_string(),
+                        database_id: database_id.to_string(),
+                        backend_ts,
+                        connection_id,
+                        account_id: viewer.account_id,
+                        nav_id: viewer.nav_id,
+                    };
+                    self.send_presence(connection_id, event).await;
+                }
+            }
+            previous => {
+                if let Some(previous) = previous {
+                    self.send_presence(connection_id, left_event(&previous, backend_ts)).await;
+                }
+                let event = WsEvent::PresenceJoined {
+                    note_id: note_id.to_string(),
+                    database_id: database_id.to_string(),
+                    backend_ts,
+                    viewer,
+                };
+                self.send_presence(connection_id, event).await;
+            }
+        }
+        others
+    }
+
+    /// Keep a connection's presence from expiring
+    pub async fn touch_presence(&self, connection_id: u64) {
+        if let Some(presence) = self.presence.write().await.get_mut(&connection_id) {
+            presence.last_seen = Instant::now();
+        }
+    }
+
+    /// Remove a connection from the note it has open
+    pub async fn leave_presence(&self, connection_id: u64) {
+        let removed = self.presence.write().await.remove(&connection_id);
+        if let Some(presence) = removed {
+            let event = left_event(&presence, Utc::now().timestamp_millis());
+            self.send_presence(connection_id, event).await;
         }
     }
+
+    /// Remove the connections that sent no heartbeat for `timeout`
+    pub async fn expire_presence(&self, timeout: Duration) {
+        let expired: Vec<Presence> = {
+            let mut presence = self.presence.write().await;
+            let ids: Vec<u64> = presence
+                .iter()
+                .filter(|(_, p)| p.last_seen.elapsed() >= timeout)
+                .map(|(id, _)| *id)
+                .collect();
+            ids.iter().filter_map(|id| presence.remove(id)).collect()
+        };
+
+        let backend_ts = Utc::now().timestamp_millis();
+        for presence in expired {
+            let event = left_event(&presence, backend_ts);
+            self.send_presence(presence.viewer.connection_id, event).await;
+        }
+    }
+
+    /// Expire presence every `interval`, after `PRESENCE_TIMEOUT`
+    pub fn spawn_presence_sweep(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let broadcaster = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                broadcaster.expire_presence(PRESENCE_TIMEOUT).await;
+            }
+        })
+    }
+
+    /// Send a presence event to the accounts of everyone else who has its
+    /// note open
+    async fn send_presence(&self, from_connection: u64, event: WsEvent) {
+        let note_id = event.note_id().map(str::to_string);
+        let account_ids: BTreeSet<i64> = self
+            .presence
+            .read()
+            .await
+            .values()
+            .filter(|p| Some(&p.note_id) == note_id.as_ref() && p.viewer.connection_id != from_connection)
+            .map(|p| p.viewer.account_id)
+            .collect();
+
+        let msg = WsMessage {
+            seq: None,
+            database_id: event.database_id().map(str::to_string),
+            note_id,
+            text: serde_json::to_string(&event).unwrap_or_default(),
+            from_connection: Some(from_connection),
+        };
+        let channels = self.channels.read().await;
+        for account_id in account_ids {
+            if let Some(sender) = channels.get(&account_id) {
+                let _ = sender.send(msg.clone());
+            }
+        }
+    }
+}
+
+fn left_event(presence: &Presence, backend_ts: i64) -> WsEvent {
+    WsEvent::PresenceLeft {
+        note_id: presence.note_id.clone(),
+        database_id: presence.database_id.clone(),
+        backend_ts,
+        connection_id: presence.viewer.connection_id,
+        account_id: presence.viewer.account_id,
+    }
+}
+
+/// The `seq` of the last logged event
+async fn latest_seq(pool: &PgPool) -> Result<i64> {
+    Ok(sqlx::query_scalar("SELECT COALESCE(MAX(seq), 0) FROM ws_events")
+        .fetch_one(pool)
+        .await?)
+}
+
+/// Delete logged events older than `older_than`
+pub async fn purge_events(pool: &PgPool, older_than: chrono::Duration) -> Result<u64> {
+    let cutoff = (Utc::now() - older_than).timestamp_millis();
+    Ok(sqlx::query("DELETE FROM ws_events WHERE backend_ts < $1")
+        .bind(cutoff)
+        .execute(pool)
+        .await?
+        .rows_affected())
+}
+
+/// Run `purge_events` in the background every `interval`, keeping
+/// `retention_days` of events
+pub fn spawn_event_purge(pool: PgPool, retention_days: i64, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match purge_events(&pool, chrono::Duration::days(retention_days)).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Purged {} WebSocket events", purged),
+                Err(e) => tracing::error!("WebSocket event purge failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Databases and notes a connection receives events for. With none it
+/// receives every event of every database shared with the account.
+#[derive(Debug, Default)]
+struct Subscriptions {
+    databases: BTreeSet<String>,
+    notes: BTreeSet<String>,
+}
+
+impl Subscriptions {
+    /// Whether an event of this database and note is sent. Events outside
+    /// any database (profile changes) always are.
+    fn wants(&self, database_id: Option<&str>, note_id: Option<&str>) -> bool {
+        let Some(database_id) = database_id else {
+            return true;
+        };
+        (self.databases.is_empty() && self.notes.is_empty())
+            || self.databases.contains(database_id)
+            || note_id.is_some_and(|id| self.notes.contains(id))
+    }
+
+    fn to_json(&self, reply_type: &str) -> Value {
+        json!({
+            "type": reply_type,
+            "success": true,
+            "database-ids": self.databases,
+            "note-ids": self.notes
+        })
+    }
+}
+
+/// One WebSocket connection: what it is subscribed to and how far through
+/// the event log it has been sent
+pub struct WsConnection {
+    id: u64,
+    account_id: i64,
+    username: String,
+    receiver: broadcast::Receiver<WsMessage>,
+    subscriptions: Subscriptions,
+    /// Highest `seq` sent, or skipped because it was not wanted
+    last_seq: i64,
+    /// The latest `seq`s sent or skipped, so that events arriving both live
+    /// and through a replay, or out of order, are only sent once
+    seen: BTreeSet<i64>,
+    /// Set when the receiver dropped events that have not been replayed yet
+    lagged: bool,
+    /// The note the client has open
+    viewing: Option<String>,
+}
+
+impl WsConnection {
+    pub async fn open(state: &AppState, account_id: i64) -> Result<Self> {
+        let username: String = sqlx::query_scalar("SELECT username FROM accounts WHERE id = $1")
+            .bind(account_id)
+            .fetch_optional(state.pool.as_ref())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Account not found".to_string()))?;
+        let (receiver, last_seq) = state.ws_broadcaster.connect(state.pool.as_ref(), account_id).await?;
+        Ok(Self {
+            id: state.ws_broadcaster.connection_id(),
+            account_id,
+            username,
+            receiver,
+            subscriptions: Subscriptions::default(),
+            last_seq,
+            seen: BTreeSet::new(),
+            lagged: false,
+            viewing: None,
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn last_seq(&self) -> i64 {
+        self.last_seq
+    }
+
+    /// Take the connection out of presence once the socket is gone
+    pub async fn close(&self, state: &AppState) {
+        state.ws_broadcaster.leave_presence(self.id).await;
+    }
+
+    /// Wait for the next events to send to the client. When the receiver
+    /// falls behind, the events it dropped are replayed from the log; if
+    /// there are too many the client is told to `resync`. Returns `None`
+    /// once the channel is closed.
+    pub async fn next_events(&mut self, state: &AppState) -> Option<Vec<String>> {
+        loop {
+            if self.lagged {
+                let events = self.catch_up(state).await;
+                self.lagged = false;
+                return Some(events);
+            }
+            match self.receiver.recv().await {
+                Ok(msg) => {
+                    if let Some(from) = msg.from_connection {
+                        if from != self.id && self.viewing.is_some() && self.viewing == msg.note_id {
+                            return Some(vec![msg.text]);
+                        }
+                        continue;
+                    }
+                    if let Some(seq) = msg.seq {
+                        // Already replayed
+                        if !self.see(seq) {
+                            continue;
+                        }
+                    }
+                    if self.subscriptions.wants(msg.database_id.as_deref(), msg.note_id.as_deref()) {
+                        return Some(vec![msg.text]);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket of account {} lagged by {} events", self.account_id, skipped);
+                    self.lagged = true;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Record that the event with this `seq` was handled. Returns false if it
+    /// already was.
+    fn see(&mut self, seq: i64) -> bool {
+        if !self.seen.insert(seq) {
+            return false;
+        }
+        if self.seen.len() > REPLAY_LIMIT as usize {
+            self.seen.pop_first();
+        }
+        self.last_seq = self.last_seq.max(seq);
+        true
+    }
+
+    /// Replay everything after `last_seq`, or tell the client to resync
+    async fn catch_up(&mut self, state: &AppState) -> Vec<String> {
+        let pool = state.pool.as_ref();
+        match self.missed_events(state, self.last_seq, i64::MIN, None).await {
+            Ok(Some(events)) => events
+                .into_iter()
+                .filter(|(seq, _)| self.see(*seq))
+                .map(|(_, event)| event.to_string())
+                .collect(),
+            Ok(None) | Err(_) => {
+                // Events up to here are covered by the client's reload
+                if let Ok(seq) = latest_seq(pool).await {
+                    self.last_seq = seq;
+                }
+                vec![json!({ "type": "resync", "seq": self.last_seq }).to_string()]
+            }
+        }
+    }
+
+    /// Logged events after `after_seq` and `after_ts`, up to `until`, that
+    /// this connection is subscribed to, with their `seq`. `None` when
+    /// there are more than `REPLAY_LIMIT` of them or the log no longer goes
+    /// back that far.
+    async fn missed_events(
+        &self,
+        state: &AppState,
+        after_seq: i64,
+        after_ts: i64,
+        until: Option<i64>,
+    ) -> Result<Option<Vec<(i64, Value)>>> {
+        let pool = state.pool.as_ref();
+        let retention = chrono::Duration::days(state.config.ws_event_retention_days);
+        let oldest_ts = (Utc::now() - retention).timestamp_millis();
+        if after_ts != i64::MIN && after_ts < oldest_ts {
+            return Ok(None);
+        }
+        if after_ts == i64::MIN {
+            let oldest_seq: Option<i64> = sqlx::query_scalar("SELECT MIN(seq) FROM ws_events")
+                .fetch_one(pool)
+                .await?;
+            if oldest_seq.is_some_and(|oldest| oldest > after_seq + 1) {
+                return Ok(None);
+            }
+        }
+
+        let databases: Vec<String> = self.subscriptions.databases.iter().cloned().collect();
+        let notes: Vec<String> = self.subscriptions.notes.iter().cloned().collect();
+        let rows: Vec<(i64, Value)> = sqlx::query_as(
+            r#"
+            SELECT e.seq, e.payload FROM ws_events e
+            WHERE e.seq > $2 AND e.seq <= $3 AND e.backend_ts > $4
+            AND (e.account_id = $1
+                 OR e.database_id IN (SELECT id::text FROM hulunote_databases WHERE account_id = $1
+                                      UNION
+                                      SELECT database_id::text FROM database_members WHERE account_id = $1))
+            AND (e.database_id IS NULL
+                 OR (cardinality($5::text[]) = 0 AND cardinality($6::text[]) = 0)
+                 OR e.database_id = ANY($5) OR e.note_id = ANY($6))
+            ORDER BY e.seq
+            LIMIT $7
+            "#,
+        )
+        .bind(self.account_id)
+        .bind(after_seq)
+        .bind(until.unwrap_or(i64::MAX))
+        .bind(after_ts)
+        .bind(&databases)
+        .bind(&notes)
+        .bind(REPLAY_LIMIT + 1)
+        .fetch_all(pool)
+        .await?;
+
+        if rows.len() as i64 > REPLAY_LIMIT {
+            return Ok(None);
+        }
+        Ok(Some(
+            rows.into_iter()
+                .map(|(seq, mut payload)| {
+                    payload["seq"] = json!(seq);
+                    (seq, payload)
+                })
+                .collect(),
+        ))
+    }
+
+    /// Handle a text message from the client and return the replies to
+    /// send, in order
+    pub async fn handle_message(&mut self, state: &AppState, text: &str) -> Vec<Value> {
+        let message: WsClientMessage = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(e) => {
+                return vec![json!({
+                    "type": "error",
+                    "error": format!("Invalid message: {}", e)
+                })]
+            }
+        };
+
+        state.ws_broadcaster.touch_presence(self.id).await;
+        match message {
+            WsClientMessage::Ping => vec![json!({ "type": "pong" })],
+            WsClientMessage::NavOp(op) => {
+                let mut ack = match apply_nav_op(state, self.account_id, &op).await {
+                    Ok(result) => result,
+                    Err(e) => error_reply("nav_op_ack", e),
+                };
+                ack["type"] = json!("nav_op_ack");
+                ack["op-id"] = json!(op.op_id);
+                vec![ack]
+            }
+            WsClientMessage::Subscribe(req) => {
+                let reply = match self.subscribe(state, req).await {
+                    Ok(()) => self.subscriptions.to_json("subscribed"),
+                    Err(e) => error_reply("subscribed", e),
+                };
+                vec![reply]
+            }
+            WsClientMessage::Unsubscribe(req) => {
+                for id in &req.database_ids {
+                    self.subscriptions.databases.remove(id);
+                }
+                for id in &req.note_ids {
+                    self.subscriptions.notes.remove(id);
+                }
+                vec![self.subscriptions.to_json("unsubscribed")]
+            }
+            WsClientMessage::Resume(req) => self.resume(state, req).await,
+            WsClientMessage::Presence(req) => {
+                let reply = match self.set_presence(state, req).await {
+                    Ok(viewers) => json!({
+                        "type": "presence",
+                        "success": true,
+                        "note-id": self.viewing,
+                        "viewers": viewers
+                    }),
+                    Err(e) => error_reply("presence", e),
+                };
+                vec![reply]
+            }
+            WsClientMessage::Leave => {
+                state.ws_broadcaster.leave_presence(self.id).await;
+                self.viewing = None;
+                vec![json!({ "type": "presence", "success": true, "note-id": null, "viewers": [] })]
+            }
+        }
+    }
+
+    /// Enter a note, or move to another nav in it, and return who else has
+    /// it open
+    async fn set_presence(&mut self, state: &AppState, req: WsPresence) -> Result<Vec<Viewer>> {
+        let pool = state.pool.as_ref();
+        let note_id = Uuid::parse_str(&req.note_id).map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+        let database_id = shared_note_database(pool, self.account_id, note_id).await?;
+        let nav_id = req
+            .nav_id
+            .as_deref()
+            .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid nav ID".to_string())))
+            .transpose()?;
+
+        let viewer = Viewer {
+            connection_id: self.id,
+            account_id: self.account_id,
+            username: self.username.clone(),
+            nav_id: nav_id.map(|id| id.to_string()),
+        };
+        let note_id = note_id.to_string();
+        let viewers = state.ws_broadcaster.set_presence(viewer, &note_id, &database_id).await;
+        self.viewing = Some(note_id);
+        Ok(viewers)
+    }
+
+    /// Add databases and notes to the subscriptions once all of them are
+    /// shared with the account
+    async fn subscribe(&mut self, state: &AppState, req: WsSubscription) -> Result<()> {
+        let pool = state.pool.as_ref();
+        let mut databases = Vec::new();
+        for id in &req.database_ids {
+            let uuid = Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid database ID".to_string()))?;
+            if database_role(pool, self.account_id, uuid).await?.is_none() {
+                return Err(AppError::PermissionDenied("Database is not shared with you".to_string()));
+            }
+            databases.push(uuid.to_string());
+        }
+        let mut notes = Vec::new();
+        for id in &req.note_ids {
+            let uuid = Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid note ID format".to_string()))?;
+            shared_note_database(pool, self.account_id, uuid).await?;
+            notes.push(uuid.to_string());
+        }
+
+        self.subscriptions.databases.extend(databases);
+        self.subscriptions.notes.extend(notes);
+        Ok(())
+    }
+
+    /// Replay the events the client missed before this connection started
+    /// streaming them, then confirm with `resumed`
+    async fn resume(&mut self, state: &AppState, req: WsResume) -> Vec<Value> {
+        let (after_seq, after_ts) = match (req.last_seq, req.backend_ts) {
+            (Some(seq), None) => (seq, i64::MIN),
+            (None, Some(ts)) => (0, ts),
+            _ => {
+                let error = AppError::BadRequest("Exactly one of last-seq or backend-ts required".to_string());
+                return vec![error_reply("resumed", error)];
+            }
+        };
+
+        match self.missed_events(state, after_seq, after_ts, Some(self.last_seq)).await {
+            Ok(Some(events)) => {
+                let replayed = events.len();
+                // Not sent again should they still arrive live
+                for (seq, _) in &events {
+                    self.see(*seq);
+                }
+                let mut replies: Vec<Value> = events.into_iter().map(|(_, event)| event).collect();
+                replies.push(json!({ "type": "resumed", "success": true, "seq": self.last_seq, "replayed": replayed }));
+                replies
+            }
+            Ok(None) => vec![json!({ "type": "resync", "seq": self.last_seq })],
+            Err(e) => vec![error_reply("resumed", e)],
+        }
+    }
+}
+
+/// The database of a note in a database shared with the account. Public
+/// notes of other databases are readable but not shared.
+async fn shared_note_database(pool: &PgPool, account_id: i64, note_id: Uuid) -> Result<String> {
+    let database_id = authorize_note(pool, account_id, note_id, Access::Read).await?;
+    let database_uuid =
+        Uuid::parse_str(&database_id).map_err(|_| AppError::Internal("Invalid database ID".to_string()))?;
+    if database_role(pool, account_id, database_uuid).await?.is_none() {
+        return Err(AppError::PermissionDenied("Note is not shared with you".to_string()));
+    }
+    Ok(database_id)
+}
+
+/// A failed reply to a client message
+fn error_reply(reply_type: &str, e: AppError) -> Value {
+    let (status, error) = e.status_and_message();
+    json!({ "type": reply_type, "success": false, "status": status.as_u16(), "error": error })
 }
 
 #[derive(Debug, Deserialize)]
@@ -94,7 +1028,6 @@ pub async fn ws_handler(
     State(app_state): State<super::AppState>,
     Query(query): Query<WsQuery>,
 ) -> Response {
-    let broadcaster = app_state.ws_broadcaster.clone();
     // Validate JWT token
     let config = Config::from_env();
     let token_data = jsonwebtoken::decode::<Claims>(
@@ -107,7 +1040,7 @@ pub async fn ws_handler(
         Ok(data) => {
             let account_id = data.claims.id;
             tracing::info!("WebSocket connected for account {}", account_id);
-            ws.on_upgrade(move |socket| handle_socket(socket, broadcaster, account_id))
+            ws.on_upgrade(move |socket| handle_socket(socket, app_state, account_id))
         }
         Err(e) => {
             tracing::warn!("WebSocket auth failed: {}", e);
@@ -124,37 +1057,53 @@ pub async fn ws_handler(
     }
 }
 
-async fn handle_socket(mut socket: WebSocket, broadcaster: WsBroadcaster, account_id: i64) {
-    // Send a welcome message
+async fn handle_socket(mut socket: WebSocket, state: AppState, account_id: i64) {
+    let mut connection = match WsConnection::open(&state, account_id).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::warn!("WebSocket setup failed for account {}: {}", account_id, e);
+            return;
+        }
+    };
+
+    // Send a welcome message with where the event stream starts
     let welcome = json!({
         "type": "connected",
-        "message": "WebSocket connected successfully"
+        "message": "WebSocket connected successfully",
+        "seq": connection.last_seq()
     });
     if socket
-        .send(Message::Text(welcome.to_string().into()))
+        .send(Message::Text(welcome.to_string()))
         .await
         .is_err()
     {
         return;
     }
 
-    // Subscribe to broadcast channel for this account
-    let sender = broadcaster.get_sender(account_id).await;
-    let mut receiver = sender.subscribe();
-
-    loop {
+    'socket: loop {
         tokio::select! {
             // Forward broadcast events to the WebSocket client
-            Ok(msg) = receiver.recv() => {
-                if socket.send(Message::Text(msg.into())).await.is_err() {
-                    break;
+            events = connection.next_events(&state) => {
+                let Some(events) = events else { break };
+                for event in events {
+                    if socket.send(Message::Text(event)).await.is_err() {
+                        break 'socket;
+                    }
                 }
             }
-            // Handle incoming messages from the client (mainly for ping/pong)
+            // Handle incoming messages from the client
             Some(msg) = socket.recv() => {
                 match msg {
+                    Ok(Message::Text(text)) => {
+                        for reply in connection.handle_message(&state, &text).await {
+                            if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                                break 'socket;
+                            }
+                        }
+                    }
                     Ok(Message::Ping(data)) => {
-                        if socket.send(Message::Pong(data)).await.is_err() {
+                        let sent = socket.send(Message::Pong(data)).await;
+                        if sent.is_err() {
                             break;
                         }
                     }
@@ -171,5 +1120,6 @@ async fn handle_socket(mut socket: WebSocket, broadcaster: WsBroadcaster, accoun
         }
     }
 
+    connection.close(&state).await;
     tracing::info!("WebSocket disconnected for account {}", account_id);
 }
@@ -0,0 +1,7 @@
+pub mod config;
+pub mod db;
+pub mod error;
+pub mod handlers;
+pub mod middleware;
+pub mod models;
+pub mod routes;
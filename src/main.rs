@@ -1,14 +1,7 @@
-mod config;
-mod db;
-mod error;
-mod handlers;
-mod middleware;
-mod models;
-mod routes;
-
 use axum::Router;
 use axum::http::{HeaderName, HeaderValue, Method};
 use std::net::SocketAddr;
+use hulunote_server::{config::Config, db, handlers, models::FsckRepair, routes};
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
@@ -30,8 +23,34 @@ async fn main() -> anyhow::Result<()> {
     // Initialize database pool
     let pool = db::init_pool().await?;
 
+    // `hulunote-server fsck ...` checks the outline trees and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("fsck") {
+        return run_fsck(&pool, &args[1..]).await;
+    }
+
+    // Purge the trash in the background
+    let config = Config::from_env();
+    if config.trash_retention_days > 0 {
+        handlers::spawn_trash_purge(
+            pool.clone(),
+            config.trash_retention_days,
+            std::time::Duration::from_secs(config.trash_purge_interval_secs),
+        );
+    }
+
+    // Drop WebSocket events too old to replay
+    handlers::ws::spawn_event_purge(
+        pool.clone(),
+        config.ws_event_retention_days,
+        std::time::Duration::from_secs(config.ws_event_purge_interval_secs),
+    );
+
     // Build application state
-    let app_state = handlers::AppState::new(pool);
+    let app_state = handlers::AppState::new(pool, config);
+
+    // Drop the presence of connections that stopped sending heartbeats
+    app_state.ws_broadcaster.spawn_presence_sweep(std::time::Duration::from_secs(10));
 
     // CORS configuration - 允许开发环境跨域
     let cors = CorsLayer::new()
@@ -71,3 +90,42 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// `fsck [--repair reattach|recover] [DATABASE_ID...]`: check the given
+/// databases, or every database when none is given, print a report for each
+/// and exit with status 1 if problems are left unrepaired
+async fn run_fsck(pool: &sqlx::PgPool, args: &[String]) -> anyhow::Result<()> {
+    let mut repair = None;
+    let mut database_ids = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--repair" => {
+                let mode = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--repair needs a mode: reattach or recover"))?;
+                repair = Some(mode.parse::<FsckRepair>()?);
+            }
+            id => database_ids.push(uuid::Uuid::parse_str(id)?),
+        }
+    }
+
+    if database_ids.is_empty() {
+        database_ids = sqlx::query_scalar("SELECT id FROM hulunote_databases WHERE is_delete = false ORDER BY created_at")
+            .fetch_all(pool)
+            .await?;
+    }
+
+    let mut unrepaired = false;
+    for database_id in database_ids {
+        let report = handlers::fsck_database(pool, database_id, repair).await?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        unrepaired |= report.repaired < report.issues.len();
+    }
+
+    if unrepaired {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -1,5 +1,6 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use sqlx::FromRow;
 use uuid::Uuid;
 
@@ -22,6 +23,8 @@ pub struct Account {
     pub is_new_user: bool,
     pub expires_at: Option<DateTime<Utc>>,
     pub registration_code: Option<String>,
+    pub timezone: String,
+    pub date_format: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -66,6 +69,10 @@ pub struct AccountInfo {
     pub invitation_code: Option<String>,
     #[serde(rename = "accounts/is-new-user")]
     pub is_new_user: bool,
+    #[serde(rename = "accounts/timezone")]
+    pub timezone: String,
+    #[serde(rename = "accounts/date-format")]
+    pub date_format: String,
     #[serde(rename = "accounts/created-at")]
     pub created_at: String,
     #[serde(rename = "accounts/updated-at")]
@@ -83,6 +90,8 @@ impl From<Account> for AccountInfo {
             introduction: account.introduction,
             invitation_code: account.invitation_code,
             is_new_user: account.is_new_user,
+            timezone: account.timezone,
+            date_format: account.date_format,
             created_at: account.created_at.to_rfc3339(),
             updated_at: account.updated_at.to_rfc3339(),
         }
@@ -93,6 +102,9 @@ impl From<Account> for AccountInfo {
 pub struct UpdateProfileRequest {
     pub nickname: Option<String>,
     pub introduction: Option<String>,
+    pub timezone: Option<String>,
+    #[serde(rename = "date-format")]
+    pub date_format: Option<String>,
 }
 
 // ========== Database Models ==========
@@ -112,7 +124,7 @@ pub struct HulunoteDatabase {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DatabaseInfo {
     #[serde(rename = "hulunote-databases/id")]
     pub id: String,
@@ -128,6 +140,9 @@ pub struct DatabaseInfo {
     pub is_default: bool,
     #[serde(rename = "hulunote-databases/account-id")]
     pub account_id: i64,
+    /// The caller's role, when listing the databases they can open
+    #[serde(rename = "hulunote-databases/role", skip_serializing_if = "Option::is_none")]
+    pub role: Option<MemberRole>,
     #[serde(rename = "hulunote-databases/created-at")]
     pub created_at: String,
     #[serde(rename = "hulunote-databases/updated-at")]
@@ -144,6 +159,7 @@ impl From<HulunoteDatabase> for DatabaseInfo {
             is_public: db.is_public,
             is_default: db.is_default,
             account_id: db.account_id,
+            role: None,
             created_at: db.created_at.to_rfc3339(),
             updated_at: db.updated_at.to_rfc3339(),
         }
@@ -172,6 +188,100 @@ pub struct UpdateDatabaseRequest {
     pub db_name: Option<String>,
 }
 
+/// Role of an account in a database, ordered from least to most privileged.
+/// Commenters can read like viewers; there is nothing more for them to do yet.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberRole {
+    Viewer,
+    Commenter,
+    Editor,
+    Owner,
+}
+
+impl MemberRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MemberRole::Viewer => "viewer",
+            MemberRole::Commenter => "commenter",
+            MemberRole::Editor => "editor",
+            MemberRole::Owner => "owner",
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct DatabaseMember {
+    pub account_id: i64,
+    pub username: String,
+    pub nickname: Option<String>,
+    pub role: String,
+    pub invited_by: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseMemberInfo {
+    #[serde(rename = "account-id")]
+    pub account_id: i64,
+    pub username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    pub role: String,
+    #[serde(rename = "invited-by")]
+    pub invited_by: Option<i64>,
+    #[serde(rename = "created-at")]
+    pub created_at: String,
+}
+
+impl From<DatabaseMember> for DatabaseMemberInfo {
+    fn from(member: DatabaseMember) -> Self {
+        Self {
+            account_id: member.account_id,
+            username: member.username,
+            nickname: member.nickname,
+            role: member.role,
+            invited_by: member.invited_by,
+            created_at: member.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Invite an existing account, found by id, username or email
+#[derive(Debug, Deserialize)]
+pub struct InviteMemberRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    #[serde(rename = "account-id")]
+    pub account_id: Option<i64>,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub role: MemberRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetMembersRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    #[serde(rename = "account-id")]
+    pub account_id: i64,
+    pub role: MemberRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoveMemberRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    #[serde(rename = "account-id")]
+    pub account_id: i64,
+}
+
 // ========== Note Models ==========
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -183,6 +293,7 @@ pub struct HulunoteNote {
     pub is_delete: bool,
     pub is_public: bool,
     pub is_shortcut: bool,
+    pub is_template: bool,
     pub account_id: i64,
     pub pv: i64,
     pub created_at: DateTime<Utc>,
@@ -205,6 +316,8 @@ pub struct NoteInfo {
     pub is_public: bool,
     #[serde(rename = "hulunote-notes/is-shortcut")]
     pub is_shortcut: bool,
+    #[serde(rename = "hulunote-notes/is-template")]
+    pub is_template: bool,
     #[serde(rename = "hulunote-notes/account-id")]
     pub account_id: i64,
     #[serde(rename = "hulunote-notes/pv")]
@@ -225,6 +338,7 @@ impl From<HulunoteNote> for NoteInfo {
             is_delete: note.is_delete,
             is_public: note.is_public,
             is_shortcut: note.is_shortcut,
+            is_template: note.is_template,
             account_id: note.account_id,
             pv: note.pv,
             created_at: note.created_at.to_rfc3339(),
@@ -273,6 +387,64 @@ pub struct UpdateNoteRequest {
     pub is_public: Option<bool>,
     #[serde(rename = "is-shortcut")]
     pub is_shortcut: Option<bool>,
+    #[serde(rename = "is-template")]
+    pub is_template: Option<bool>,
+    /// When renaming, also rewrite `[[Old Title]]` references to the new title
+    #[serde(rename = "rewrite-references")]
+    pub rewrite_references: Option<bool>,
+}
+
+// ========== Daily Note Models ==========
+
+#[derive(Debug, Deserialize)]
+pub struct DailyNoteRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub database: Option<String>,
+    #[serde(rename = "database-name")]
+    pub database_name: Option<String>,
+    /// `YYYY-MM-DD`; defaults to today in `timezone`
+    pub date: Option<String>,
+    /// Overrides the account's timezone for this request
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetDailyNotesRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub database: Option<String>,
+    #[serde(rename = "database-name")]
+    pub database_name: Option<String>,
+    /// First day of the range, `YYYY-MM-DD`
+    pub from: String,
+    /// Last day of the range (inclusive), `YYYY-MM-DD`
+    pub to: String,
+}
+
+// ========== Template Models ==========
+
+#[derive(Debug, Deserialize)]
+pub struct InstantiateTemplateRequest {
+    #[serde(rename = "template-id")]
+    pub template_id: String,
+    /// Nav to copy the template under; when omitted a new note is created
+    pub parid: Option<String>,
+    pub order: Option<f32>,
+    /// Database of the new note; defaults to the template's
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub database: Option<String>,
+    #[serde(rename = "database-name")]
+    pub database_name: Option<String>,
+    /// Title of the new note; defaults to the template's title with its
+    /// variables substituted
+    pub title: Option<String>,
+    /// `YYYY-MM-DD` for `{{date}}`; defaults to today in the account's timezone
+    pub date: Option<String>,
+    /// Extra `{{name}}` variables
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
 }
 
 // ========== Nav Models ==========
@@ -284,6 +456,8 @@ pub struct HulunoteNav {
     pub same_deep_order: f32,
     pub content: String,
     pub account_id: i64,
+    /// Account that last edited the nav; `None` until its first edit
+    pub last_account_id: Option<i64>,
     pub note_id: String,
     pub database_id: String,
     pub is_display: bool,
@@ -295,7 +469,7 @@ pub struct HulunoteNav {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NavInfo {
     pub id: String,
     pub parid: String,
@@ -319,6 +493,8 @@ pub struct NavInfo {
     #[serde(rename = "is-delete")]
     pub is_delete: bool,
     pub properties: String,
+    #[serde(rename = "extra-id")]
+    pub extra_id: String,
     #[serde(rename = "created-at")]
     pub created_at: String,
     #[serde(rename = "updated-at")]
@@ -333,7 +509,7 @@ impl From<HulunoteNav> for NavInfo {
             same_deep_order: nav.same_deep_order,
             content: nav.content,
             account_id: nav.account_id,
-            last_account_id: nav.account_id, // Use account_id as last_account_id
+            last_account_id: nav.last_account_id.unwrap_or(nav.account_id),
             note_id: nav.note_id.clone(),
             hulunote_note: nav.note_id,
             database_id: nav.database_id,
@@ -341,6 +517,7 @@ impl From<HulunoteNav> for NavInfo {
             is_public: nav.is_public,
             is_delete: nav.is_delete,
             properties: nav.properties,
+            extra_id: nav.extra_id,
             created_at: nav.created_at.to_rfc3339(),
             updated_at: nav.updated_at.to_rfc3339(),
         }
@@ -363,6 +540,8 @@ pub struct CreateOrUpdateNavRequest {
     pub is_delete: Option<bool>,
     #[serde(rename = "is-display")]
     pub is_display: Option<bool>,
+    #[serde(rename = "is-public")]
+    pub is_public: Option<bool>,
     pub properties: Option<String>,
     pub order: Option<f32>,
 }
@@ -371,6 +550,47 @@ pub struct CreateOrUpdateNavRequest {
 pub struct GetNavsRequest {
     #[serde(rename = "note-id")]
     pub note_id: String,
+    /// Return the navs nested under the note's root nav instead of a flat list
+    #[serde(default)]
+    pub tree: bool,
+    pub depth: Option<i32>,
+    #[serde(rename = "skip-hidden", default)]
+    pub skip_hidden: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetNavSubtreeRequest {
+    #[serde(rename = "nav-id")]
+    pub nav_id: String,
+    pub depth: Option<i32>,
+    #[serde(rename = "skip-hidden", default)]
+    pub skip_hidden: bool,
+}
+
+/// New order of a nav whose siblings were renumbered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavOrder {
+    pub id: String,
+    #[serde(rename = "same-deep-order")]
+    pub same_deep_order: f32,
+}
+
+/// New content of a nav rewritten by the server, such as a page reference
+/// updated after a note rename
+#[derive(Debug, Clone, Serialize)]
+pub struct NavContent {
+    pub id: String,
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    pub content: String,
+}
+
+/// A nav with its children, in order
+#[derive(Debug, Serialize)]
+pub struct NavTree {
+    #[serde(flatten)]
+    pub nav: NavInfo,
+    pub children: Vec<NavTree>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -386,6 +606,578 @@ pub struct GetAllNavsByPageRequest {
     pub size: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct BatchNavsRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    pub ops: Vec<BatchNavOp>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchNavOpKind {
+    Create,
+    Update,
+    Move,
+    Delete,
+}
+
+/// One operation of a nav batch. `create` may omit `id`; the others need it.
+#[derive(Debug, Deserialize)]
+pub struct BatchNavOp {
+    pub op: BatchNavOpKind,
+    pub id: Option<String>,
+    pub parid: Option<String>,
+    pub content: Option<String>,
+    #[serde(rename = "is-display")]
+    pub is_display: Option<bool>,
+    pub properties: Option<String>,
+    pub order: Option<f32>,
+}
+
+/// A nav operation sent by a client over the WebSocket. `backend-ts` is
+/// when the client made the edit, on the clock of the `backend-ts` values
+/// the server hands out; each field the operation sets only changes if no
+/// later edit of that field has been applied.
+#[derive(Debug, Deserialize)]
+pub struct WsNavOp {
+    /// Echoed back in the acknowledgement
+    #[serde(rename = "op-id")]
+    pub op_id: Option<String>,
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    #[serde(rename = "backend-ts")]
+    pub backend_ts: Option<i64>,
+    #[serde(flatten)]
+    pub op: BatchNavOp,
+}
+
+/// Databases and notes to start or stop receiving events for over the
+/// WebSocket
+#[derive(Debug, Default, Deserialize)]
+pub struct WsSubscription {
+    #[serde(rename = "database-ids", default)]
+    pub database_ids: Vec<String>,
+    #[serde(rename = "note-ids", default)]
+    pub note_ids: Vec<String>,
+}
+
+/// The note a client has open over the WebSocket, and the nav it is editing
+#[derive(Debug, Deserialize)]
+pub struct WsPresence {
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    #[serde(rename = "nav-id")]
+    pub nav_id: Option<String>,
+}
+
+/// Where a reconnecting client left off: the last `seq` it received, or
+/// the `backend-ts` of the last change it has
+#[derive(Debug, Deserialize)]
+pub struct WsResume {
+    #[serde(rename = "last-seq")]
+    pub last_seq: Option<i64>,
+    #[serde(rename = "backend-ts")]
+    pub backend_ts: Option<i64>,
+}
+
+/// Where to move or copy a nav and its descendants. The target note
+/// defaults to the nav's own note and the parent to the target's root nav.
+#[derive(Debug, Deserialize)]
+pub struct NavSubtreeRequest {
+    #[serde(rename = "nav-id")]
+    pub nav_id: String,
+    #[serde(rename = "target-note-id")]
+    pub target_note_id: Option<String>,
+    pub parid: Option<String>,
+    pub order: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateNavRequest {
+    #[serde(rename = "nav-id")]
+    pub nav_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetBacklinksRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: Option<String>,
+    #[serde(rename = "nav-id")]
+    pub nav_id: Option<String>,
+}
+
+// ========== Search Models ==========
+
+#[derive(Debug, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub database: Option<String>,
+    #[serde(rename = "database-name")]
+    pub database_name: Option<String>,
+    pub page: Option<i64>,
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct SearchHit {
+    pub kind: String,
+    pub note_id: String,
+    pub nav_id: Option<Uuid>,
+    pub database_id: String,
+    pub title: String,
+    pub snippet: String,
+    pub rank: f32,
+}
+
+// ========== Query Models ==========
+
+#[derive(Debug, Deserialize)]
+pub struct QueryNavsRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub database: Option<String>,
+    #[serde(rename = "database-name")]
+    pub database_name: Option<String>,
+    #[serde(rename = "note-id")]
+    pub note_id: Option<String>,
+    /// Navs must have every one of these tags (case-insensitive)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Navs must match every one of these property filters
+    #[serde(default)]
+    pub properties: Vec<PropertyFilter>,
+    pub page: Option<i64>,
+    pub size: Option<i64>,
+}
+
+/// A property that must be present, equal to `value` and/or within a range.
+/// Number bounds compare numerically, string bounds compare as text.
+#[derive(Debug, Deserialize)]
+pub struct PropertyFilter {
+    pub key: String,
+    pub value: Option<String>,
+    pub gt: Option<serde_json::Value>,
+    pub gte: Option<serde_json::Value>,
+    pub lt: Option<serde_json::Value>,
+    pub lte: Option<serde_json::Value>,
+}
+
+// ========== Task Models ==========
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    Todo,
+    Doing,
+    Done,
+    Canceled,
+}
+
+impl TaskStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::Todo => "todo",
+            TaskStatus::Doing => "doing",
+            TaskStatus::Done => "done",
+            TaskStatus::Canceled => "canceled",
+        }
+    }
+
+    /// The marker written at the start of the nav content
+    pub fn marker(self) -> &'static str {
+        match self {
+            TaskStatus::Todo => "TODO",
+            TaskStatus::Doing => "DOING",
+            TaskStatus::Done => "DONE",
+            TaskStatus::Canceled => "CANCELED",
+        }
+    }
+}
+
+/// A task parsed from a nav's content and properties
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct NavTask {
+    pub status: TaskStatus,
+    /// `A`, `B` or `C`, from `[#A]`
+    pub priority: Option<String>,
+    #[serde(rename = "due-date")]
+    pub due_date: Option<NaiveDate>,
+    #[serde(rename = "scheduled-date")]
+    pub scheduled_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSort {
+    #[default]
+    Due,
+    Scheduled,
+    Priority,
+    Updated,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetTasksRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub database: Option<String>,
+    #[serde(rename = "database-name")]
+    pub database_name: Option<String>,
+    #[serde(rename = "note-id")]
+    pub note_id: Option<String>,
+    /// Defaults to the open statuses, `todo` and `doing`
+    pub status: Option<Vec<TaskStatus>>,
+    /// Only tasks due on or after this date, `YYYY-MM-DD`
+    #[serde(rename = "due-from")]
+    pub due_from: Option<String>,
+    /// Only tasks due on or before this date, `YYYY-MM-DD`
+    #[serde(rename = "due-to")]
+    pub due_to: Option<String>,
+    #[serde(default)]
+    pub sort: TaskSort,
+    pub page: Option<i64>,
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ToggleTaskRequest {
+    #[serde(rename = "nav-id")]
+    pub nav_id: String,
+    /// New status; by default `done` tasks go back to `todo` and every other
+    /// nav becomes `done`
+    pub status: Option<TaskStatus>,
+}
+
+// ========== Revision Models ==========
+
+/// A nav's state after one change, from `hulunote_nav_revisions`
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct NavRevision {
+    pub id: i64,
+    #[serde(rename = "nav-id")]
+    pub nav_id: Uuid,
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    #[serde(rename = "account-id")]
+    pub account_id: i64,
+    pub op: String,
+    pub parid: String,
+    #[serde(rename = "same-deep-order")]
+    pub same_deep_order: f32,
+    pub content: String,
+    pub properties: String,
+    #[serde(rename = "is-display")]
+    pub is_display: bool,
+    #[serde(rename = "is-delete")]
+    pub is_delete: bool,
+    #[serde(rename = "created-at")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetNoteHistoryRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    /// Only the history of this nav
+    #[serde(rename = "nav-id")]
+    pub nav_id: Option<String>,
+    pub page: Option<i64>,
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffNoteRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    /// Milliseconds since the epoch, like `backend-ts`
+    pub from: i64,
+    /// Defaults to now
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreNoteRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    /// Milliseconds since the epoch to restore the note to
+    pub at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreNavRequest {
+    #[serde(rename = "nav-id")]
+    pub nav_id: String,
+    /// Revision to restore, or else
+    #[serde(rename = "revision-id")]
+    pub revision_id: Option<i64>,
+    /// the nav's state at this time (milliseconds since the epoch)
+    pub at: Option<i64>,
+}
+
+// ========== Public Sharing Models ==========
+
+/// A nav as shown to visitors of a public page, without account details
+#[derive(Debug, Serialize)]
+pub struct PublicNav {
+    pub id: String,
+    pub content: String,
+    pub properties: String,
+    #[serde(rename = "is-display")]
+    pub is_display: bool,
+    pub children: Vec<PublicNav>,
+}
+
+impl From<NavTree> for PublicNav {
+    fn from(tree: NavTree) -> Self {
+        Self {
+            id: tree.nav.id,
+            content: tree.nav.content,
+            properties: tree.nav.properties,
+            is_display: tree.nav.is_display,
+            children: tree.children.into_iter().map(PublicNav::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareTarget {
+    Note,
+    Nav,
+    Database,
+}
+
+impl ShareTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShareTarget::Note => "note",
+            ShareTarget::Nav => "nav",
+            ShareTarget::Database => "database",
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ShareLink {
+    pub id: i64,
+    pub token: String,
+    pub account_id: i64,
+    pub target_type: String,
+    pub target_id: String,
+    pub password_hash: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub view_count: i64,
+    pub last_viewed_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareLinkInfo {
+    pub id: i64,
+    pub token: String,
+    /// Path of the public read route for this link
+    pub url: String,
+    #[serde(rename = "target-type")]
+    pub target_type: String,
+    #[serde(rename = "target-id")]
+    pub target_id: String,
+    #[serde(rename = "has-password")]
+    pub has_password: bool,
+    #[serde(rename = "expires-at")]
+    pub expires_at: Option<String>,
+    #[serde(rename = "view-count")]
+    pub view_count: i64,
+    #[serde(rename = "last-viewed-at")]
+    pub last_viewed_at: Option<String>,
+    #[serde(rename = "revoked-at")]
+    pub revoked_at: Option<String>,
+    /// Neither revoked nor expired
+    pub active: bool,
+    #[serde(rename = "created-at")]
+    pub created_at: String,
+}
+
+impl From<ShareLink> for ShareLinkInfo {
+    fn from(link: ShareLink) -> Self {
+        let active = link.revoked_at.is_none() && link.expires_at.is_none_or(|at| at > Utc::now());
+        Self {
+            id: link.id,
+            url: format!("/public/share/{}", link.token),
+            token: link.token,
+            target_type: link.target_type,
+            target_id: link.target_id,
+            has_password: link.password_hash.is_some(),
+            expires_at: link.expires_at.map(|at| at.to_rfc3339()),
+            view_count: link.view_count,
+            last_viewed_at: link.last_viewed_at.map(|at| at.to_rfc3339()),
+            revoked_at: link.revoked_at.map(|at| at.to_rfc3339()),
+            active,
+            created_at: link.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Share exactly one of a note, a nav's subtree or a database
+#[derive(Debug, Deserialize)]
+pub struct CreateShareLinkRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: Option<String>,
+    #[serde(rename = "nav-id")]
+    pub nav_id: Option<String>,
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub password: Option<String>,
+    #[serde(rename = "expires-at")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetShareLinksRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: Option<String>,
+    #[serde(rename = "nav-id")]
+    pub nav_id: Option<String>,
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    /// Also list revoked links
+    #[serde(rename = "include-revoked", default)]
+    pub include_revoked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeShareLinkRequest {
+    pub id: i64,
+}
+
+/// Password for a protected share link, when it is given in the query string
+#[derive(Debug, Deserialize)]
+pub struct SharePasswordQuery {
+    pub password: Option<String>,
+}
+
+// ========== Trash Models ==========
+
+#[derive(Debug, Deserialize)]
+pub struct GetTrashRequest {
+    /// Only items of this database
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    /// Maximum number of items of each kind
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrashDatabase {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "deleted-at")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrashNote {
+    pub id: Uuid,
+    pub title: String,
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    #[serde(rename = "database-name")]
+    pub database_name: String,
+    #[serde(rename = "deleted-at")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrashNav {
+    pub id: Uuid,
+    pub content: String,
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    #[serde(rename = "note-title")]
+    pub note_title: String,
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    #[serde(rename = "deleted-at")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Exactly one of the ids names the item to restore
+#[derive(Debug, Deserialize)]
+pub struct RestoreFromTrashRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    #[serde(rename = "note-id")]
+    pub note_id: Option<String>,
+    #[serde(rename = "nav-id")]
+    pub nav_id: Option<String>,
+}
+
+// ========== Fsck Models ==========
+
+/// Where `fsck` puts navs that are not where they belong: under their
+/// note's root nav, or in a "Recovered" note
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FsckRepair {
+    Reattach,
+    Recover,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsckRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    /// Fix the problems found instead of only reporting them
+    pub repair: Option<FsckRepair>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FsckIssueKind {
+    /// A note's root nav does not exist or is deleted
+    MissingRoot,
+    /// A nav other than the note's root nav sits at the top level
+    DuplicateRoot,
+    /// A nav's parent or note does not exist
+    Orphan,
+    /// Following a nav's parents leads back to the nav
+    Cycle,
+    /// A nav's parent is in another note
+    MismatchedNote,
+    /// A nav's database is not its note's database
+    MismatchedDatabase,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsckIssue {
+    pub kind: FsckIssueKind,
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    #[serde(rename = "nav-id")]
+    pub nav_id: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FsckReport {
+    #[serde(rename = "database-id")]
+    pub database_id: String,
+    #[serde(rename = "notes-checked")]
+    pub notes_checked: usize,
+    #[serde(rename = "navs-checked")]
+    pub navs_checked: usize,
+    pub issues: Vec<FsckIssue>,
+    /// Number of issues fixed
+    pub repaired: usize,
+    #[serde(rename = "recovered-note-id")]
+    pub recovered_note_id: Option<String>,
+}
+
 // ========== Registration Code Models ==========
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -402,13 +1194,15 @@ pub struct RegistrationCode {
 
 // ========== Import Models ==========
 
-#[derive(Debug, Deserialize)]
+/// One note with its navs, the file format read by `import_notes`
+/// and written by the export endpoints
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ImportNoteJson {
     pub note: ImportNoteData,
     pub navs: Vec<ImportNavData>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ImportNoteData {
     #[serde(rename = "hulunote-notes/id")]
     pub id: String,
@@ -422,22 +1216,103 @@ pub struct ImportNoteData {
     pub is_public: Option<bool>,
     #[serde(rename = "hulunote-notes/is-shortcut")]
     pub is_shortcut: Option<bool>,
+    #[serde(rename = "hulunote-notes/is-template")]
+    pub is_template: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ImportNavData {
     pub id: String,
     pub parid: String,
     pub content: String,
     #[serde(rename = "same-deep-order")]
     pub same_deep_order: f64,
-    #[allow(dead_code)]
     #[serde(rename = "hulunote-note")]
     pub hulunote_note: String,
     #[serde(rename = "is-display")]
     pub is_display: Option<bool>,
     #[serde(rename = "is-delete")]
     pub is_delete: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<String>,
+    #[serde(rename = "extra-id", skip_serializing_if = "Option::is_none")]
+    pub extra_id: Option<String>,
+}
+
+impl From<HulunoteNav> for ImportNavData {
+    fn from(nav: HulunoteNav) -> Self {
+        Self {
+            id: nav.id.to_string(),
+            parid: nav.parid,
+            content: nav.content,
+            same_deep_order: nav.same_deep_order as f64,
+            hulunote_note: nav.note_id,
+            is_display: Some(nav.is_display),
+            is_delete: Some(nav.is_delete),
+            properties: Some(nav.properties).filter(|p| !p.is_empty()),
+            extra_id: Some(nav.extra_id).filter(|e| !e.is_empty()),
+        }
+    }
+}
+
+/// A page in a Roam Research JSON export
+#[derive(Debug, Deserialize)]
+pub struct RoamPage {
+    pub title: String,
+    #[serde(default)]
+    pub children: Vec<RoamBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoamBlock {
+    #[serde(default)]
+    pub string: String,
+    pub uid: Option<String>,
+    pub open: Option<bool>,
+    #[serde(default)]
+    pub children: Vec<RoamBlock>,
+}
+
+/// A Logseq JSON export: `{"version": 1, "blocks": [...]}` where
+/// top-level blocks are pages
+#[derive(Debug, Deserialize)]
+pub struct LogseqExport {
+    pub blocks: Vec<LogseqBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogseqBlock {
+    pub id: Option<String>,
+    #[serde(rename = "page-name")]
+    pub page_name: Option<String>,
+    #[serde(rename = "original-name")]
+    pub original_name: Option<String>,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub children: Vec<LogseqBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportNoteRequest {
+    #[serde(rename = "note-id")]
+    pub note_id: String,
+    /// `json` (default), `markdown` or `opml`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportDatabaseRequest {
+    #[serde(rename = "database-id")]
+    pub database_id: Option<String>,
+    pub database: Option<String>,
+    #[serde(rename = "database-name")]
+    pub database_name: Option<String>,
+    /// `json` (default), `markdown` or `opml`
+    pub format: Option<String>,
 }
 
 // ========== JWT Claims ==========
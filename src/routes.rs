@@ -12,7 +12,12 @@ pub fn create_routes() -> Router<AppState> {
     let public_routes = Router::new()
         .route("/login/web-login", post(handlers::web_login))
         .route("/login/web-signup", post(handlers::web_signup))
-        .route("/login/send-ack-msg", post(handlers::send_ack_msg));
+        .route("/login/send-ack-msg", post(handlers::send_ack_msg))
+        // Share link routes (read-only)
+        .route("/public/share/:token", get(handlers::get_shared))
+        .route("/public/share/:token/html", get(handlers::get_shared_html))
+        .route("/public/share/:token/note/:note_id", get(handlers::get_shared_note))
+        .route("/public/share/:token/note/:note_id/html", get(handlers::get_shared_note_html));
 
     // WebSocket route (auth via query param token)
     let ws_routes = Router::new()
@@ -31,21 +36,61 @@ pub fn create_routes() -> Router<AppState> {
         .route("/hulunote/get-database-list", post(handlers::get_database_list))
         .route("/hulunote/update-database", post(handlers::update_database))
         .route("/hulunote/delete-database", post(handlers::delete_database))
+        // Member routes
+        .route("/hulunote/invite-database-member", post(handlers::invite_database_member))
+        .route("/hulunote/get-database-members", post(handlers::get_database_members))
+        .route("/hulunote/update-database-member", post(handlers::update_database_member))
+        .route("/hulunote/remove-database-member", post(handlers::remove_database_member))
         // Note routes
         .route("/hulunote/new-note", post(handlers::create_note))
         .route("/hulunote/get-note-list", post(handlers::get_note_list))
         .route("/hulunote/get-all-note-list", post(handlers::get_all_note_list))
         .route("/hulunote/update-hulunote-note", post(handlers::update_note))
         .route("/hulunote/get-shortcuts-note-list", post(handlers::get_shortcuts_note_list))
+        // Daily note routes
+        .route("/hulunote/get-or-create-daily-note", post(handlers::get_or_create_daily_note))
+        .route("/hulunote/get-daily-notes", post(handlers::get_daily_notes))
+        // Template routes
+        .route("/hulunote/get-template-note-list", post(handlers::get_template_note_list))
+        .route("/hulunote/instantiate-template", post(handlers::instantiate_template))
         // Nav routes
         .route("/hulunote/create-or-update-nav", post(handlers::create_or_update_nav))
         .route("/hulunote/new-hulunote-navs-uuid-v2", post(handlers::create_or_update_nav))
+        .route("/hulunote/batch-navs", post(handlers::batch_navs))
+        .route("/hulunote/move-nav", post(handlers::move_nav))
+        .route("/hulunote/copy-nav", post(handlers::copy_nav))
+        .route("/hulunote/duplicate-nav", post(handlers::duplicate_nav))
         .route("/hulunote/get-note-navs", post(handlers::get_note_navs))
         .route("/hulunote/get-nav-list-by-id", post(handlers::get_note_navs))
+        .route("/hulunote/get-nav-subtree", post(handlers::get_nav_subtree))
         .route("/hulunote/get-all-nav-by-page", post(handlers::get_all_navs_by_page))
         .route("/hulunote/get-all-navs", post(handlers::get_all_navs))
+        .route("/hulunote/get-backlinks", post(handlers::get_backlinks))
+        // Task routes
+        .route("/hulunote/get-tasks", post(handlers::get_tasks))
+        .route("/hulunote/toggle-task", post(handlers::toggle_task))
+        // History routes
+        .route("/hulunote/get-note-history", post(handlers::get_note_history))
+        .route("/hulunote/diff-note", post(handlers::diff_note))
+        .route("/hulunote/restore-note", post(handlers::restore_note))
+        .route("/hulunote/restore-nav", post(handlers::restore_nav))
+        // Trash routes
+        .route("/hulunote/get-trash", post(handlers::get_trash))
+        .route("/hulunote/restore-from-trash", post(handlers::restore_from_trash))
+        // Search routes
+        .route("/hulunote/search", post(handlers::search))
+        .route("/hulunote/query-navs", post(handlers::query_navs))
         // Import routes
         .route("/hulunote/import-notes", post(handlers::import_notes))
+        // Export routes
+        .route("/hulunote/export-note", post(handlers::export_note))
+        .route("/hulunote/export-database", post(handlers::export_database))
+        // Share link routes
+        .route("/hulunote/create-share-link", post(handlers::create_share_link))
+        .route("/hulunote/get-share-link-list", post(handlers::get_share_link_list))
+        .route("/hulunote/revoke-share-link", post(handlers::revoke_share_link))
+        // Admin routes
+        .route("/admin/fsck", post(handlers::fsck))
         .route_layer(middleware::from_fn(auth_middleware));
 
     Router::new()
@@ -0,0 +1,129 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn cross_account_reads_are_refused() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let other = app.create_account().await;
+
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root_nav_id) = app.create_note(&owner, &database_id, "Private").await;
+    app.create_nav(&owner, &note_id, &root_nav_id, "secret", 1.0).await;
+
+    let requests = [
+        ("/hulunote/get-note-navs", json!({ "note-id": note_id })),
+        ("/hulunote/get-all-navs", json!({ "database-id": database_id })),
+        ("/hulunote/get-all-nav-by-page", json!({ "database-id": database_id })),
+        ("/hulunote/get-note-list", json!({ "database-id": database_id })),
+        ("/hulunote/get-all-note-list", json!({ "database-id": database_id })),
+        ("/hulunote/get-shortcuts-note-list", json!({ "database-id": database_id })),
+    ];
+
+    for (path, body) in requests {
+        let (status, _) = app.post(&owner, path, body.clone()).await;
+        assert_eq!(status, StatusCode::OK, "owner should read {}", path);
+
+        let (status, _) = app.post(&other, path, body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "other account should not read {}", path);
+    }
+}
+
+#[tokio::test]
+async fn cross_account_writes_are_refused() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let other = app.create_account().await;
+
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root_nav_id) = app.create_note(&owner, &database_id, "Private").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root_nav_id, "original", 1.0).await;
+
+    let requests = [
+        ("/hulunote/new-note", json!({ "database-id": database_id, "title": "Intruder" })),
+        ("/hulunote/create-or-update-nav", json!({ "note-id": note_id, "parid": root_nav_id, "content": "injected" })),
+        ("/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": nav_id, "content": "overwritten" })),
+        ("/hulunote/update-hulunote-note", json!({ "note-id": note_id, "title": "Renamed" })),
+        ("/hulunote/update-database", json!({ "database-id": database_id, "db-name": "Renamed" })),
+        ("/hulunote/delete-database", json!({ "database-id": database_id })),
+    ];
+
+    for (path, body) in requests {
+        let (status, _) = app.post(&other, path, body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "other account should not write {}", path);
+    }
+
+    // Nothing the other account sent may have landed
+    let (_, body) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": note_id })).await;
+    let contents: Vec<&str> = body["nav-list"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|nav| nav["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, vec!["ROOT", "original"]);
+
+    let (_, body) = app.post(&owner, "/hulunote/get-all-note-list", json!({ "database-id": database_id })).await;
+    assert_eq!(body["note-list"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn nav_cannot_be_moved_into_another_accounts_note() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let other = app.create_account().await;
+
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root_nav_id) = app.create_note(&owner, &database_id, "Private").await;
+
+    // A database of one's own does not grant access to another account's note
+    let other_database_id = app.create_database(&other).await;
+    let (status, _) = app
+        .post(
+            &other,
+            "/hulunote/create-or-update-nav",
+            json!({
+                "database-id": other_database_id,
+                "note-id": note_id,
+                "parid": root_nav_id,
+                "content": "injected"
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn public_content_is_readable_but_not_writable() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let other = app.create_account().await;
+
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root_nav_id) = app.create_note(&owner, &database_id, "Shared").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root_nav_id, "hello", 1.0).await;
+
+    let (status, _) = app
+        .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": note_id, "is-public": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app.post(&other, "/hulunote/get-note-navs", json!({ "note-id": note_id })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["nav-list"].as_array().unwrap().len(), 2);
+
+    // A public note does not expose the rest of its database
+    let (status, _) = app.post(&other, "/hulunote/get-all-navs", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = app
+        .post(
+            &other,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": nav_id, "content": "overwritten" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn batch_applies_operations_in_order() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Batch").await;
+    let existing = app.create_nav(&owner, &note_id, &root, "existing", 1.0).await;
+    let (linked, _) = app.create_note(&owner, &database_id, "Linked").await;
+    let parent = uuid::Uuid::new_v4().to_string();
+
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/batch-navs",
+            json!({
+                "note-id": note_id,
+                "ops": [
+                    { "op": "create", "id": parent, "parid": root, "content": "parent", "order": 2.0 },
+                    { "op": "create", "parid": parent, "content": "child [[Linked]]", "order": 1.0 },
+                    { "op": "move", "id": existing, "parid": parent, "order": 2.0 },
+                    { "op": "update", "id": existing, "content": "indented" },
+                    { "op": "create", "parid": root, "content": "gone", "order": 3.0 }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["backend-ts"].is_i64());
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0]["id"], parent.as_str());
+    assert_eq!(results[2]["op"], "move");
+
+    let gone = results[4]["id"].as_str().unwrap();
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/batch-navs",
+            json!({ "note-id": note_id, "ops": [{ "op": "delete", "id": gone }] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    assert_eq!(
+        app.note_outline(&owner, &note_id).await,
+        json!([{
+            "content": "parent", "order": 2.0, "is-display": true, "properties": "",
+            "children": [
+                { "content": "child [[Linked]]", "order": 1.0, "is-display": true, "properties": "", "children": [] },
+                { "content": "indented", "order": 2.0, "is-display": true, "properties": "", "children": [] }
+            ]
+        }])
+    );
+
+    // Links of created navs are indexed like single creates
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-backlinks", json!({ "note-id": linked }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["backlinks"].as_array().unwrap().len(), 1, "{}", body);
+}
+
+#[tokio::test]
+async fn failing_operation_rolls_back_the_whole_batch() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Atomic").await;
+    let parent = app.create_nav(&owner, &note_id, &root, "parent", 1.0).await;
+    let child = app.create_nav(&owner, &note_id, &parent, "child", 1.0).await;
+    let original = app.note_outline(&owner, &note_id).await;
+
+    // Moving a nav under its own child would make a cycle
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/batch-navs",
+            json!({
+                "note-id": note_id,
+                "ops": [
+                    { "op": "update", "id": parent, "content": "changed" },
+                    { "op": "create", "parid": root, "content": "new" },
+                    { "op": "move", "id": parent, "parid": child }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().starts_with("Operation 2:"), "{}", body);
+    assert_eq!(app.note_outline(&owner, &note_id).await, original);
+
+    // Navs of other notes cannot be touched through this note
+    let (other_note, other_root) = app.create_note(&owner, &database_id, "Other").await;
+    let foreign = app.create_nav(&owner, &other_note, &other_root, "foreign", 1.0).await;
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/batch-navs",
+            json!({ "note-id": note_id, "ops": [{ "op": "delete", "id": foreign }] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Nor can other accounts write to the note
+    let stranger = app.create_account().await;
+    let (status, _) = app
+        .post(
+            &stranger,
+            "/hulunote/batch-navs",
+            json!({ "note-id": note_id, "ops": [{ "op": "create", "parid": root, "content": "x" }] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::Receiver;
+
+use common::{TestAccount, TestApp};
+use hulunote_server::handlers::ws::{WsConnection, WsMessage};
+
+/// Send a message as if it came over the account's WebSocket
+async fn send(app: &TestApp, account: &TestAccount, message: Value) -> Value {
+    send_raw(app, account, &message.to_string()).await
+}
+
+async fn send_raw(app: &TestApp, account: &TestAccount, text: &str) -> Value {
+    let mut connection = WsConnection::open(&app.state, account.id).await.unwrap();
+    connection.handle_message(&app.state, text).await.remove(0)
+}
+
+async fn subscribe(app: &TestApp, account: &TestAccount) -> Receiver<WsMessage> {
+    app.state.ws_broadcaster.get_sender(account.id).await.subscribe()
+}
+
+/// Events pushed to a subscriber so far
+fn events(receiver: &mut Receiver<WsMessage>) -> Vec<Value> {
+    std::iter::from_fn(|| receiver.try_recv().ok())
+        .map(|msg| serde_json::from_str(&msg.text).unwrap())
+        .collect()
+}
+
+async fn invite_editor(app: &TestApp, owner: &TestAccount, database_id: &str) -> TestAccount {
+    let editor = app.create_account().await;
+    let (status, body) = app
+        .post(owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": editor.id, "role": "editor" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    editor
+}
+
+#[tokio::test]
+async fn ops_are_acked_and_fanned_out_to_collaborators() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let editor = invite_editor(&app, &owner, &database_id).await;
+    let stranger = app.create_account().await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Shared").await;
+
+    let mut owner_events = subscribe(&app, &owner).await;
+    let mut stranger_events = subscribe(&app, &stranger).await;
+
+    let ack = send(
+        &app,
+        &editor,
+        json!({ "type": "nav_op", "op-id": "c1", "op": "create", "note-id": note_id, "parid": root, "content": "draft", "order": 1.0 }),
+    )
+    .await;
+    assert_eq!(ack["type"], "nav_op_ack");
+    assert_eq!(ack["op-id"], "c1");
+    assert_eq!(ack["success"], true, "{}", ack);
+    assert!(ack["backend-ts"].is_i64());
+    let nav_id = ack["id"].as_str().unwrap().to_string();
+    assert_eq!(ack["nav"]["account-id"], editor.id);
+
+    let pushed = events(&mut owner_events);
+    assert_eq!(pushed.len(), 1, "{:?}", pushed);
+    assert_eq!(pushed[0]["type"], "nav_created");
+    assert_eq!(pushed[0]["nav"]["id"], nav_id.as_str());
+    assert_eq!(pushed[0]["nav"]["content"], "draft");
+    assert!(events(&mut stranger_events).is_empty());
+
+    // Retrying the create is harmless
+    let ack = send(
+        &app,
+        &editor,
+        json!({ "type": "nav_op", "op-id": "c1", "op": "create", "id": nav_id, "note-id": note_id, "parid": root, "content": "draft" }),
+    )
+    .await;
+    assert_eq!(ack["success"], true, "{}", ack);
+    assert_eq!(app.note_outline(&owner, &note_id).await.as_array().unwrap().len(), 1);
+
+    // REST edits reach collaborators too
+    let mut editor_events = subscribe(&app, &editor).await;
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "parid": root, "content": "from rest" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(events(&mut editor_events)[0]["nav"]["content"], "from rest");
+
+    // Outsiders and bad messages get an error reply
+    let ack = send(
+        &app,
+        &stranger,
+        json!({ "type": "nav_op", "op-id": "x", "op": "update", "id": nav_id, "note-id": note_id, "content": "hijack" }),
+    )
+    .await;
+    assert_eq!(ack["success"], false);
+    assert_eq!(ack["status"], 403);
+    assert_eq!(ack["op-id"], "x");
+    assert_eq!(send(&app, &editor, json!({ "type": "ping" })).await["type"], "pong");
+    let reply = send_raw(&app, &editor, "not json").await;
+    assert_eq!(reply["type"], "error");
+}
+
+#[tokio::test]
+async fn last_writer_wins_per_field() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let editor = invite_editor(&app, &owner, &database_id).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Race").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root, "base", 1.0).await;
+    let other = app.create_nav(&owner, &note_id, &root, "other", 2.0).await;
+
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let edited = chrono::Utc::now().timestamp_millis();
+
+    // The owner's edit is newer than the editor's, which arrives late
+    let ack = send(
+        &app,
+        &owner,
+        json!({ "type": "nav_op", "op": "update", "id": nav_id, "note-id": note_id, "content": "owner", "backend-ts": edited }),
+    )
+    .await;
+    assert_eq!(ack["applied"], json!(["content"]), "{}", ack);
+    let ack = send(
+        &app,
+        &editor,
+        json!({ "type": "nav_op", "op": "update", "id": nav_id, "note-id": note_id, "content": "editor", "order": 5.0, "backend-ts": edited - 1 }),
+    )
+    .await;
+    assert_eq!(ack["success"], true, "{}", ack);
+    assert_eq!(ack["applied"], json!(["order"]));
+    assert_eq!(ack["ignored"], json!(["content"]));
+    assert_eq!(ack["nav"]["content"], "owner");
+    assert_eq!(ack["nav"]["same-deep-order"], 5.0);
+
+    // Edits older than the last REST write of a field lose to it
+    let ack = send(
+        &app,
+        &editor,
+        json!({ "type": "nav_op", "op": "move", "id": other, "note-id": note_id, "parid": nav_id, "backend-ts": edited - 60_000 }),
+    )
+    .await;
+    assert_eq!(ack["ignored"], json!(["parid"]), "{}", ack);
+    assert_eq!(ack["nav"]["parid"], root.as_str());
+
+    // A client clock in the future counts as now, so it cannot block later edits
+    let ack = send(
+        &app,
+        &editor,
+        json!({ "type": "nav_op", "op": "delete", "id": other, "note-id": note_id, "backend-ts": edited + 86_400_000 }),
+    )
+    .await;
+    assert_eq!(ack["applied"], json!(["is-delete"]), "{}", ack);
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let (status, body) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": other, "is-delete": false }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let ack = send(&app, &editor, json!({ "type": "nav_op", "op": "update", "id": other, "note-id": note_id, "content": "back" })).await;
+    assert_eq!(ack["nav"]["is-delete"], false, "{}", ack);
+    assert_eq!(ack["nav"]["content"], "back");
+}
@@ -0,0 +1,243 @@
+//! Shared helpers for the HTTP integration tests.
+//!
+//! Tests run against the PostgreSQL database in `TEST_DATABASE_URL`, which
+//! must be initialized with `init.sql` and every file in `migrations/`.
+//! When the variable is unset the tests are skipped.
+
+#![allow(dead_code)]
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::Value;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use hulunote_server::config::Config;
+use hulunote_server::handlers::AppState;
+use hulunote_server::middleware::generate_token;
+use hulunote_server::routes::create_routes;
+
+pub struct TestApp {
+    pub pool: PgPool,
+    /// State shared with the router, for reaching the WebSocket broadcaster
+    pub state: AppState,
+    router: Router,
+}
+
+/// A test account and its JWT token
+pub struct TestAccount {
+    pub id: i64,
+    pub token: String,
+}
+
+/// Connect to the test database, or return `None` to skip the test
+pub async fn setup() -> Option<TestApp> {
+    let url = match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("TEST_DATABASE_URL not set, skipping");
+            return None;
+        }
+    };
+    // The auth middleware reads its config from the environment
+    std::env::set_var("DATABASE_URL", &url);
+
+    let pool = PgPool::connect(&url).await.expect("connect to test database");
+    Some(TestApp::new(pool, Config::from_env()))
+}
+
+impl TestApp {
+    fn new(pool: PgPool, config: Config) -> Self {
+        let state = AppState::new(pool.clone(), config);
+        let router = create_routes().with_state(state.clone());
+        TestApp { pool, state, router }
+    }
+
+    /// The same app with the given account as an admin
+    pub fn with_admin(&self, account_id: i64) -> TestApp {
+        let mut config = self.state.config.as_ref().clone();
+        config.admin_account_ids.push(account_id);
+        TestApp::new(self.pool.clone(), config)
+    }
+
+    /// Create a fresh account directly in the database
+    pub async fn create_account(&self) -> TestAccount {
+        let username = format!("test-{}", Uuid::new_v4());
+        let (id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO accounts (username, nickname, mail, invitation_code, cell_number, is_new_user)
+            VALUES ($1, $1, $1, $1, $1, false)
+            RETURNING id
+            "#,
+        )
+        .bind(&username)
+        .fetch_one(&self.pool)
+        .await
+        .expect("insert account");
+
+        TestAccount {
+            id,
+            token: generate_token(id).expect("generate token"),
+        }
+    }
+
+    /// Create a database through the API and return its id
+    pub async fn create_database(&self, account: &TestAccount) -> String {
+        let (status, body) = self
+            .post(
+                account,
+                "/hulunote/create-database",
+                serde_json::json!({ "database-name": format!("db-{}", Uuid::new_v4()) }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body["database"]["hulunote-databases/id"]
+            .as_str()
+            .expect("database id")
+            .to_string()
+    }
+
+    /// Create a note through the API and return (note id, root nav id)
+    pub async fn create_note(&self, account: &TestAccount, database_id: &str, title: &str) -> (String, String) {
+        let (status, body) = self
+            .post(
+                account,
+                "/hulunote/new-note",
+                serde_json::json!({ "database-id": database_id, "title": title }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        (
+            body["hulunote-notes/id"].as_str().expect("note id").to_string(),
+            body["hulunote-notes/root-nav-id"].as_str().expect("root nav id").to_string(),
+        )
+    }
+
+    /// Create a nav through the API and return its id
+    pub async fn create_nav(&self, account: &TestAccount, note_id: &str, parid: &str, content: &str, order: f32) -> String {
+        let (status, body) = self
+            .post(
+                account,
+                "/hulunote/create-or-update-nav",
+                serde_json::json!({
+                    "note-id": note_id,
+                    "parid": parid,
+                    "content": content,
+                    "order": order
+                }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body["id"].as_str().expect("nav id").to_string()
+    }
+
+    /// Send an authenticated JSON POST request
+    pub async fn post(&self, account: &TestAccount, path: &str, body: Value) -> (StatusCode, Value) {
+        let request = Request::post(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", account.token))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    /// Send an authenticated multipart POST request.
+    /// Each part is (field name, optional file name, data).
+    pub async fn post_multipart(
+        &self,
+        account: &TestAccount,
+        path: &str,
+        parts: &[(&str, Option<&str>, &[u8])],
+    ) -> (StatusCode, Value) {
+        let boundary = format!("boundary-{}", Uuid::new_v4());
+        let mut body = Vec::new();
+        for (name, filename, data) in parts {
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            match filename {
+                Some(filename) => body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\r\n",
+                        name, filename
+                    )
+                    .as_bytes(),
+                ),
+                None => body.extend_from_slice(
+                    format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes(),
+                ),
+            }
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        let request = Request::post(path)
+            .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+            .header(header::AUTHORIZATION, format!("Bearer {}", account.token))
+            .body(Body::from(body))
+            .unwrap();
+        self.send(request).await
+    }
+
+    /// Send an authenticated JSON POST request and return the raw response body
+    pub async fn post_raw(&self, account: &TestAccount, path: &str, body: Value) -> (StatusCode, Vec<u8>) {
+        let request = Request::post(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", account.token))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        self.send_raw(request).await
+    }
+
+    /// Fetch a note's navs as a nested outline that ignores nav ids, so
+    /// copies of a note can be compared with the original
+    pub async fn note_outline(&self, account: &TestAccount, note_id: &str) -> Value {
+        let (status, body) = self
+            .post(account, "/hulunote/get-note-navs", serde_json::json!({ "note-id": note_id }))
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let navs = body["nav-list"].as_array().unwrap().clone();
+
+        fn children(navs: &[Value], parid: &str) -> Value {
+            let mut kids: Vec<&Value> = navs.iter().filter(|n| n["parid"] == parid).collect();
+            kids.sort_by(|a, b| {
+                a["same-deep-order"].as_f64().unwrap().total_cmp(&b["same-deep-order"].as_f64().unwrap())
+            });
+            Value::Array(
+                kids.into_iter()
+                    .map(|n| {
+                        serde_json::json!({
+                            "content": n["content"],
+                            "order": n["same-deep-order"],
+                            "is-display": n["is-display"],
+                            "properties": n["properties"],
+                            "children": children(navs, n["id"].as_str().unwrap())
+                        })
+                    })
+                    .collect(),
+            )
+        }
+
+        let root = navs
+            .iter()
+            .find(|n| n["parid"] == "00000000-0000-0000-0000-000000000000")
+            .expect("root nav");
+        children(&navs, root["id"].as_str().unwrap())
+    }
+
+    /// Send a request and decode the JSON response
+    pub async fn send(&self, request: Request<Body>) -> (StatusCode, Value) {
+        let (status, bytes) = self.send_raw(request).await;
+        let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, value)
+    }
+
+    /// Send a request and return the raw response body
+    pub async fn send_raw(&self, request: Request<Body>) -> (StatusCode, Vec<u8>) {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, bytes.to_vec())
+    }
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+#[tokio::test]
+async fn daily_notes_follow_the_account_date_format() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let (status, body) = app
+        .post(&owner, "/user/update-profile", json!({ "date-format": "%B %d, %Y" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["profile"]["accounts/date-format"], "%B %d, %Y");
+
+    let daily = |date: &str| {
+        let (app, owner, database_id) = (&app, &owner, &database_id);
+        let body = json!({ "database-id": database_id, "date": date });
+        async move { app.post(owner, "/hulunote/get-or-create-daily-note", body).await }
+    };
+
+    let (status, created) = daily("2026-10-17").await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+    assert_eq!(created["created"], true);
+    assert_eq!(created["date"], "2026-10-17");
+    assert_eq!(created["note"]["hulunote-notes/title"], "October 17, 2026");
+    let note_id = created["note"]["hulunote-notes/id"].as_str().unwrap();
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-note-navs", json!({ "note-id": note_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["nav-list"][0]["content"], "ROOT");
+
+    let (_, again) = daily("2026-10-17").await;
+    assert_eq!(again["created"], false);
+    assert_eq!(again["note"]["hulunote-notes/id"], note_id);
+
+    // Journal notes titled in other formats are found too
+    let (imported, _) = app.create_note(&owner, &database_id, "2026-10-02").await;
+    let (_, body) = daily("2026-10-02").await;
+    assert_eq!(body["created"], false);
+    assert_eq!(body["note"]["hulunote-notes/id"], imported.as_str());
+    app.create_note(&owner, &database_id, "Not a date").await;
+    app.create_note(&owner, &database_id, "2026-11-01").await;
+
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/get-daily-notes",
+            json!({ "database-id": database_id, "from": "2026-10-01", "to": "2026-10-31" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let dates: Vec<&str> = body["daily-notes"].as_array().unwrap().iter().map(|d| d["date"].as_str().unwrap()).collect();
+    assert_eq!(dates, vec!["2026-10-02", "2026-10-17"]);
+
+    let (status, _) = daily("17/10/2026").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/get-daily-notes",
+            json!({ "database-id": database_id, "from": "2026-10-31", "to": "2026-10-01" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/get-daily-notes",
+            json!({ "database-id": database_id, "from": "2000-01-01", "to": "2100-01-01" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn today_depends_on_the_timezone() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    for profile in [
+        json!({ "timezone": "Mars/Olympus_Mons" }),
+        json!({ "date-format": "%Y-%m" }),
+        json!({ "date-format": "%Q" }),
+    ] {
+        let (status, body) = app.post(&owner, "/user/update-profile", profile).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+
+    let (status, body) = app
+        .post(&owner, "/user/update-profile", json!({ "timezone": "Pacific/Kiritimati" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["profile"]["accounts/timezone"], "Pacific/Kiritimati");
+
+    // UTC+14 from the profile, UTC-11 as a per-request override
+    let (status, ahead) = app
+        .post(&owner, "/hulunote/get-or-create-daily-note", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", ahead);
+    let expected = (Utc::now() + Duration::hours(14)).date_naive().format("%Y-%m-%d").to_string();
+    assert_eq!(ahead["date"], expected.as_str());
+    assert_eq!(ahead["note"]["hulunote-notes/title"], expected.as_str());
+
+    let (status, behind) = app
+        .post(
+            &owner,
+            "/hulunote/get-or-create-daily-note",
+            json!({ "database-id": database_id, "timezone": "Pacific/Pago_Pago" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", behind);
+    assert_eq!(behind["created"], true);
+    assert_ne!(behind["date"], ahead["date"]);
+
+    let (_, body) = app
+        .post(
+            &owner,
+            "/hulunote/get-daily-notes",
+            json!({ "database-id": database_id, "from": behind["date"], "to": ahead["date"] }),
+        )
+        .await;
+    assert_eq!(body["today"], ahead["date"]);
+    assert_eq!(body["daily-notes"].as_array().unwrap().len(), 2);
+
+    // Someone else's database cannot be written to
+    let stranger = app.create_account().await;
+    let (status, _) = app
+        .post(&stranger, "/hulunote/get-or-create-daily-note", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::Receiver;
+
+use common::{TestAccount, TestApp};
+use hulunote_server::handlers::ws::WsMessage;
+
+async fn subscribe(app: &TestApp, account: &TestAccount) -> Receiver<WsMessage> {
+    app.state.ws_broadcaster.get_sender(account.id).await.subscribe()
+}
+
+/// Events pushed to a subscriber so far
+fn events(receiver: &mut Receiver<WsMessage>) -> Vec<Value> {
+    std::iter::from_fn(|| receiver.try_recv().ok())
+        .map(|msg| serde_json::from_str(&msg.text).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn note_and_nav_changes_carry_changed_fields() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let member = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (status, body) = app
+        .post(&owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": member.id, "role": "viewer" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (note_id, root) = app.create_note(&owner, &database_id, "Old").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root, "first", 1.0).await;
+    let mut received = subscribe(&app, &member).await;
+
+    // A nav edit sends only the fields that changed
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": nav_id, "content": "edited" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let pushed = events(&mut received);
+    assert_eq!(pushed.len(), 1, "{:?}", pushed);
+    assert_eq!(pushed[0]["type"], "nav_updated");
+    assert_eq!(pushed[0]["nav-id"], nav_id.as_str());
+    assert_eq!(pushed[0]["content"], "edited");
+    assert!(pushed[0]["backend-ts"].is_i64());
+    assert!(pushed[0].get("parid").is_none());
+
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": nav_id, "is-delete": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let pushed = events(&mut received);
+    assert_eq!(pushed[0]["type"], "nav_deleted");
+    assert_eq!(pushed[0]["nav-id"], nav_id.as_str());
+
+    // Renames, toggles and deletes of notes
+    let (status, body) = app
+        .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": note_id, "title": "New", "is-shortcut": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let pushed = events(&mut received);
+    assert_eq!(pushed.len(), 1, "{:?}", pushed);
+    assert_eq!(pushed[0]["type"], "note_updated");
+    assert_eq!(pushed[0]["title"], "New");
+    assert_eq!(pushed[0]["is-shortcut"], true);
+    assert!(pushed[0].get("is-public").is_none());
+    assert_eq!(pushed[0]["backend-ts"], body["backend-ts"]);
+
+    let (status, _) = app
+        .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": note_id, "is-delete": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let pushed = events(&mut received);
+    assert_eq!(pushed[0]["type"], "note_deleted");
+    assert_eq!(pushed[0]["note-id"], note_id.as_str());
+    assert_eq!(pushed[0]["database-id"], database_id.as_str());
+}
+
+#[tokio::test]
+async fn database_and_profile_changes_are_pushed() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let member = app.create_account().await;
+    let mut owner_events = subscribe(&app, &owner).await;
+    let database_id = app.create_database(&owner).await;
+    let pushed = events(&mut owner_events);
+    assert_eq!(pushed[0]["type"], "database_created");
+    assert_eq!(pushed[0]["database"]["hulunote-databases/id"], database_id.as_str());
+
+    let (status, _) = app
+        .post(&owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": member.id, "role": "editor" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let mut member_events = subscribe(&app, &member).await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/update-database", json!({ "database-id": database_id, "db-name": "Renamed" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let pushed = events(&mut member_events);
+    assert_eq!(pushed.len(), 1, "{:?}", pushed);
+    assert_eq!(pushed[0]["type"], "database_updated");
+    assert_eq!(pushed[0]["name"], "Renamed");
+    assert!(pushed[0].get("is-public").is_none());
+
+    // Profile changes only reach the account's own connections
+    events(&mut owner_events);
+    let (status, _) = app.post(&member, "/user/update-profile", json!({ "nickname": "Sam" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let pushed = events(&mut member_events);
+    assert_eq!(pushed[0]["type"], "profile_updated");
+    assert_eq!(pushed[0]["nickname"], "Sam");
+    assert!(pushed[0].get("timezone").is_none());
+    assert!(events(&mut owner_events).is_empty());
+
+    let (status, _) = app.post(&owner, "/hulunote/delete-database", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::OK);
+    let pushed = events(&mut member_events);
+    assert_eq!(pushed[0]["type"], "database_deleted");
+    assert_eq!(pushed[0]["database-id"], database_id.as_str());
+}
@@ -0,0 +1,280 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use std::io::Read;
+
+use common::{TestAccount, TestApp};
+
+/// Build a small outline with nesting, a collapsed nav and properties
+async fn sample_note(app: &TestApp, owner: &TestAccount, database_id: &str, title: &str) -> String {
+    let (note_id, root) = app.create_note(owner, database_id, title).await;
+    let first = app.create_nav(owner, &note_id, &root, "first", 1.0).await;
+    app.create_nav(owner, &note_id, &first, "first child", 1.0).await;
+    let second = app.create_nav(owner, &note_id, &first, "second child", 2.0).await;
+    app.create_nav(owner, &note_id, &second, "grandchild with [[Link]]", 1.0).await;
+    app.create_nav(owner, &note_id, &root, "second", 2.5).await;
+
+    let (status, _) = app
+        .post(
+            owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": second, "is-display": false, "properties": "status:: open" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    note_id
+}
+
+/// Read every entry of a ZIP archive
+fn unzip(data: Vec<u8>) -> Vec<(String, Value)> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+    (0..archive.len())
+        .map(|i| {
+            let mut entry = archive.by_index(i).unwrap();
+            let mut buf = String::new();
+            entry.read_to_string(&mut buf).unwrap();
+            (entry.name().to_string(), serde_json::from_str(&buf).unwrap())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn exported_note_uses_import_format() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let note_id = sample_note(&app, &owner, &database_id, "Plans/2026").await;
+
+    let (status, data) = app.post_raw(&owner, "/hulunote/export-note", json!({ "note-id": note_id })).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let files = unzip(data);
+    assert_eq!(files.len(), 1);
+    let (name, file) = &files[0];
+    assert_eq!(name, "Plans_2026.json");
+    assert_eq!(file["note"]["hulunote-notes/id"], note_id.as_str());
+    assert_eq!(file["note"]["hulunote-notes/title"], "Plans/2026");
+    assert!(file["note"]["hulunote-navs/root-nav-id"].is_string());
+    // The root nav is implied by root-nav-id
+    assert_eq!(file["navs"].as_array().unwrap().len(), 5);
+    for nav in file["navs"].as_array().unwrap() {
+        assert_eq!(nav["hulunote-note"], note_id.as_str());
+        assert!(nav["same-deep-order"].is_number());
+    }
+
+    // Other accounts cannot export it
+    let other = app.create_account().await;
+    let (status, _) = app.post_raw(&other, "/hulunote/export-note", json!({ "note-id": note_id })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn export_then_import_into_another_account_reproduces_outline() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let note_id = sample_note(&app, &owner, &database_id, "Moving").await;
+    let original = app.note_outline(&owner, &note_id).await;
+
+    let (_, data) = app.post_raw(&owner, "/hulunote/export-note", json!({ "note-id": note_id })).await;
+
+    let receiver = app.create_account().await;
+    let target_database = app.create_database(&receiver).await;
+    let (status, body) = app
+        .post_multipart(
+            &receiver,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, target_database.as_bytes()),
+                ("new-ids", None, b"true"),
+                ("file", Some("export.zip"), &data),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported-count"], 1, "{}", body);
+
+    let copy_id = body["imported"][0]["note-id"].as_str().unwrap();
+    assert_ne!(copy_id, note_id);
+    assert_eq!(app.note_outline(&receiver, copy_id).await, original);
+}
+
+#[tokio::test]
+async fn database_export_restores_identical_notes() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let first = sample_note(&app, &owner, &database_id, "First").await;
+    let second = sample_note(&app, &owner, &database_id, "Second").await;
+
+    let (_, before_first) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": first })).await;
+    let (_, before_second) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": second })).await;
+
+    let (status, data) = app
+        .post_raw(&owner, "/hulunote/export-database", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(unzip(data.clone()).len(), 2);
+
+    // Lose the notes, then restore them from the backup
+    sqlx::query("DELETE FROM hulunote_navs WHERE database_id = $1")
+        .bind(&database_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM hulunote_notes WHERE database_id = $1")
+        .bind(&database_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, database_id.as_bytes()),
+                ("file", Some("backup.zip"), &data),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported-count"], 2, "{}", body);
+
+    // Same ids, tree, order, content and properties
+    let strip = |body: Value| -> Vec<Value> {
+        let mut navs: Vec<Value> = body["nav-list"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| {
+                json!([n["id"], n["parid"], n["same-deep-order"], n["content"], n["is-display"], n["properties"]])
+            })
+            .collect();
+        navs.sort_by_key(|n| n[0].as_str().unwrap().to_string());
+        navs
+    };
+    let (_, after_first) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": first })).await;
+    let (_, after_second) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": second })).await;
+    assert_eq!(strip(after_first), strip(before_first));
+    assert_eq!(strip(after_second), strip(before_second));
+}
+
+#[tokio::test]
+async fn import_refuses_navs_that_already_exist() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let note_id = sample_note(&app, &owner, &database_id, "Existing").await;
+
+    let (_, data) = app.post_raw(&owner, "/hulunote/export-note", json!({ "note-id": note_id })).await;
+
+    // The note is gone but its navs are still there
+    sqlx::query("DELETE FROM hulunote_notes WHERE id::text = $1")
+        .bind(&note_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, database_id.as_bytes()),
+                ("file", Some("export.zip"), &data),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported-count"], 0, "{}", body);
+    assert!(body["errors"][0]["error"].as_str().unwrap().contains("already exists"), "{}", body);
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM hulunote_notes WHERE id::text = $1")
+        .bind(&note_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn import_refuses_parents_outside_the_note() {
+    let Some(app) = common::setup().await else { return };
+    let victim = app.create_account().await;
+    let victim_database = app.create_database(&victim).await;
+    let (victim_note, victim_root) = app.create_note(&victim, &victim_database, "Private").await;
+    let victim_nav = app.create_nav(&victim, &victim_note, &victim_root, "secret", 1.0).await;
+
+    let attacker = app.create_account().await;
+    let database_id = app.create_database(&attacker).await;
+    let (note_id, root_nav_id) = (uuid::Uuid::new_v4().to_string(), uuid::Uuid::new_v4().to_string());
+    let file = json!({
+        "note": { "hulunote-notes/id": note_id, "hulunote-notes/title": "Graft", "hulunote-navs/root-nav-id": root_nav_id },
+        "navs": [{
+            "id": uuid::Uuid::new_v4().to_string(),
+            "parid": victim_nav,
+            "content": "grafted",
+            "same-deep-order": 1.0,
+            "hulunote-note": note_id
+        }]
+    })
+    .to_string();
+
+    for new_ids in ["false", "true"] {
+        let (status, body) = app
+            .post_multipart(
+                &attacker,
+                "/hulunote/import-notes",
+                &[
+                    ("database-id", None, database_id.as_bytes()),
+                    ("new-ids", None, new_ids.as_bytes()),
+                    ("file", Some("graft.json"), file.as_bytes()),
+                ],
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["imported-count"], 0, "{}", body);
+        assert!(body["errors"][0]["error"].as_str().unwrap().contains("parent outside the note"), "{}", body);
+    }
+    let outline = app.note_outline(&victim, &victim_note).await;
+    assert!(outline[0]["children"].as_array().unwrap().is_empty(), "{}", outline);
+}
+
+#[tokio::test]
+async fn database_export_keeps_each_notes_navs_apart() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let mut expected = Vec::new();
+    for i in 0..12 {
+        let title = format!("Note {}", i);
+        let (note_id, root) = app.create_note(&owner, &database_id, &title).await;
+        // Every third note is empty
+        let contents: Vec<String> = if i % 3 == 0 {
+            Vec::new()
+        } else {
+            (0..i).map(|j| format!("{} line {}", title, j)).collect()
+        };
+        for (j, content) in contents.iter().enumerate() {
+            app.create_nav(&owner, &note_id, &root, content, j as f32).await;
+        }
+        expected.push((format!("{}.json", title), contents));
+    }
+
+    let (status, data) = app
+        .post_raw(&owner, "/hulunote/export-database", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let files = unzip(data);
+    let exported: Vec<(String, Vec<String>)> = files
+        .into_iter()
+        .map(|(name, note)| {
+            let contents = note["navs"].as_array().unwrap().iter().map(|n| n["content"].as_str().unwrap().to_string());
+            (name, contents.collect())
+        })
+        .collect();
+    assert_eq!(exported, expected);
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{TestAccount, TestApp};
+use hulunote_server::handlers::fsck_database;
+use hulunote_server::models::{FsckIssueKind, FsckRepair, FsckReport};
+
+const ROOT_NAV_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Insert a nav directly, bypassing the checks of the API
+async fn insert_nav(app: &TestApp, owner: &TestAccount, note_id: &str, database_id: &str, parid: &str, content: &str) -> String {
+    let (id,): (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO hulunote_navs (id, parid, same_deep_order, content, account_id, note_id, database_id)
+        VALUES ($1, $2, 1, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(parid)
+    .bind(content)
+    .bind(owner.id)
+    .bind(note_id)
+    .bind(database_id)
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    id.to_string()
+}
+
+async fn set_parid(app: &TestApp, nav_id: &str, parid: &str) {
+    sqlx::query("UPDATE hulunote_navs SET parid = $2 WHERE id::text = $1")
+        .bind(nav_id)
+        .bind(parid)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+}
+
+fn kinds(report: &FsckReport) -> Vec<(FsckIssueKind, String)> {
+    let mut kinds: Vec<(FsckIssueKind, String)> =
+        report.issues.iter().map(|i| (i.kind, i.nav_id.clone())).collect();
+    kinds.sort_by_key(|(_, id)| id.clone());
+    kinds
+}
+
+#[tokio::test]
+async fn reattach_fixes_orphans_cycles_and_stray_roots() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let db = Uuid::parse_str(&database_id).unwrap();
+    let (note_id, root) = app.create_note(&owner, &database_id, "Broken").await;
+    let parent = app.create_nav(&owner, &note_id, &root, "parent", 1.0).await;
+    let child = app.create_nav(&owner, &note_id, &parent, "child", 1.0).await;
+
+    let report = fsck_database(&app.pool, db, None).await.unwrap();
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+    assert_eq!(report.navs_checked, 3);
+
+    let orphan = insert_nav(&app, &owner, &note_id, &database_id, &Uuid::new_v4().to_string(), "orphan").await;
+    let stray_root = insert_nav(&app, &owner, &note_id, &database_id, ROOT_NAV_ID, "stray root").await;
+    let x = app.create_nav(&owner, &note_id, &root, "x", 2.0).await;
+    let y = app.create_nav(&owner, &note_id, &x, "y", 1.0).await;
+    set_parid(&app, &x, &y).await;
+    sqlx::query("UPDATE hulunote_navs SET database_id = $2 WHERE id::text = $1")
+        .bind(&child)
+        .bind(Uuid::new_v4().to_string())
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let report = fsck_database(&app.pool, db, None).await.unwrap();
+    let mut expected = vec![
+        (FsckIssueKind::Orphan, orphan.clone()),
+        (FsckIssueKind::DuplicateRoot, stray_root.clone()),
+        (FsckIssueKind::Cycle, x.clone().min(y.clone())),
+        (FsckIssueKind::MismatchedDatabase, child.clone()),
+    ];
+    expected.sort_by_key(|(_, id)| id.clone());
+    assert_eq!(kinds(&report), expected);
+    assert_eq!(report.repaired, 0);
+
+    let report = fsck_database(&app.pool, db, Some(FsckRepair::Reattach)).await.unwrap();
+    assert_eq!(report.repaired, 4);
+    assert!(report.recovered_note_id.is_none());
+
+    let report = fsck_database(&app.pool, db, None).await.unwrap();
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+
+    // Everything hangs off the note's root again
+    let outline = app.note_outline(&owner, &note_id).await;
+    let mut top: Vec<&str> = outline.as_array().unwrap().iter().map(|n| n["content"].as_str().unwrap()).collect();
+    top.sort();
+    assert_eq!(top, vec!["orphan", "parent", "stray root", if x < y { "x" } else { "y" }]);
+}
+
+#[tokio::test]
+async fn recover_moves_misplaced_navs_into_a_recovered_note() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let db = Uuid::parse_str(&database_id).unwrap();
+    let (first, first_root) = app.create_note(&owner, &database_id, "First").await;
+    let (second, second_root) = app.create_note(&owner, &database_id, "Second").await;
+    let parent = app.create_nav(&owner, &first, &first_root, "parent", 1.0).await;
+    let stray = app.create_nav(&owner, &second, &second_root, "stray", 1.0).await;
+    app.create_nav(&owner, &second, &stray, "stray child", 1.0).await;
+
+    // A nav of the second note under a nav of the first, and a lost root
+    set_parid(&app, &stray, &parent).await;
+    sqlx::query("DELETE FROM hulunote_navs WHERE id::text = $1")
+        .bind(&first_root)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    // Only admins may use the endpoint
+    let (status, _) = app
+        .post(&owner, "/admin/fsck", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let app = app.with_admin(owner.id);
+    let mut received = app.state.ws_broadcaster.get_sender(owner.id).await.subscribe();
+    let (status, body) = app
+        .post(&owner, "/admin/fsck", json!({ "database-id": database_id, "repair": "recover" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let kinds: Vec<&str> = body["issues"].as_array().unwrap().iter().map(|i| i["kind"].as_str().unwrap()).collect();
+    assert!(kinds.contains(&"missing-root"), "{}", body);
+    assert!(kinds.contains(&"mismatched-note"), "{}", body);
+    assert_eq!(body["repaired"], body["issues"].as_array().unwrap().len());
+
+    // The parent lost its root, so it ends up recovered along with the stray nav
+    let recovered = body["recovered-note-id"].as_str().unwrap();
+    let outline = app.note_outline(&owner, recovered).await;
+    assert_eq!(outline.as_array().unwrap().len(), 2, "{}", outline);
+    assert!(outline.as_array().unwrap().iter().any(|n| n["content"] == "stray" && n["children"][0]["content"] == "stray child"));
+
+    assert!(app.note_outline(&owner, &first).await.as_array().unwrap().is_empty());
+
+    // Clients hear about the new note and the navs that moved into it
+    let pushed: Vec<Value> = std::iter::from_fn(|| received.try_recv().ok())
+        .map(|msg| serde_json::from_str(&msg.text).unwrap())
+        .collect();
+    assert_eq!(pushed[0]["type"], "note_created", "{:?}", pushed);
+    assert_eq!(pushed[0]["note-id"], recovered);
+    let moved = pushed.iter().find(|e| e["type"] == "navs_batch" && e["note-id"] == recovered).unwrap();
+    assert!(moved["navs"].as_array().unwrap().iter().any(|n| n["content"] == "stray"));
+    assert!(fsck_database(&app.pool, db, None).await.unwrap().issues.is_empty());
+}
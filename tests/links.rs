@@ -0,0 +1,121 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn backlinks_follow_page_and_block_references() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+
+    let database_id = app.create_database(&owner).await;
+    let (target_note, target_root) = app.create_note(&owner, &database_id, "Project Alpha").await;
+    let target_nav = app.create_nav(&owner, &target_note, &target_root, "kickoff", 1.0).await;
+
+    let (source_note, source_root) = app.create_note(&owner, &database_id, "Journal").await;
+    let parent = app.create_nav(&owner, &source_note, &source_root, "meetings", 1.0).await;
+    let page_ref = app
+        .create_nav(&owner, &source_note, &parent, "talked about [[Project Alpha]] today", 1.0)
+        .await;
+    let block_ref = app
+        .create_nav(&owner, &source_note, &source_root, &format!("see (({}))", target_nav), 2.0)
+        .await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-backlinks", json!({ "note-id": target_note }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let backlinks = body["backlinks"].as_array().unwrap();
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0]["nav"]["id"], page_ref.as_str());
+    assert_eq!(backlinks[0]["note-title"], "Journal");
+    assert_eq!(backlinks[0]["path"], json!([{ "id": parent, "content": "meetings" }]));
+
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-backlinks", json!({ "nav-id": target_nav }))
+        .await;
+    let backlinks = body["backlinks"].as_array().unwrap();
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0]["nav"]["id"], block_ref.as_str());
+
+    // Editing the content away removes the link
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": source_note, "id": block_ref, "content": "nothing here" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-backlinks", json!({ "nav-id": target_nav }))
+        .await;
+    assert!(body["backlinks"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn renaming_a_note_can_rewrite_references() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+
+    let database_id = app.create_database(&owner).await;
+    let (target_note, _) = app.create_note(&owner, &database_id, "Old Name").await;
+    let (source_note, source_root) = app.create_note(&owner, &database_id, "Index").await;
+    let nav = app
+        .create_nav(&owner, &source_note, &source_root, "[[Old Name]] and [[Other]]", 1.0)
+        .await;
+
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/update-hulunote-note",
+            json!({ "note-id": target_note, "title": "New Name", "rewrite-references": true }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["rewritten-navs"], 1);
+
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-backlinks", json!({ "note-id": target_note }))
+        .await;
+    let backlinks = body["backlinks"].as_array().unwrap();
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0]["nav"]["id"], nav.as_str());
+    assert_eq!(backlinks[0]["nav"]["content"], "[[New Name]] and [[Other]]");
+}
+
+#[tokio::test]
+async fn outsiders_only_see_backlinks_from_public_notes() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let outsider = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (target_note, target_root) = app.create_note(&owner, &database_id, "Launch").await;
+    let target_nav = app.create_nav(&owner, &target_note, &target_root, "date", 1.0).await;
+    let (private_note, private_root) = app.create_note(&owner, &database_id, "Secrets").await;
+    app.create_nav(&owner, &private_note, &private_root, "salary talk before [[Launch]]", 1.0).await;
+    app.create_nav(&owner, &private_note, &private_root, &format!("moved (({}))", target_nav), 2.0).await;
+    let (public_note, public_root) = app.create_note(&owner, &database_id, "Blog").await;
+    let public_ref = app.create_nav(&owner, &public_note, &public_root, "announcing [[Launch]]", 1.0).await;
+    for note_id in [&target_note, &public_note] {
+        let (status, _) = app
+            .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": note_id, "is-public": true }))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, body) = app.post(&outsider, "/hulunote/get-backlinks", json!({ "note-id": target_note })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let backlinks = body["backlinks"].as_array().unwrap();
+    assert_eq!(backlinks.len(), 1, "{}", body);
+    assert_eq!(backlinks[0]["nav"]["id"], public_ref.as_str());
+    assert!(!body.to_string().contains("salary"));
+
+    let (status, body) = app.post(&outsider, "/hulunote/get-backlinks", json!({ "nav-id": target_nav })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["backlinks"].as_array().unwrap().is_empty(), "{}", body);
+
+    // The owner still sees every reference
+    let (_, body) = app.post(&owner, "/hulunote/get-backlinks", json!({ "note-id": target_note })).await;
+    assert_eq!(body["backlinks"].as_array().unwrap().len(), 2);
+}
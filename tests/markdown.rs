@@ -0,0 +1,200 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+
+/// Outline without orders, which Markdown import renumbers
+fn shape(outline: &Value) -> Value {
+    Value::Array(
+        outline
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| {
+                json!({
+                    "content": n["content"],
+                    "is-display": n["is-display"],
+                    "properties": n["properties"],
+                    "children": shape(&n["children"])
+                })
+            })
+            .collect(),
+    )
+}
+
+async fn sample_note(app: &TestApp, owner: &TestAccount, database_id: &str) -> String {
+    let (note_id, root) = app.create_note(owner, database_id, "Weekly Review").await;
+    let first = app.create_nav(owner, &note_id, &root, "Wins", 1.0).await;
+    let nested = app.create_nav(owner, &note_id, &first, "shipped export\nwith two lines", 1.0).await;
+    app.create_nav(owner, &note_id, &nested, "deep", 1.0).await;
+    let second = app.create_nav(owner, &note_id, &root, "Next", 2.5).await;
+    app.create_nav(owner, &note_id, &second, "hidden child", 1.0).await;
+
+    for (id, body) in [
+        (&nested, json!({ "properties": "status:: done\nowner:: me" })),
+        (&second, json!({ "is-display": false, "properties": "{\"color\": \"red\"}" })),
+    ] {
+        let mut body = body;
+        body["note-id"] = json!(note_id);
+        body["id"] = json!(id);
+        let (status, _) = app.post(owner, "/hulunote/create-or-update-nav", body).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    note_id
+}
+
+#[tokio::test]
+async fn note_exports_as_nested_bullets() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let note_id = sample_note(&app, &owner, &database_id).await;
+
+    let (status, data) = app
+        .post_raw(&owner, "/hulunote/export-note", json!({ "note-id": note_id, "format": "markdown" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        String::from_utf8(data).unwrap(),
+        "- Wins\n\
+         \x20 - shipped export\n\
+         \x20   with two lines\n\
+         \x20   status:: done\n\
+         \x20   owner:: me\n\
+         \x20   - deep\n\
+         - Next\n\
+         \x20 collapsed:: true\n\
+         \x20 properties:: \"{\\\"color\\\": \\\"red\\\"}\"\n\
+         \x20 - hidden child\n"
+    );
+}
+
+#[tokio::test]
+async fn markdown_round_trips_through_import() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let note_id = sample_note(&app, &owner, &database_id).await;
+    let original = shape(&app.note_outline(&owner, &note_id).await);
+
+    let (_, markdown) = app
+        .post_raw(&owner, "/hulunote/export-note", json!({ "note-id": note_id, "format": "markdown" }))
+        .await;
+
+    let target_database = app.create_database(&owner).await;
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, target_database.as_bytes()),
+                ("file", Some("Weekly Review.md"), &markdown),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported-count"], 1, "{}", body);
+    assert_eq!(body["imported"][0]["title"], "Weekly Review");
+
+    let copy_id = body["imported"][0]["note-id"].as_str().unwrap();
+    assert_eq!(shape(&app.note_outline(&owner, copy_id).await), original);
+}
+
+#[tokio::test]
+async fn content_lines_that_look_like_markup_round_trip() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Tricky").await;
+    let content = "Shopping\n- milk\n* eggs\n-\nstatus:: not a property\n\n  indented\n\\escaped\nplain";
+    let nav = app.create_nav(&owner, &note_id, &root, content, 1.0).await;
+    app.create_nav(&owner, &note_id, &nav, "child", 1.0).await;
+    let original = shape(&app.note_outline(&owner, &note_id).await);
+
+    let (_, markdown) = app
+        .post_raw(&owner, "/hulunote/export-note", json!({ "note-id": note_id, "format": "markdown" }))
+        .await;
+    let target_database = app.create_database(&owner).await;
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, target_database.as_bytes()),
+                ("file", Some("Tricky.md"), &markdown),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let copy_id = body["imported"][0]["note-id"].as_str().unwrap();
+    let copy = shape(&app.note_outline(&owner, copy_id).await);
+    assert_eq!(copy, original, "{}", String::from_utf8_lossy(&markdown));
+    assert_eq!(copy[0]["content"], content);
+}
+
+#[tokio::test]
+async fn markdown_files_inside_zip_are_imported() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let note_id = sample_note(&app, &owner, &database_id).await;
+    let original = shape(&app.note_outline(&owner, &note_id).await);
+
+    let (status, data) = app
+        .post_raw(&owner, "/hulunote/export-database", json!({ "database-id": database_id, "format": "markdown" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let target_database = app.create_database(&owner).await;
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, target_database.as_bytes()),
+                ("file", Some("notes.zip"), &data),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported-count"], 1, "{}", body);
+
+    let copy_id = body["imported"][0]["note-id"].as_str().unwrap();
+    assert_eq!(shape(&app.note_outline(&owner, copy_id).await), original);
+}
+
+#[tokio::test]
+async fn plain_markdown_becomes_an_outline() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let markdown = "# Heading\n\n* one\n\t* nested with tab\n+ two\n\ntrailing paragraph\n";
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, database_id.as_bytes()),
+                ("file", Some("folder/Plain.md"), markdown.as_bytes()),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported"][0]["title"], "Plain");
+
+    let copy_id = body["imported"][0]["note-id"].as_str().unwrap();
+    let outline = app.note_outline(&owner, copy_id).await;
+    let contents: Vec<&str> = outline
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, vec!["# Heading", "one", "two", "trailing paragraph"]);
+    assert_eq!(outline[1]["children"][0]["content"], "nested with tab");
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn roles_gate_reads_writes_and_management() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let viewer = app.create_account().await;
+    let editor = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Plans").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root, "original", 1.0).await;
+
+    // Members are invited by id; non-owners cannot invite
+    let (status, _) = app
+        .post(&editor, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": editor.id, "role": "owner" }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    for (member, role) in [(&viewer, "viewer"), (&editor, "editor")] {
+        let (status, body) = app
+            .post(&owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": member.id, "role": role }))
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["member"]["role"], role);
+        assert_eq!(body["member"]["invited-by"], owner.id);
+    }
+    let (status, _) = app
+        .post(&owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": viewer.id, "role": "editor" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Viewers read but cannot write; editors write but cannot manage
+    let (status, _) = app.post(&viewer, "/hulunote/get-note-navs", json!({ "note-id": note_id })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .post(&viewer, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": nav_id, "content": "viewer edit" }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app
+        .post(&editor, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": nav_id, "content": "editor edit" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = app
+        .post(&editor, "/hulunote/update-database", json!({ "database-id": database_id, "db-name": "Renamed" }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.post(&editor, "/hulunote/delete-database", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The author stays, the last editor is recorded
+    let (_, body) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": note_id })).await;
+    let nav = body["nav-list"].as_array().unwrap().iter().find(|n| n["id"] == nav_id.as_str()).unwrap().clone();
+    assert_eq!(nav["content"], "editor edit");
+    assert_eq!(nav["account-id"], owner.id);
+    assert_eq!(nav["last-account-id"], editor.id);
+
+    // Promoting the editor to owner lets them manage members
+    let (status, body) = app
+        .post(&owner, "/hulunote/update-database-member", json!({ "database-id": database_id, "account-id": editor.id, "role": "owner" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = app
+        .post(&editor, "/hulunote/update-database-member", json!({ "database-id": database_id, "account-id": owner.id, "role": "viewer" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .post(&editor, "/hulunote/remove-database-member", json!({ "database-id": database_id, "account-id": viewer.id }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post(&viewer, "/hulunote/get-note-navs", json!({ "note-id": note_id })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn shared_databases_are_listed_with_roles() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let member = app.create_account().await;
+    let stranger = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let own_id = app.create_database(&member).await;
+    app.create_note(&owner, &database_id, "Shared note").await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": member.id, "role": "commenter" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let username = body["member"]["username"].as_str().unwrap().to_string();
+
+    let (status, body) = app.post(&member, "/hulunote/get-database-list", json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let list: Vec<(&str, &str)> = body["database-list"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["hulunote-databases/id"].as_str().unwrap(), d["hulunote-databases/role"].as_str().unwrap()))
+        .collect();
+    assert_eq!(list, vec![(own_id.as_str(), "owner"), (database_id.as_str(), "commenter")]);
+
+    let (status, body) = app.post(&member, "/hulunote/get-note-list", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = app.post(&member, "/hulunote/new-note", json!({ "database-id": database_id, "title": "Comment" })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Members see each other, the creator first; strangers see nobody
+    let (status, body) = app.post(&member, "/hulunote/get-database-members", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["members"][0]["account-id"], owner.id);
+    assert_eq!(body["members"][0]["role"], "owner");
+    assert_eq!(body["members"][1]["username"], username.as_str());
+    let (status, _) = app.post(&stranger, "/hulunote/get-database-members", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Members can leave, the creator cannot
+    let (status, _) = app
+        .post(&owner, "/hulunote/remove-database-member", json!({ "database-id": database_id, "account-id": owner.id }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .post(&member, "/hulunote/remove-database-member", json!({ "database-id": database_id, "account-id": member.id }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = app.post(&member, "/hulunote/get-database-list", json!({})).await;
+    assert_eq!(body["database-list"].as_array().unwrap().len(), 1);
+}
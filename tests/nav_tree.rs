@@ -0,0 +1,123 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+/// Contents of a nav tree, nested like the tree
+fn contents(tree: &Value) -> Value {
+    let children: Vec<Value> = tree["children"].as_array().unwrap().iter().map(contents).collect();
+    if children.is_empty() {
+        tree["content"].clone()
+    } else {
+        json!({ tree["content"].as_str().unwrap(): children })
+    }
+}
+
+#[tokio::test]
+async fn note_navs_can_be_returned_as_a_tree() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Tree").await;
+    let b = app.create_nav(&owner, &note_id, &root, "b", 2.0).await;
+    let a = app.create_nav(&owner, &note_id, &root, "a", 1.0).await;
+    app.create_nav(&owner, &note_id, &a, "a2", 2.0).await;
+    app.create_nav(&owner, &note_id, &a, "a1", 1.0).await;
+    let gone = app.create_nav(&owner, &note_id, &b, "gone", 1.0).await;
+
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": gone, "is-delete": true }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-note-navs", json!({ "note-id": note_id, "tree": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let tree = &body["nav-tree"];
+    assert_eq!(tree["id"], root.as_str());
+    assert_eq!(tree["children"][0]["note-id"], note_id.as_str());
+    assert_eq!(contents(&tree["children"][0]), json!({ "a": ["a1", "a2"] }));
+    assert_eq!(contents(&tree["children"][1]), json!("b"));
+
+    // The flat list is still the default
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-note-navs", json!({ "note-id": note_id }))
+        .await;
+    assert_eq!(body["nav-list"].as_array().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn subtree_honours_depth_and_collapsed_navs() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Subtree").await;
+    let top = app.create_nav(&owner, &note_id, &root, "top", 1.0).await;
+    let open = app.create_nav(&owner, &note_id, &top, "open", 1.0).await;
+    app.create_nav(&owner, &note_id, &open, "open child", 1.0).await;
+    let collapsed = app.create_nav(&owner, &note_id, &top, "collapsed", 2.0).await;
+    app.create_nav(&owner, &note_id, &collapsed, "hidden child", 1.0).await;
+
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": collapsed, "is-display": false }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let subtree = |body: Value| {
+        let app = &app;
+        let owner = &owner;
+        async move {
+            let (status, body) = app.post(owner, "/hulunote/get-nav-subtree", body).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            contents(&body["nav-tree"])
+        }
+    };
+
+    assert_eq!(
+        subtree(json!({ "nav-id": top })).await,
+        json!({ "top": [{ "open": ["open child"] }, { "collapsed": ["hidden child"] }] })
+    );
+    assert_eq!(
+        subtree(json!({ "nav-id": top, "skip-hidden": true })).await,
+        json!({ "top": [{ "open": ["open child"] }, "collapsed"] })
+    );
+    assert_eq!(subtree(json!({ "nav-id": top, "depth": 1 })).await, json!({ "top": ["open", "collapsed"] }));
+    // The requested nav itself is always expanded
+    assert_eq!(
+        subtree(json!({ "nav-id": collapsed, "skip-hidden": true })).await,
+        json!({ "collapsed": ["hidden child"] })
+    );
+
+    let stranger = app.create_account().await;
+    let (status, _) = app
+        .post(&stranger, "/hulunote/get-nav-subtree", json!({ "nav-id": top }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // A nav from another note pointing at ours is not part of the tree
+    let stranger_db = app.create_database(&stranger).await;
+    let (foreign, foreign_root) = app.create_note(&stranger, &stranger_db, "Foreign").await;
+    let grafted = app.create_nav(&stranger, &foreign, &foreign_root, "grafted", 1.0).await;
+    sqlx::query("UPDATE hulunote_navs SET parid = $2 WHERE id::text = $1")
+        .bind(&grafted)
+        .bind(&open)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(subtree(json!({ "nav-id": open })).await, json!({ "open": ["open child"] }));
+    let (status, body) = app.post(&owner, "/hulunote/duplicate-nav", json!({ "nav-id": open })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        subtree(json!({ "nav-id": top })).await,
+        json!({ "top": [{ "open": ["open child"] }, { "open": ["open child"] }, { "collapsed": ["hidden child"] }] })
+    );
+}
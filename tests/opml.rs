@@ -0,0 +1,158 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+/// Outline without orders, which OPML import renumbers
+fn shape(outline: &Value) -> Value {
+    Value::Array(
+        outline
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| {
+                json!({
+                    "content": n["content"],
+                    "is-display": n["is-display"],
+                    "properties": n["properties"],
+                    "children": shape(&n["children"])
+                })
+            })
+            .collect(),
+    )
+}
+
+const WORKFLOWY: &str = r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <head><title>Groceries &amp; Chores</title></head>
+  <body>
+    <outline text="Shopping">
+      <outline text="Milk" _complete="true"/>
+      <outline text="Bread" _note="whole grain&#10;two loaves"/>
+    </outline>
+    <outline text="Call &lt;mom&gt;"/>
+  </body>
+</opml>
+"#;
+
+#[tokio::test]
+async fn opml_outlines_become_navs_with_properties() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, database_id.as_bytes()),
+                ("file", Some("export.opml"), WORKFLOWY.as_bytes()),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported"][0]["title"], "Groceries & Chores");
+
+    let note_id = body["imported"][0]["note-id"].as_str().unwrap();
+    let outline = app.note_outline(&owner, note_id).await;
+    assert_eq!(outline[0]["content"], "Shopping");
+    assert_eq!(outline[0]["children"][0]["content"], "Milk");
+    assert_eq!(outline[0]["children"][0]["properties"], "_complete:: true");
+    assert_eq!(outline[0]["children"][1]["properties"], "_note:: \"whole grain\\ntwo loaves\"");
+    assert_eq!(outline[1]["content"], "Call <mom>");
+}
+
+#[tokio::test]
+async fn opml_export_round_trips_attributes() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let (_, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, database_id.as_bytes()),
+                ("file", Some("export.opml"), WORKFLOWY.as_bytes()),
+            ],
+        )
+        .await;
+    let note_id = body["imported"][0]["note-id"].as_str().unwrap().to_string();
+
+    // Add a multi-line nav, a collapsed nav and free-form properties
+    let (_, root_body) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": note_id })).await;
+    let root = root_body["nav-list"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["content"] == "Shopping")
+        .unwrap()["parid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let extra = app.create_nav(&owner, &note_id, &root, "line one\nline \"two\"", 3.0).await;
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": extra, "is-display": false, "properties": "{\"a\": 1}" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let original = shape(&app.note_outline(&owner, &note_id).await);
+
+    let (status, opml) = app
+        .post_raw(&owner, "/hulunote/export-note", json!({ "note-id": note_id, "format": "opml" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let opml = String::from_utf8(opml).unwrap();
+    assert!(opml.contains(r#"<outline text="Milk" _complete="true"/>"#), "{}", opml);
+    assert!(opml.contains(r#"_note="whole grain&#10;two loaves""#), "{}", opml);
+
+    let target_database = app.create_database(&owner).await;
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, target_database.as_bytes()),
+                ("file", Some("copy.opml"), opml.as_bytes()),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let copy_id = body["imported"][0]["note-id"].as_str().unwrap();
+    assert_eq!(shape(&app.note_outline(&owner, copy_id).await), original);
+
+    // A database exports as a ZIP of .opml files
+    let (status, data) = app
+        .post_raw(&owner, "/hulunote/export-database", json!({ "database-id": database_id, "format": "opml" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+    let names: Vec<&str> = archive.file_names().collect();
+    assert_eq!(names, vec!["Groceries & Chores.opml"]);
+}
+
+#[tokio::test]
+async fn invalid_opml_is_reported_per_file() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let (status, body) = app
+        .post_multipart(
+            &owner,
+            "/hulunote/import-notes",
+            &[
+                ("database-id", None, database_id.as_bytes()),
+                ("file", Some("broken.opml"), b"<opml><body><outline text=\"x\">"),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["imported-count"], 0);
+    assert_eq!(body["errors"][0]["file"], "broken.opml");
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn repeated_midpoint_inserts_trigger_renumbering() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Midpoints").await;
+    app.create_nav(&owner, &note_id, &root, "first", 1.0).await;
+    app.create_nav(&owner, &note_id, &root, "last", 2.0).await;
+
+    // Keep inserting right after "first", the way clients average orders
+    let (mut low, mut high) = (1.0_f32, 2.0_f32);
+    let mut renumbered = None;
+    for i in 0..30 {
+        let order = (low + high) / 2.0;
+        let (status, body) = app
+            .post(
+                &owner,
+                "/hulunote/create-or-update-nav",
+                json!({ "note-id": note_id, "parid": root, "content": format!("insert {}", i), "order": order }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        let reordered = body["reordered"].as_array().unwrap();
+        if !reordered.is_empty() {
+            renumbered = Some((i, reordered.len()));
+            break;
+        }
+        high = order;
+        low = 1.0;
+    }
+
+    let (i, count) = renumbered.expect("siblings were never renumbered");
+    assert!(i < 20, "renumbered only after {} inserts", i);
+    assert_eq!(count, i + 3);
+
+    // Every insert still sits right after "first", newest first
+    let outline = app.note_outline(&owner, &note_id).await;
+    let contents: Vec<&str> = outline.as_array().unwrap().iter().map(|n| n["content"].as_str().unwrap()).collect();
+    let mut expected = vec!["first".to_string()];
+    expected.extend((0..=i).rev().map(|j| format!("insert {}", j)));
+    expected.push("last".to_string());
+    assert_eq!(contents, expected);
+    let orders: Vec<f64> = outline.as_array().unwrap().iter().map(|n| n["order"].as_f64().unwrap()).collect();
+    assert_eq!(orders, (1..=orders.len()).map(|o| o as f64).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn tied_orders_in_a_batch_are_renumbered() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Ties").await;
+    let untouched = app.create_nav(&owner, &note_id, &root, "other parent", 5.0).await;
+
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/batch-navs",
+            json!({
+                "note-id": note_id,
+                "ops": [
+                    { "op": "create", "parid": root, "content": "a", "order": 1.0 },
+                    { "op": "create", "parid": root, "content": "b", "order": 1.0 },
+                    { "op": "create", "parid": untouched, "content": "child", "order": 1.0 }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // Only the crowded parent is renumbered
+    let reordered = body["reordered"].as_array().unwrap();
+    assert_eq!(reordered.len(), 3, "{}", body);
+    let a = body["results"][0]["id"].as_str().unwrap();
+    let b = body["results"][1]["id"].as_str().unwrap();
+    let order_of = |id: &str| reordered.iter().find(|o| o["id"] == id).unwrap()["same-deep-order"].clone();
+    let mut tied = vec![order_of(a).as_f64().unwrap(), order_of(b).as_f64().unwrap()];
+    tied.sort_by(f64::total_cmp);
+    assert_eq!(tied, vec![1.0, 2.0]);
+    assert_eq!(order_of(&untouched), 3.0);
+
+    // Spread-out siblings are left alone
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "parid": root, "content": "c", "order": 10.0 }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["reordered"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn renumbering_wins_over_older_edits_and_skips_unmoved_navs() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Clocks").await;
+    let first = app.create_nav(&owner, &note_id, &root, "first", 1.0).await;
+    let last = app.create_nav(&owner, &note_id, &root, "last", 5.0).await;
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let edited = chrono::Utc::now().timestamp_millis();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    // A tie renumbers the navs after "first", which keeps its order
+    let (status, body) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "parid": root, "content": "tie", "order": 1.0 }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["reordered"].as_array().unwrap().len(), 3, "{}", body);
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-note-history", json!({ "note-id": note_id, "nav-id": first }))
+        .await;
+    assert_eq!(body["revisions"].as_array().unwrap().len(), 1, "{}", body);
+
+    // An edit made before the renumbering arrives late and loses to it
+    let op = json!({ "type": "nav_op", "op": "update", "id": last, "note-id": note_id, "order": 1.0, "backend-ts": edited });
+    let mut connection = hulunote_server::handlers::ws::WsConnection::open(&app.state, owner.id).await.unwrap();
+    let ack = connection.handle_message(&app.state, &op.to_string()).await.remove(0);
+    assert_eq!(ack["ignored"], json!(["order"]), "{}", ack);
+    assert_eq!(ack["nav"]["same-deep-order"], 3.0);
+}
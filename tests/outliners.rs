@@ -0,0 +1,145 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+
+async fn import(app: &TestApp, owner: &TestAccount, database_id: &str, filename: &str, data: &Value) -> Value {
+    let data = serde_json::to_vec(data).unwrap();
+    let (status, body) = app
+        .post_multipart(
+            owner,
+            "/hulunote/import-notes",
+            &[("database-id", None, database_id.as_bytes()), ("file", Some(filename), &data)],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    body
+}
+
+fn note_id<'a>(body: &'a Value, title: &str) -> &'a str {
+    body["imported"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["title"] == title)
+        .unwrap_or_else(|| panic!("{} not imported: {}", title, body))["note-id"]
+        .as_str()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn roam_export_becomes_notes() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let export = json!([
+        {
+            "title": "October 17th, 2026",
+            "children": [
+                { "string": "met with [[Project X]]", "uid": "aB3dE6fGh", "children": [
+                    { "string": "follow up", "uid": "Zz9Yy8Xx7" }
+                ]},
+                { "string": "closed block", "uid": "cLoSeD123", "open": false }
+            ]
+        },
+        {
+            "title": "Project X",
+            "children": [
+                { "string": "see ((aB3dE6fGh)) from [[October 17th, 2026]]", "uid": "qQ1wW2eE3" }
+            ]
+        }
+    ]);
+    let body = import(&app, &owner, &database_id, "roam.json", &export).await;
+    assert_eq!(body["imported-count"], 2, "{}", body);
+
+    let daily = note_id(&body, "2026-10-17");
+    let outline = app.note_outline(&owner, daily).await;
+    assert_eq!(outline[0]["content"], "met with [[Project X]]");
+    assert_eq!(outline[0]["children"][0]["content"], "follow up");
+    assert_eq!(outline[1]["is-display"], false);
+
+    let (_, navs) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": daily })).await;
+    let block = navs["nav-list"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["content"] == "met with [[Project X]]")
+        .unwrap()
+        .clone();
+    assert_eq!(block["extra-id"], "aB3dE6fGh");
+
+    // References follow the new ids and the normalized title
+    let project = note_id(&body, "Project X");
+    let outline = app.note_outline(&owner, project).await;
+    assert_eq!(
+        outline[0]["content"],
+        format!("see (({})) from [[2026-10-17]]", block["id"].as_str().unwrap())
+    );
+    let (_, backlinks) = app
+        .post(&owner, "/hulunote/get-backlinks", json!({ "note-id": daily }))
+        .await;
+    assert_eq!(backlinks["backlinks"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn logseq_export_becomes_notes() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let export = json!({
+        "version": 1,
+        "blocks": [
+            {
+                "id": "6650a1b2-0000-4000-8000-000000000001",
+                "page-name": "Oct 5th, 2026",
+                "properties": {},
+                "format": "markdown",
+                "children": [
+                    {
+                        "id": "6650a1b2-0000-4000-8000-000000000002",
+                        "properties": { "status": "done", "tags": ["a", "b"], "collapsed": true },
+                        "format": "markdown",
+                        "content": "reviewed\nstatus:: done\ntags:: a, b\ncollapsed:: true",
+                        "children": [
+                            { "id": "6650a1b2-0000-4000-8000-000000000003", "properties": {}, "content": "child", "children": [] }
+                        ]
+                    }
+                ]
+            }
+        ]
+    });
+    let body = import(&app, &owner, &database_id, "logseq.json", &export).await;
+    assert_eq!(body["imported-count"], 1, "{}", body);
+
+    let daily = note_id(&body, "2026-10-05");
+    let outline = app.note_outline(&owner, daily).await;
+    assert_eq!(outline[0]["content"], "reviewed");
+    assert_eq!(outline[0]["properties"], "status:: done\ntags:: a, b");
+    assert_eq!(outline[0]["is-display"], false);
+    assert_eq!(outline[0]["children"][0]["content"], "child");
+
+    let (_, navs) = app.post(&owner, "/hulunote/get-note-navs", json!({ "note-id": daily })).await;
+    assert!(navs["nav-list"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|n| n["extra-id"] == "6650a1b2-0000-4000-8000-000000000003"));
+}
+
+#[tokio::test]
+async fn unknown_json_is_rejected_per_file() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+
+    let body = import(&app, &owner, &database_id, "other.json", &json!({ "pages": [] })).await;
+    assert_eq!(body["imported-count"], 0);
+    assert!(body["errors"][0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("Unrecognized JSON format"));
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+use hulunote_server::handlers::ws::WsConnection;
+
+async fn send(app: &TestApp, connection: &mut WsConnection, message: Value) -> Value {
+    connection.handle_message(&app.state, &message.to_string()).await.remove(0)
+}
+
+/// The next events pushed to a connection, or nothing if none arrive soon
+async fn next(app: &TestApp, connection: &mut WsConnection) -> Vec<Value> {
+    match tokio::time::timeout(Duration::from_millis(200), connection.next_events(&app.state)).await {
+        Ok(Some(events)) => events.iter().map(|e| serde_json::from_str(e).unwrap()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+async fn invite(app: &TestApp, owner: &TestAccount, database_id: &str) -> TestAccount {
+    let member = app.create_account().await;
+    let (status, body) = app
+        .post(owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": member.id, "role": "viewer" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    member
+}
+
+#[tokio::test]
+async fn viewers_see_each_other_join_move_and_leave() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let stranger = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let member = invite(&app, &owner, &database_id).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Together").await;
+    let (other_note, _) = app.create_note(&owner, &database_id, "Elsewhere").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root, "line", 1.0).await;
+
+    let mut alice = WsConnection::open(&app.state, owner.id).await.unwrap();
+    let mut bob = WsConnection::open(&app.state, member.id).await.unwrap();
+    let reply = send(&app, &mut alice, json!({ "type": "presence", "note-id": note_id })).await;
+    assert_eq!(reply["success"], true, "{}", reply);
+    assert_eq!(reply["viewers"], json!([]));
+
+    // Joining lists who is already there and tells them
+    let reply = send(&app, &mut bob, json!({ "type": "presence", "note-id": note_id, "nav-id": nav_id })).await;
+    assert_eq!(reply["viewers"][0]["connection-id"], alice.id());
+    assert_eq!(reply["viewers"][0]["account-id"], owner.id);
+    let pushed = next(&app, &mut alice).await;
+    assert_eq!(pushed[0]["type"], "presence_joined");
+    assert_eq!(pushed[0]["viewer"]["connection-id"], bob.id());
+    assert_eq!(pushed[0]["viewer"]["nav-id"], nav_id.as_str());
+    assert!(next(&app, &mut bob).await.is_empty());
+
+    // Heartbeats change nothing; moving the cursor is announced
+    send(&app, &mut bob, json!({ "type": "presence", "note-id": note_id, "nav-id": nav_id })).await;
+    assert!(next(&app, &mut alice).await.is_empty());
+    send(&app, &mut alice, json!({ "type": "presence", "note-id": note_id, "nav-id": nav_id })).await;
+    let pushed = next(&app, &mut bob).await;
+    assert_eq!(pushed[0]["type"], "presence_cursor");
+    assert_eq!(pushed[0]["connection-id"], alice.id());
+    assert_eq!(pushed[0]["nav-id"], nav_id.as_str());
+
+    // Switching notes leaves the old one; people on other notes hear nothing
+    let reply = send(&app, &mut bob, json!({ "type": "presence", "note-id": other_note })).await;
+    assert_eq!(reply["viewers"], json!([]));
+    let pushed = next(&app, &mut alice).await;
+    assert_eq!(pushed[0]["type"], "presence_left");
+    assert_eq!(pushed[0]["connection-id"], bob.id());
+    send(&app, &mut alice, json!({ "type": "leave" })).await;
+    assert!(next(&app, &mut bob).await.is_empty());
+
+    // Only collaborators can announce themselves
+    let mut outsider = WsConnection::open(&app.state, stranger.id).await.unwrap();
+    let reply = send(&app, &mut outsider, json!({ "type": "presence", "note-id": note_id })).await;
+    assert_eq!(reply["success"], false);
+    assert_eq!(reply["status"], 403);
+}
+
+#[tokio::test]
+async fn silent_and_closed_connections_leave() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let member = invite(&app, &owner, &database_id).await;
+    let (note_id, _) = app.create_note(&owner, &database_id, "Heartbeats").await;
+
+    let mut alice = WsConnection::open(&app.state, owner.id).await.unwrap();
+    let mut bob = WsConnection::open(&app.state, member.id).await.unwrap();
+    // The same account in a second tab is a separate viewer
+    let mut alice_tab = WsConnection::open(&app.state, owner.id).await.unwrap();
+    for connection in [&mut alice, &mut bob, &mut alice_tab] {
+        send(&app, connection, json!({ "type": "presence", "note-id": note_id })).await;
+    }
+    next(&app, &mut alice).await;
+    next(&app, &mut alice).await;
+    next(&app, &mut bob).await;
+
+    // Recent heartbeats keep everyone; a closed connection leaves at once
+    app.state.ws_broadcaster.expire_presence(Duration::from_secs(60)).await;
+    assert!(next(&app, &mut bob).await.is_empty());
+    alice_tab.close(&app.state).await;
+    let pushed = next(&app, &mut alice).await;
+    assert_eq!(pushed[0]["type"], "presence_left");
+    assert_eq!(pushed[0]["connection-id"], alice_tab.id());
+    assert_eq!(next(&app, &mut bob).await[0]["type"], "presence_left");
+
+    // Connections that miss their heartbeats expire
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    send(&app, &mut bob, json!({ "type": "ping" })).await;
+    app.state.ws_broadcaster.expire_presence(Duration::from_millis(50)).await;
+    let pushed = next(&app, &mut bob).await;
+    assert_eq!(pushed[0]["type"], "presence_left");
+    assert_eq!(pushed[0]["connection-id"], alice.id());
+    let reply = send(&app, &mut bob, json!({ "type": "presence", "note-id": note_id })).await;
+    assert_eq!(reply["viewers"], json!([]));
+}
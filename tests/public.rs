@@ -0,0 +1,172 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+
+/// GET a public route without a token
+async fn get(app: &TestApp, uri: &str) -> (StatusCode, Value) {
+    app.send(Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+async fn get_html(app: &TestApp, uri: &str) -> (StatusCode, String) {
+    let (status, bytes) = app.send_raw(Request::get(uri).body(Body::empty()).unwrap()).await;
+    (status, String::from_utf8(bytes).unwrap())
+}
+
+async fn create_link(app: &TestApp, owner: &TestAccount, body: Value) -> Value {
+    let (status, body) = app.post(owner, "/hulunote/create-share-link", body).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    body["share-link"].clone()
+}
+
+#[tokio::test]
+async fn note_is_shared_by_token_until_revoked() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Recipes <2026>").await;
+    let soup = app.create_nav(&owner, &note_id, &root, "Soup & <b>bread</b>", 1.0).await;
+    app.create_nav(&owner, &note_id, &soup, "Boil water", 1.0).await;
+    app.create_nav(&owner, &note_id, &root, "Dessert", 2.0).await;
+
+    let stranger = app.create_account().await;
+    let (status, _) = app
+        .post(&stranger, "/hulunote/create-share-link", json!({ "note-id": note_id }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-share-link", json!({ "note-id": note_id, "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let link = create_link(&app, &owner, json!({ "note-id": note_id })).await;
+    assert_eq!(link["target-type"], "note");
+    assert_eq!(link["has-password"], false);
+    assert_eq!(link["active"], true);
+    let token = link["token"].as_str().unwrap();
+    assert!(token.len() >= 40);
+    let uri = link["url"].as_str().unwrap().to_string();
+
+    let (status, body) = get(&app, &uri).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["share"]["target-type"], "note");
+    assert_eq!(body["note"]["title"], "Recipes <2026>");
+    assert_eq!(body["note"]["pv"], 1);
+    assert_eq!(body["navs"][0]["content"], "Soup & <b>bread</b>");
+    assert_eq!(body["navs"][0]["children"][0]["content"], "Boil water");
+    assert_eq!(body["navs"][1]["content"], "Dessert");
+    assert!(body["navs"][0].get("account-id").is_none());
+
+    // The HTML view escapes content and counts as a view too
+    let (status, html) = get_html(&app, &format!("{}/html", uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(html.contains("<title>Recipes &lt;2026&gt;</title>"), "{}", html);
+    assert!(html.contains("<li>Soup &amp; &lt;b&gt;bread&lt;/b&gt;\n<ul>\n<li>Boil water"), "{}", html);
+
+    // Raw ids do not work, even for public notes
+    let (status, _) = app
+        .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": note_id, "is-public": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(get(&app, &format!("/public/share/{}", note_id)).await.0, StatusCode::NOT_FOUND);
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-share-link-list", json!({ "note-id": note_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["share-links"][0]["view-count"], 2);
+    assert!(body["share-links"][0]["last-viewed-at"].is_string());
+    let (_, body) = app.post(&stranger, "/hulunote/get-share-link-list", json!({})).await;
+    assert_eq!(body["share-links"], json!([]));
+
+    // Only the owner can revoke, and a revoked link stops resolving
+    let id = link["id"].clone();
+    let (status, _) = app.post(&stranger, "/hulunote/revoke-share-link", json!({ "id": id })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = app.post(&owner, "/hulunote/revoke-share-link", json!({ "id": id })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["share-link"]["active"], false);
+    assert_eq!(get(&app, &uri).await.0, StatusCode::NOT_FOUND);
+
+    let (_, body) = app.post(&owner, "/hulunote/get-share-link-list", json!({})).await;
+    assert_eq!(body["share-links"], json!([]));
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-share-link-list", json!({ "include-revoked": true }))
+        .await;
+    assert_eq!(body["share-links"].as_array().unwrap().len(), 1);
+
+    // Deleting the note takes its links offline
+    let uri = create_link(&app, &owner, json!({ "note-id": note_id })).await["url"].as_str().unwrap().to_string();
+    let (status, _) = app
+        .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": note_id, "is-delete": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(get(&app, &uri).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn links_can_expire_require_passwords_and_cover_subtrees() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Journal").await;
+    let (other_id, _) = app.create_note(&owner, &database_id, "Archive").await;
+    let entry = app.create_nav(&owner, &note_id, &root, "Shared entry", 1.0).await;
+    app.create_nav(&owner, &note_id, &entry, "Detail", 1.0).await;
+    app.create_nav(&owner, &note_id, &root, "Private entry", 2.0).await;
+
+    // A nav link shares its subtree only
+    let uri = create_link(&app, &owner, json!({ "nav-id": entry })).await["url"].as_str().unwrap().to_string();
+    let (status, body) = get(&app, &uri).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["nav"]["content"], "Shared entry");
+    assert_eq!(body["nav"]["children"][0]["content"], "Detail");
+    assert_eq!(get(&app, &format!("{}/note/{}", uri, note_id)).await.0, StatusCode::NOT_FOUND);
+
+    // A password-protected database link
+    let link = create_link(&app, &owner, json!({ "database-id": database_id, "password": "s3cret" })).await;
+    assert_eq!(link["has-password"], true);
+    let uri = link["url"].as_str().unwrap().to_string();
+    assert_eq!(get(&app, &uri).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(get(&app, &format!("{}?password=wrong", uri)).await.0, StatusCode::UNAUTHORIZED);
+    let (status, html) = get_html(&app, &format!("{}/html", uri)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(html.contains("<input type=\"password\" name=\"password\""), "{}", html);
+
+    let request = Request::get(&uri).header("x-share-password", "s3cret").body(Body::empty()).unwrap();
+    let (status, body) = app.send(request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let titles: Vec<&str> = body["notes"].as_array().unwrap().iter().map(|n| n["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["Archive", "Journal"]);
+    let (status, html) = get_html(&app, &format!("{}/html?password=s3cret", uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(html.contains(&format!("{}/note/{}/html\">Archive</a>", uri, other_id)), "{}", html);
+
+    // Notes of the shared database open through the same link, others do not
+    let (status, body) = get(&app, &format!("{}/note/{}?password=s3cret", uri, note_id)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["navs"].as_array().unwrap().len(), 2);
+    let elsewhere = app.create_database(&owner).await;
+    let (foreign, _) = app.create_note(&owner, &elsewhere, "Elsewhere").await;
+    assert_eq!(get(&app, &format!("{}/note/{}?password=s3cret", uri, foreign)).await.0, StatusCode::NOT_FOUND);
+
+    // Expired links stop resolving
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-share-link", json!({ "note-id": note_id, "expires-at": "2020-01-01T00:00:00Z" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let link = create_link(&app, &owner, json!({ "note-id": note_id, "expires-at": "2999-01-01T00:00:00Z" })).await;
+    let uri = link["url"].as_str().unwrap().to_string();
+    assert_eq!(get(&app, &uri).await.0, StatusCode::OK);
+    sqlx::query("UPDATE hulunote_share_links SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1")
+        .bind(link["id"].as_i64().unwrap())
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(get(&app, &uri).await.0, StatusCode::NOT_FOUND);
+    let (_, body) = app.post(&owner, "/hulunote/get-share-link-list", json!({ "note-id": note_id })).await;
+    assert_eq!(body["share-links"][0]["active"], false);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+use hulunote_server::handlers::{parse_properties, parse_tags};
+
+async fn create_nav_with_properties(
+    app: &TestApp,
+    owner: &TestAccount,
+    note_id: &str,
+    parid: &str,
+    content: &str,
+    properties: &str,
+) -> String {
+    let (status, body) = app
+        .post(
+            owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "parid": parid, "content": content, "properties": properties }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    body["id"].as_str().unwrap().to_string()
+}
+
+fn contents(body: &Value) -> Vec<String> {
+    let mut contents: Vec<String> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["nav"]["content"].as_str().unwrap().to_string())
+        .collect();
+    contents.sort();
+    contents
+}
+
+#[test]
+fn tags_and_properties_are_parsed() {
+    assert_eq!(
+        parse_tags("#todo call #[[Big Project]] about page#anchor, #Todo and ## heading #a/b."),
+        vec!["todo", "Big Project", "a/b"]
+    );
+    assert_eq!(
+        parse_properties("Status:: done\nnot a property\npriority:: 2\nstatus:: open\nempty::"),
+        vec![("priority".to_string(), "2".to_string()), ("status".to_string(), "open".to_string())]
+    );
+    assert_eq!(
+        parse_properties(r#"{"due": "2026-10-17", "points": 3, "gone": null}"#),
+        vec![("due".to_string(), "2026-10-17".to_string()), ("points".to_string(), "3".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn navs_are_found_by_tag_and_property() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Tasks").await;
+    let project = app.create_nav(&owner, &note_id, &root, "Project", 1.0).await;
+    let first = create_nav_with_properties(&app, &owner, &note_id, &project, "Write docs #work", "priority:: 1\ndue:: 2026-10-01").await;
+    create_nav_with_properties(&app, &owner, &note_id, &project, "Fix bug #Work #urgent", "priority:: 3\ndue:: 2026-10-20").await;
+    create_nav_with_properties(&app, &owner, &note_id, &root, "Groceries", r#"{"tags": "home, #[[Errands]]", "priority": 2}"#).await;
+    let (other_note, other_root) = app.create_note(&owner, &database_id, "Elsewhere").await;
+    app.create_nav(&owner, &other_note, &other_root, "Unrelated #work", 1.0).await;
+
+    let query = |body: Value| {
+        let (app, owner) = (&app, &owner);
+        async move {
+            let (status, body) = app.post(owner, "/hulunote/query-navs", body).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            body
+        }
+    };
+
+    let body = query(json!({ "database-id": database_id, "tags": ["WORK"] })).await;
+    assert_eq!(contents(&body), vec!["Fix bug #Work #urgent", "Unrelated #work", "Write docs #work"]);
+
+    let body = query(json!({ "note-id": note_id, "tags": ["#work", "urgent"] })).await;
+    assert_eq!(contents(&body), vec!["Fix bug #Work #urgent"]);
+    assert_eq!(body["results"][0]["note-title"], "Tasks");
+    assert_eq!(body["results"][0]["path"], json!([{ "id": project, "content": "Project" }]));
+
+    // Tags from a tags:: property, numeric and text ranges, and equality
+    let body = query(json!({ "tags": ["errands"] })).await;
+    assert_eq!(contents(&body), vec!["Groceries"]);
+    let body = query(json!({ "database-id": database_id, "properties": [{ "key": "priority", "gte": 2 }] })).await;
+    assert_eq!(contents(&body), vec!["Fix bug #Work #urgent", "Groceries"]);
+    let body = query(json!({ "database-id": database_id, "properties": [{ "key": "Due", "lt": "2026-10-17" }] })).await;
+    assert_eq!(contents(&body), vec!["Write docs #work"]);
+    let body = query(json!({ "database-id": database_id, "properties": [{ "key": "priority", "value": "3" }] })).await;
+    assert_eq!(contents(&body), vec!["Fix bug #Work #urgent"]);
+
+    // Edits re-index the nav, and deleted navs drop out
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": first, "content": "Write docs", "properties": "priority:: 5" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let body = query(json!({ "note-id": note_id, "tags": ["work"] })).await;
+    assert_eq!(contents(&body), vec!["Fix bug #Work #urgent"]);
+    let body = query(json!({ "note-id": note_id, "properties": [{ "key": "priority", "gt": 4 }] })).await;
+    assert_eq!(contents(&body), vec!["Write docs"]);
+
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": first, "is-delete": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let body = query(json!({ "note-id": note_id, "properties": [{ "key": "priority" }] })).await;
+    assert_eq!(contents(&body), vec!["Fix bug #Work #urgent", "Groceries"]);
+
+    let (status, _) = app.post(&owner, "/hulunote/query-navs", json!({ "note-id": note_id })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let stranger = app.create_account().await;
+    let (status, _) = app
+        .post(&stranger, "/hulunote/query-navs", json!({ "database-id": database_id, "tags": ["work"] }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
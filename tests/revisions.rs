@@ -0,0 +1,136 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use common::{TestAccount, TestApp};
+
+/// A point in time strictly between the changes before and after it
+async fn checkpoint() -> i64 {
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let now = chrono::Utc::now().timestamp_millis();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    now
+}
+
+async fn update_nav(app: &TestApp, owner: &TestAccount, note_id: &str, nav_id: &str, fields: Value) {
+    let mut body = fields;
+    body["note-id"] = json!(note_id);
+    body["id"] = json!(nav_id);
+    let (status, body) = app.post(owner, "/hulunote/create-or-update-nav", body).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn history_lists_changes_with_changed_fields() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "History").await;
+    let nav = app.create_nav(&owner, &note_id, &root, "draft", 1.0).await;
+
+    update_nav(&app, &owner, &note_id, &nav, json!({ "content": "final", "order": 2.0 })).await;
+    // No-op updates are not recorded
+    update_nav(&app, &owner, &note_id, &nav, json!({ "content": "final" })).await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-note-history", json!({ "note-id": note_id, "nav-id": nav }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let revisions = body["revisions"].as_array().unwrap();
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions[0]["revision"]["content"], "final");
+    assert_eq!(revisions[0]["revision"]["account-id"], owner.id);
+    assert_eq!(revisions[0]["changed"], json!(["content", "same-deep-order"]));
+    assert_eq!(revisions[1]["revision"]["op"], "create");
+    assert_eq!(revisions[1]["changed"], json!(["created"]));
+
+    let other = app.create_account().await;
+    let (status, _) = app
+        .post(&other, "/hulunote/get-note-history", json!({ "note-id": note_id }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn diff_and_restore_a_note() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Plan").await;
+    let first = app.create_nav(&owner, &note_id, &root, "keep me", 1.0).await;
+    let second = app.create_nav(&owner, &note_id, &first, "child", 1.0).await;
+    let original = app.note_outline(&owner, &note_id).await;
+
+    let before = checkpoint().await;
+
+    update_nav(&app, &owner, &note_id, &first, json!({ "content": "overwritten" })).await;
+    update_nav(&app, &owner, &note_id, &second, json!({ "parid": root, "order": 2.0 })).await;
+    let added = app.create_nav(&owner, &note_id, &root, "new nav", 3.0).await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/diff-note", json!({ "note-id": note_id, "from": before }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let changes = body["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 3, "{}", body);
+    let change = |id: &str| changes.iter().find(|c| c["nav-id"] == id).unwrap();
+    assert_eq!(change(&first)["change"], "modified");
+    assert_eq!(change(&first)["fields"], json!(["content"]));
+    assert_eq!(change(&first)["before"]["content"], "keep me");
+    assert_eq!(change(&second)["fields"], json!(["parid", "same-deep-order"]));
+    assert_eq!(change(&added)["change"], "added");
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/restore-note", json!({ "note-id": note_id, "at": before }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["restored-navs"], 3);
+    assert_eq!(app.note_outline(&owner, &note_id).await, original);
+
+    // The restore is itself part of the history and can be undone
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-note-history", json!({ "note-id": note_id, "nav-id": first }))
+        .await;
+    assert_eq!(body["revisions"][0]["revision"]["op"], "restore");
+}
+
+#[tokio::test]
+async fn restore_a_single_nav() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Single").await;
+    let nav = app.create_nav(&owner, &note_id, &root, "v1 [[Target]]", 1.0).await;
+    let other = app.create_nav(&owner, &note_id, &root, "untouched", 2.0).await;
+
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-note-history", json!({ "note-id": note_id, "nav-id": nav }))
+        .await;
+    let first_revision = body["revisions"][0]["revision"]["id"].clone();
+
+    update_nav(&app, &owner, &note_id, &nav, json!({ "content": "v2" })).await;
+    update_nav(&app, &owner, &note_id, &other, json!({ "content": "changed too" })).await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/restore-nav", json!({ "nav-id": nav, "revision-id": first_revision }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["restored"], true);
+
+    let outline = app.note_outline(&owner, &note_id).await;
+    assert_eq!(outline[0]["content"], "v1 [[Target]]");
+    assert_eq!(outline[1]["content"], "changed too");
+
+    // Links follow the restored content
+    let (target, _) = app.create_note(&owner, &database_id, "Target").await;
+    let (_, body) = app.post(&owner, "/hulunote/get-backlinks", json!({ "note-id": target })).await;
+    assert_eq!(body["backlinks"].as_array().unwrap().len(), 1);
+
+    let reader = app.create_account().await;
+    let (status, _) = app
+        .post(&reader, "/hulunote/restore-nav", json!({ "nav-id": nav, "revision-id": first_revision }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
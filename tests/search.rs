@@ -0,0 +1,65 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn search_finds_titles_and_navs_with_paths() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let other = app.create_account().await;
+
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root_nav_id) = app.create_note(&owner, &database_id, "Gardening plans").await;
+    let parent_id = app.create_nav(&owner, &note_id, &root_nav_id, "Vegetables", 1.0).await;
+    let child_id = app.create_nav(&owner, &note_id, &parent_id, "plant tomatoes in spring", 1.0).await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/search", json!({ "query": "tomatoes", "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["type"], "nav");
+    assert_eq!(results[0]["nav-id"], child_id.as_str());
+    assert_eq!(results[0]["note-id"], note_id.as_str());
+    assert!(results[0]["snippet"].as_str().unwrap().contains("<mark>tomatoes</mark>"));
+    assert_eq!(results[0]["path"], json!([{ "id": parent_id, "content": "Vegetables" }]));
+
+    let (_, body) = app
+        .post(&owner, "/hulunote/search", json!({ "query": "gardening", "database-id": database_id }))
+        .await;
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["type"], "note");
+
+    // Root navs are never hits, and snippets carry no markup but the highlights
+    sqlx::query("UPDATE hulunote_navs SET content = 'Gardening' WHERE id::text = $1")
+        .bind(&root_nav_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    app.create_nav(&owner, &note_id, &root_nav_id, "<img src=x onerror=\"alert('pests')\"> & pests", 2.0).await;
+    let (_, body) = app
+        .post(&owner, "/hulunote/search", json!({ "query": "pests", "database-id": database_id }))
+        .await;
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1, "{}", body);
+    let snippet = results[0]["snippet"].as_str().unwrap();
+    assert!(snippet.contains("&quot;alert(&#39;<mark>pests</mark>&#39;)&quot;&gt; &amp; <mark>pests</mark>"), "{}", snippet);
+    assert!(!snippet.replace("<mark>", "").replace("</mark>", "").contains('<'), "{}", snippet);
+    let (_, body) = app
+        .post(&owner, "/hulunote/search", json!({ "query": "gardening", "database-id": database_id }))
+        .await;
+    assert_eq!(body["results"].as_array().unwrap().len(), 1, "{}", body);
+
+    // Other accounts neither see the hits nor may name the database
+    let (_, body) = app.post(&other, "/hulunote/search", json!({ "query": "tomatoes" })).await;
+    assert!(body["results"].as_array().unwrap().is_empty());
+
+    let (status, _) = app
+        .post(&other, "/hulunote/search", json!({ "query": "tomatoes", "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,229 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+use hulunote_server::handlers::ws::{WsConnection, WsEvent, WsMessage};
+
+async fn send(app: &TestApp, connection: &mut WsConnection, message: Value) -> Vec<Value> {
+    connection.handle_message(&app.state, &message.to_string()).await
+}
+
+/// The next events pushed to a connection, or nothing if none arrive soon
+async fn next(app: &TestApp, connection: &mut WsConnection) -> Vec<Value> {
+    match tokio::time::timeout(Duration::from_millis(200), connection.next_events(&app.state)).await {
+        Ok(Some(events)) => events.iter().map(|e| serde_json::from_str(e).unwrap()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The `seq`s of every event pushed to a connection until it goes quiet
+async fn pushed_seqs(app: &TestApp, connection: &mut WsConnection) -> Vec<i64> {
+    let mut seqs = Vec::new();
+    loop {
+        let pushed = next(app, connection).await;
+        if pushed.is_empty() {
+            return seqs;
+        }
+        seqs.extend(pushed.iter().map(|e| e["seq"].as_i64().unwrap()));
+    }
+}
+
+async fn post_ok(app: &TestApp, account: &TestAccount, path: &str, body: Value) {
+    let (status, body) = app.post(account, path, body).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+async fn edit(app: &TestApp, account: &TestAccount, note_id: &str, nav_id: &str, content: &str) {
+    let body = json!({ "note-id": note_id, "id": nav_id, "content": content });
+    post_ok(app, account, "/hulunote/create-or-update-nav", body).await;
+}
+
+#[tokio::test]
+async fn subscriptions_pick_databases_and_notes() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let stranger = app.create_account().await;
+    let first_db = app.create_database(&owner).await;
+    let second_db = app.create_database(&owner).await;
+    let (first_note, first_root) = app.create_note(&owner, &first_db, "First").await;
+    let (other_note, other_root) = app.create_note(&owner, &first_db, "Other").await;
+    let (second_note, second_root) = app.create_note(&owner, &second_db, "Second").await;
+    let first_nav = app.create_nav(&owner, &first_note, &first_root, "a", 1.0).await;
+    let other_nav = app.create_nav(&owner, &other_note, &other_root, "b", 1.0).await;
+    let second_nav = app.create_nav(&owner, &second_note, &second_root, "c", 1.0).await;
+
+    let mut connection = WsConnection::open(&app.state, owner.id).await.unwrap();
+    let reply = send(&app, &mut connection, json!({ "type": "subscribe", "note-ids": [first_note] })).await;
+    assert_eq!(reply[0]["type"], "subscribed");
+    assert_eq!(reply[0]["note-ids"], json!([first_note]), "{}", reply[0]);
+
+    // Only the subscribed note's events come through
+    edit(&app, &owner, &second_note, &second_nav, "skipped").await;
+    edit(&app, &owner, &other_note, &other_nav, "skipped").await;
+    edit(&app, &owner, &first_note, &first_nav, "sent").await;
+    let pushed = next(&app, &mut connection).await;
+    assert_eq!(pushed.len(), 1, "{:?}", pushed);
+    assert_eq!(pushed[0]["content"], "sent");
+    assert!(pushed[0]["seq"].as_i64().unwrap() > 0);
+
+    // A database subscription covers all of its notes and the database itself
+    send(&app, &mut connection, json!({ "type": "subscribe", "database-ids": [second_db] })).await;
+    edit(&app, &owner, &second_note, &second_nav, "now sent").await;
+    assert_eq!(next(&app, &mut connection).await[0]["content"], "now sent");
+    let (status, _) = app.post(&owner, "/hulunote/update-database", json!({ "database-id": second_db, "is-public": true })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(next(&app, &mut connection).await[0]["type"], "database_updated");
+
+    let reply = send(&app, &mut connection, json!({ "type": "unsubscribe", "database-ids": [second_db] })).await;
+    assert_eq!(reply[0]["database-ids"], json!([]));
+    edit(&app, &owner, &second_note, &second_nav, "skipped again").await;
+    assert!(next(&app, &mut connection).await.is_empty());
+
+    // Only databases shared with the account can be subscribed to
+    let mut outsider = WsConnection::open(&app.state, stranger.id).await.unwrap();
+    let reply = send(&app, &mut outsider, json!({ "type": "subscribe", "database-ids": [first_db] })).await;
+    assert_eq!(reply[0]["success"], false);
+    assert_eq!(reply[0]["status"], 403);
+    let reply = send(&app, &mut outsider, json!({ "type": "subscribe", "note-ids": [first_note] })).await;
+    assert_eq!(reply[0]["status"], 403);
+}
+
+#[tokio::test]
+async fn missed_events_are_replayed() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Offline").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root, "start", 1.0).await;
+
+    let last_seen = WsConnection::open(&app.state, owner.id).await.unwrap().last_seq();
+    let since = chrono::Utc::now().timestamp_millis();
+    for content in ["one", "two", "three"] {
+        edit(&app, &owner, &note_id, &nav_id, content).await;
+    }
+
+    // A client reconnecting with its last seq gets what it missed, in order
+    let mut connection = WsConnection::open(&app.state, owner.id).await.unwrap();
+    let replies = send(&app, &mut connection, json!({ "type": "resume", "last-seq": last_seen })).await;
+    let contents: Vec<&Value> = replies.iter().filter_map(|r| r.get("content")).collect();
+    assert_eq!(contents, vec!["one", "two", "three"], "{:?}", replies);
+    assert!(replies[0]["seq"].as_i64().unwrap() < replies[1]["seq"].as_i64().unwrap());
+    assert_eq!(replies[3]["type"], "resumed");
+    assert_eq!(replies[3]["replayed"], 3);
+    assert_eq!(replies[3]["seq"], connection.last_seq());
+
+    let replies = send(&app, &mut connection, json!({ "type": "resume", "backend-ts": since - 1 })).await;
+    assert_eq!(replies.last().unwrap()["replayed"], 3, "{:?}", replies);
+    let week_ago = since - 8 * 24 * 3600 * 1000;
+    let replies = send(&app, &mut connection, json!({ "type": "resume", "backend-ts": week_ago })).await;
+    assert_eq!(replies[0]["type"], "resync");
+
+    // A connection that falls behind its channel replays the dropped events
+    let start = connection.last_seq();
+    for _ in 0..70 {
+        let event = WsEvent::DatabaseDeleted { database_id: database_id.clone(), backend_ts: since };
+        app.state.ws_broadcaster.broadcast_to_collaborators(&app.state.pool, event).await;
+    }
+    let caught_up = next(&app, &mut connection).await;
+    assert_eq!(caught_up.len(), 70);
+    assert!(caught_up.iter().all(|e| e["seq"].as_i64().unwrap() > start));
+    edit(&app, &owner, &note_id, &nav_id, "live").await;
+    let pushed = next(&app, &mut connection).await;
+    assert_eq!(pushed.len(), 1, "{:?}", pushed);
+    assert_eq!(pushed[0]["content"], "live");
+}
+
+#[tokio::test]
+async fn restores_are_pushed_and_replayed() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Undo").await;
+    let nav_id = app.create_nav(&owner, &note_id, &root, "original", 1.0).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let before_edit = chrono::Utc::now().timestamp_millis();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    edit(&app, &owner, &note_id, &nav_id, "edited").await;
+
+    let mut live = WsConnection::open(&app.state, owner.id).await.unwrap();
+    let last_seen = live.last_seq();
+    // History restores send the navs in their restored state
+    post_ok(&app, &owner, "/hulunote/restore-note", json!({ "note-id": note_id, "at": before_edit })).await;
+    let pushed = next(&app, &mut live).await;
+    assert_eq!(pushed[0]["type"], "navs_batch", "{:?}", pushed);
+    assert_eq!(pushed[0]["navs"][0]["content"], "original");
+
+    // So do trash restores of navs, notes and databases
+    edit(&app, &owner, &note_id, &nav_id, "original").await;
+    let deleted = json!({ "note-id": note_id, "id": nav_id, "is-delete": true });
+    post_ok(&app, &owner, "/hulunote/create-or-update-nav", deleted).await;
+    post_ok(&app, &owner, "/hulunote/restore-from-trash", json!({ "nav-id": nav_id })).await;
+    let deleted = json!({ "note-id": note_id, "is-delete": true });
+    post_ok(&app, &owner, "/hulunote/update-hulunote-note", deleted).await;
+    post_ok(&app, &owner, "/hulunote/restore-from-trash", json!({ "note-id": note_id })).await;
+    post_ok(&app, &owner, "/hulunote/delete-database", json!({ "database-id": database_id })).await;
+    post_ok(&app, &owner, "/hulunote/restore-from-trash", json!({ "database-id": database_id })).await;
+
+    // A client that missed all of it gets the restores on resume
+    let mut connection = WsConnection::open(&app.state, owner.id).await.unwrap();
+    let replies = send(&app, &mut connection, json!({ "type": "resume", "last-seq": last_seen })).await;
+    let types: Vec<&str> = replies.iter().map(|r| r["type"].as_str().unwrap()).collect();
+    assert_eq!(
+        types,
+        vec![
+            "navs_batch",
+            "nav_updated",
+            "nav_deleted",
+            "navs_batch",
+            "note_deleted",
+            "note_updated",
+            "database_deleted",
+            "database_updated",
+            "resumed"
+        ],
+        "{:?}",
+        replies
+    );
+    assert_eq!(replies[3]["navs"][0]["is-delete"], false);
+    assert_eq!(replies[5]["is-delete"], false);
+    assert_eq!(replies[7]["is-delete"], false);
+}
+
+#[tokio::test]
+async fn events_published_together_arrive_once() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let mut connection = WsConnection::open(&app.state, owner.id).await.unwrap();
+
+    // Publishing does not wait on other publishes, so events can arrive out of order
+    let publishes: Vec<_> = (0..20)
+        .map(|_| {
+            let (state, database_id) = (app.state.clone(), database_id.clone());
+            tokio::spawn(async move {
+                let event = WsEvent::DatabaseDeleted { database_id, backend_ts: chrono::Utc::now().timestamp_millis() };
+                state.ws_broadcaster.broadcast_to_collaborators(&state.pool, event).await;
+            })
+        })
+        .collect();
+    for publish in publishes {
+        publish.await.unwrap();
+    }
+    let mut seqs = pushed_seqs(&app, &mut connection).await;
+    seqs.sort();
+    seqs.dedup();
+    assert_eq!(seqs.len(), 20);
+
+    // An event seen before is dropped even when it comes after a later one
+    let sender = app.state.ws_broadcaster.get_sender(owner.id).await;
+    for seq in [seqs[19] + 2, seqs[19] + 1, seqs[19] + 2, seqs[0]] {
+        let text = json!({ "type": "database_deleted", "seq": seq }).to_string();
+        sender.send(WsMessage { seq: Some(seq), database_id: None, note_id: None, text, from_connection: None }).unwrap();
+    }
+    assert_eq!(pushed_seqs(&app, &mut connection).await, vec![seqs[19] + 2, seqs[19] + 1]);
+    assert_eq!(connection.last_seq(), seqs[19] + 2);
+}
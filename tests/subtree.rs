@@ -0,0 +1,177 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn move_rewrites_note_and_database_of_descendants() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let source_db = app.create_database(&owner).await;
+    let (source, source_root) = app.create_note(&owner, &source_db, "Source").await;
+    let parent = app.create_nav(&owner, &source, &source_root, "parent", 1.0).await;
+    let child = app.create_nav(&owner, &source, &parent, "child", 1.0).await;
+    app.create_nav(&owner, &source, &child, "grandchild", 1.0).await;
+    app.create_nav(&owner, &source, &source_root, "stays", 2.0).await;
+    let moved_outline = app.note_outline(&owner, &source).await[0]["children"].clone();
+
+    let target_db = app.create_database(&owner).await;
+    let (target, target_root) = app.create_note(&owner, &target_db, "Target").await;
+    app.create_nav(&owner, &target, &target_root, "existing", 1.0).await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/move-nav", json!({ "nav-id": parent, "target-note-id": target }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["moved"], 3);
+
+    let source_outline = app.note_outline(&owner, &source).await;
+    assert_eq!(source_outline.as_array().unwrap().len(), 1);
+    assert_eq!(source_outline[0]["content"], "stays");
+
+    // Appended after the target's existing navs, with the subtree intact
+    let target_outline = app.note_outline(&owner, &target).await;
+    assert_eq!(target_outline[1]["content"], "parent");
+    assert_eq!(target_outline[1]["order"], 2.0);
+    assert_eq!(target_outline[1]["children"], moved_outline);
+
+    let (count,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM hulunote_navs WHERE note_id = $1 AND database_id = $2",
+    )
+    .bind(&target)
+    .bind(&target_db)
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(count, 5);
+
+    // The move is part of the nav's history
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-note-history", json!({ "note-id": target, "nav-id": parent }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["revisions"][0]["changed"].as_array().unwrap().contains(&json!("note-id")), "{}", body);
+
+    // Moving needs write access to the target
+    let stranger = app.create_account().await;
+    let stranger_db = app.create_database(&stranger).await;
+    let (foreign, _) = app.create_note(&stranger, &stranger_db, "Foreign").await;
+    let (status, _) = app
+        .post(&owner, "/hulunote/move-nav", json!({ "nav-id": parent, "target-note-id": foreign }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn move_rejects_cycles_and_root_navs() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Cycles").await;
+    let parent = app.create_nav(&owner, &note_id, &root, "parent", 1.0).await;
+    let child = app.create_nav(&owner, &note_id, &parent, "child", 1.0).await;
+    let grandchild = app.create_nav(&owner, &note_id, &child, "grandchild", 1.0).await;
+    let original = app.note_outline(&owner, &note_id).await;
+
+    for parid in [&parent, &grandchild] {
+        let (status, _) = app
+            .post(&owner, "/hulunote/move-nav", json!({ "nav-id": parent, "parid": parid }))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, _) = app
+        .post(&owner, "/hulunote/move-nav", json!({ "nav-id": root }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(app.note_outline(&owner, &note_id).await, original);
+
+    // Outdenting the grandchild is fine
+    let (status, body) = app
+        .post(&owner, "/hulunote/move-nav", json!({ "nav-id": grandchild, "parid": parent, "order": 2.0 }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(app.note_outline(&owner, &note_id).await[0]["children"][1]["content"], "grandchild");
+}
+
+#[tokio::test]
+async fn copy_and_duplicate_assign_new_ids() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Copies").await;
+    let parent = app.create_nav(&owner, &note_id, &root, "parent", 1.0).await;
+    let first = app.create_nav(&owner, &note_id, &parent, "first", 1.0).await;
+    app.create_nav(&owner, &note_id, &parent, "second", 2.0).await;
+    app.create_nav(&owner, &note_id, &root, "next", 2.0).await;
+
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": first, "is-delete": true }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/duplicate-nav", json!({ "nav-id": parent }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    // Deleted descendants are not copied
+    assert_eq!(body["copied"], 2);
+    let copy = body["id"].as_str().unwrap().to_string();
+    assert_ne!(copy, parent);
+    assert!(body["ids"][&parent].is_string());
+
+    let outline = app.note_outline(&owner, &note_id).await;
+    let contents: Vec<&str> = outline.as_array().unwrap().iter().map(|n| n["content"].as_str().unwrap()).collect();
+    assert_eq!(contents, vec!["parent", "parent", "next"]);
+    assert_eq!(outline[1]["order"], 1.5);
+    assert_eq!(outline[0]["children"], outline[1]["children"]);
+
+    // Copying into another note leaves the original in place
+    let (other, _) = app.create_note(&owner, &database_id, "Other").await;
+    let (status, body) = app
+        .post(&owner, "/hulunote/copy-nav", json!({ "nav-id": copy, "target-note-id": other }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let copied = app.note_outline(&owner, &other).await;
+    assert_eq!(copied[0]["content"], "parent");
+    assert_eq!(copied[0]["children"], outline[1]["children"]);
+    assert_eq!(app.note_outline(&owner, &note_id).await, outline);
+}
+
+#[tokio::test]
+async fn create_or_update_nav_rejects_bad_parents() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Tree").await;
+    let (other_note, other_root) = app.create_note(&owner, &database_id, "Other").await;
+    let parent = app.create_nav(&owner, &note_id, &root, "parent", 1.0).await;
+    let child = app.create_nav(&owner, &note_id, &parent, "child", 1.0).await;
+    let grandchild = app.create_nav(&owner, &note_id, &child, "grandchild", 1.0).await;
+
+    // Under itself, under a descendant, into another note or under nothing
+    for parid in [parent.clone(), grandchild.clone(), other_root.clone(), uuid::Uuid::new_v4().to_string()] {
+        let (status, body) = app
+            .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "id": parent, "parid": parid }))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": note_id, "parid": other_root, "content": "stray" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .post(&owner, "/hulunote/create-or-update-nav", json!({ "note-id": other_note, "parid": child, "content": "stray" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let outline = app.note_outline(&owner, &note_id).await;
+    assert_eq!(outline.as_array().unwrap().len(), 1, "{}", outline);
+    assert_eq!(outline[0]["content"], "parent");
+    assert_eq!(outline[0]["children"][0]["content"], "child");
+    assert_eq!(outline[0]["children"][0]["children"][0]["content"], "grandchild");
+    assert!(app.note_outline(&owner, &other_note).await.as_array().unwrap().is_empty());
+}
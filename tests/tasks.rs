@@ -0,0 +1,116 @@
+mod common;
+
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+
+use hulunote_server::handlers::{parse_task, set_task_marker};
+use hulunote_server::models::TaskStatus;
+
+fn date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+#[test]
+fn task_markers_and_dates_are_parsed() {
+    let task = parse_task("TODO [#A] ship it\nDEADLINE: <2026-10-20 Tue>\nSCHEDULED: <2026-10-18 Sun>", &[]).unwrap();
+    assert_eq!(task.status, TaskStatus::Todo);
+    assert_eq!(task.priority.as_deref(), Some("A"));
+    assert_eq!(task.due_date, date("2026-10-20"));
+    assert_eq!(task.scheduled_date, date("2026-10-18"));
+
+    let properties = vec![("due".to_string(), "[[October 18th, 2026]]".to_string())];
+    let task = parse_task("{{[[DONE]]}} from Roam", &properties).unwrap();
+    assert_eq!(task.status, TaskStatus::Done);
+    assert_eq!(task.due_date, date("2026-10-18"));
+    assert_eq!(parse_task("NOW", &[]).unwrap().status, TaskStatus::Doing);
+
+    assert!(parse_task("TODOS are not tasks", &[]).is_none());
+    assert!(parse_task("Call about the TODO list", &[]).is_none());
+
+    assert_eq!(set_task_marker("  TODO ship it", TaskStatus::Done), "  DONE ship it");
+    assert_eq!(set_task_marker("{{[[TODO]]}} roam", TaskStatus::Doing), "{{[[DOING]]}} roam");
+    assert_eq!(set_task_marker("plain", TaskStatus::Todo), "TODO plain");
+}
+
+#[tokio::test]
+async fn open_tasks_are_listed_and_toggled() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Work").await;
+    let project = app.create_nav(&owner, &note_id, &root, "Project", 1.0).await;
+    let report = app
+        .create_nav(&owner, &note_id, &project, "TODO [#A] write report DEADLINE: <2026-10-20 Tue>", 1.0)
+        .await;
+    app.create_nav(&owner, &note_id, &root, "LATER someday", 2.0).await;
+    app.create_nav(&owner, &note_id, &root, "DONE shipped", 3.0).await;
+    let plain = app.create_nav(&owner, &note_id, &root, "Just a thought", 4.0).await;
+    let (other, other_root) = app.create_note(&owner, &database_id, "Home").await;
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": other, "parid": other_root, "content": "{{[[TODO]]}} call plumber", "properties": "due:: 2026-10-18" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let plumber = body["id"].as_str().unwrap().to_string();
+
+    let tasks = |body: Value| {
+        let (app, owner) = (&app, &owner);
+        async move {
+            let (status, body) = app.post(owner, "/hulunote/get-tasks", body).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            body["tasks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| t["nav"]["content"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Open tasks across notes, earliest due first
+    let (status, body) = app.post(&owner, "/hulunote/get-tasks", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let listed: Vec<&str> = body["tasks"].as_array().unwrap().iter().map(|t| t["nav"]["content"].as_str().unwrap()).collect();
+    assert_eq!(listed, vec!["{{[[TODO]]}} call plumber", "TODO [#A] write report DEADLINE: <2026-10-20 Tue>", "LATER someday"]);
+    assert_eq!(body["tasks"][1]["task"], json!({ "status": "todo", "priority": "A", "due-date": "2026-10-20", "scheduled-date": null }));
+    assert_eq!(body["tasks"][1]["path"], json!([{ "id": project, "content": "Project" }]));
+
+    assert_eq!(
+        tasks(json!({ "database-id": database_id, "due-to": "2026-10-19" })).await,
+        vec!["{{[[TODO]]}} call plumber"]
+    );
+    assert_eq!(tasks(json!({ "note-id": note_id, "status": ["done"] })).await, vec!["DONE shipped"]);
+
+    // Toggling rewrites the marker, and the task list follows
+    let (status, body) = app.post(&owner, "/hulunote/toggle-task", json!({ "nav-id": report })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["status"], "done");
+    assert_eq!(body["nav"]["content"], "DONE [#A] write report DEADLINE: <2026-10-20 Tue>");
+    assert_eq!(
+        tasks(json!({ "database-id": database_id })).await,
+        vec!["{{[[TODO]]}} call plumber", "LATER someday"]
+    );
+
+    let (_, body) = app.post(&owner, "/hulunote/toggle-task", json!({ "nav-id": report })).await;
+    assert_eq!(body["nav"]["content"], "TODO [#A] write report DEADLINE: <2026-10-20 Tue>");
+    let (_, body) = app
+        .post(&owner, "/hulunote/toggle-task", json!({ "nav-id": plumber, "status": "doing" }))
+        .await;
+    assert_eq!(body["nav"]["content"], "{{[[DOING]]}} call plumber");
+    let (_, body) = app.post(&owner, "/hulunote/toggle-task", json!({ "nav-id": plain })).await;
+    assert_eq!(body["nav"]["content"], "DONE Just a thought");
+
+    // The rewrite is an ordinary edit of the nav
+    let (_, body) = app
+        .post(&owner, "/hulunote/get-note-history", json!({ "note-id": note_id, "nav-id": report }))
+        .await;
+    assert_eq!(body["revisions"].as_array().unwrap().len(), 3, "{}", body);
+
+    let stranger = app.create_account().await;
+    let (status, _) = app.post(&stranger, "/hulunote/toggle-task", json!({ "nav-id": report })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
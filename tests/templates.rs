@@ -0,0 +1,135 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+
+/// A template note with a nested nav and a variable in every nav
+async fn create_template(app: &TestApp, owner: &TestAccount, database_id: &str) -> (String, Value) {
+    let (template, root) = app.create_note(owner, database_id, "Meeting {{date}}").await;
+    let agenda = app.create_nav(owner, &template, &root, "Agenda for {{title}}", 1.0).await;
+    app.create_nav(owner, &template, &agenda, "{{project}} status by {{user}}", 1.0).await;
+    app.create_nav(owner, &template, &root, "Notes from [[{{date}}]] {{unknown}}", 2.0).await;
+
+    let (status, _) = app
+        .post(owner, "/hulunote/update-hulunote-note", json!({ "note-id": template, "is-template": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let outline = app.note_outline(owner, &template).await;
+    (template, outline)
+}
+
+#[tokio::test]
+async fn template_becomes_a_new_note() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (template, original) = create_template(&app, &owner, &database_id).await;
+    let (plain, _) = app.create_note(&owner, &database_id, "Plain").await;
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/get-template-note-list", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["note-list"].as_array().unwrap().len(), 1);
+    assert_eq!(body["note-list"][0]["hulunote-notes/is-template"], true);
+
+    let (status, _) = app
+        .post(&owner, "/hulunote/instantiate-template", json!({ "template-id": plain }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/instantiate-template",
+            json!({ "template-id": template, "date": "2026-10-17", "variables": { "project": "Apollo" } }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["created"], true);
+    assert_eq!(body["copied"], 3);
+    assert_eq!(body["note"]["hulunote-notes/title"], "Meeting 2026-10-17");
+    assert_eq!(body["note"]["hulunote-notes/is-template"], false);
+
+    let note_id = body["note"]["hulunote-notes/id"].as_str().unwrap();
+    let username: (String,) = sqlx::query_as("SELECT username FROM accounts WHERE id = $1")
+        .bind(owner.id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    let outline = app.note_outline(&owner, note_id).await;
+    assert_eq!(outline[0]["content"], "Agenda for Meeting 2026-10-17");
+    assert_eq!(outline[0]["children"][0]["content"], format!("Apollo status by {}", username.0));
+    assert_eq!(outline[1]["content"], "Notes from [[2026-10-17]] {{unknown}}");
+    assert_eq!(outline[1]["order"], original[1]["order"]);
+
+    // Fresh ids, and the template itself is left alone
+    for (old, new) in body["ids"].as_object().unwrap() {
+        assert_ne!(old, new.as_str().unwrap());
+    }
+    assert_eq!(app.note_outline(&owner, &template).await, original);
+
+    // The same title cannot be created twice
+    let (status, _) = app
+        .post(&owner, "/hulunote/instantiate-template", json!({ "template-id": template, "date": "2026-10-17" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn template_is_copied_under_a_nav() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (template, _) = create_template(&app, &owner, &database_id).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Weekly").await;
+    let parent = app.create_nav(&owner, &note_id, &root, "Monday", 1.0).await;
+    app.create_nav(&owner, &note_id, &parent, "existing", 1.0).await;
+
+    let (status, body) = app
+        .post(
+            &owner,
+            "/hulunote/instantiate-template",
+            json!({ "template-id": template, "parid": parent, "variables": { "project": "Gemini" } }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["created"], false);
+    assert_eq!(body["note"]["hulunote-notes/id"], note_id.as_str());
+
+    // Appended after the existing children, in the template's order
+    let outline = app.note_outline(&owner, &note_id).await;
+    let children: Vec<&str> = outline[0]["children"].as_array().unwrap().iter().map(|n| n["content"].as_str().unwrap()).collect();
+    assert_eq!(children[0], "existing");
+    assert_eq!(children[1], "Agenda for Weekly");
+    assert!(children[2].starts_with("Notes from [["), "{:?}", children);
+    assert!(outline[0]["children"][1]["children"][0]["content"].as_str().unwrap().starts_with("Gemini status by "));
+
+    // Writing under someone else's nav is not allowed
+    let stranger = app.create_account().await;
+    let (status, _) = app
+        .post(&stranger, "/hulunote/instantiate-template", json!({ "template-id": template, "parid": parent }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // A template copied into itself is copied as it was before
+    let (looped, looped_root) = app.create_note(&owner, &database_id, "Loop").await;
+    app.create_nav(&owner, &looped, &looped_root, "first", 1.0).await;
+    let second = app.create_nav(&owner, &looped, &looped_root, "second", 2.0).await;
+    let (status, _) = app
+        .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": looped, "is-template": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = app
+        .post(&owner, "/hulunote/instantiate-template", json!({ "template-id": looped, "parid": second }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["copied"], 2);
+    let outline = app.note_outline(&owner, &looped).await;
+    let copied: Vec<&str> = outline[1]["children"].as_array().unwrap().iter().map(|n| n["content"].as_str().unwrap()).collect();
+    assert_eq!(copied, vec!["first", "second"]);
+    assert!(outline[1]["children"][1]["children"].as_array().unwrap().is_empty());
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{TestAccount, TestApp};
+
+async fn trash(app: &TestApp, owner: &TestAccount, database_id: &str) -> Value {
+    let (status, body) = app
+        .post(owner, "/hulunote/get-trash", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    body
+}
+
+fn ids(items: &Value) -> Vec<&str> {
+    items.as_array().unwrap().iter().map(|i| i["id"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn restoring_a_database_brings_back_what_it_cascaded() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (kept, root) = app.create_note(&owner, &database_id, "Kept").await;
+    app.create_nav(&owner, &kept, &root, "content", 1.0).await;
+    let (trashed_earlier, _) = app.create_note(&owner, &database_id, "Trashed earlier").await;
+    let original = app.note_outline(&owner, &kept).await;
+
+    let (status, _) = app
+        .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": trashed_earlier, "is-delete": true }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .post(&owner, "/hulunote/delete-database", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The cascaded note is not listed on its own; the earlier one is
+    let body = trash(&app, &owner, &database_id).await;
+    assert_eq!(ids(&body["databases"]), vec![database_id.as_str()]);
+    assert_eq!(ids(&body["notes"]), vec![trashed_earlier.as_str()]);
+    assert!(body["navs"].as_array().unwrap().is_empty());
+
+    // Restoring the note first is refused
+    let (status, _) = app
+        .post(&owner, "/hulunote/restore-from-trash", json!({ "note-id": trashed_earlier }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/restore-from-trash", json!({ "database-id": database_id }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["restored-notes"], 1, "{}", body);
+    assert_eq!(app.note_outline(&owner, &kept).await, original);
+
+    let body = trash(&app, &owner, &database_id).await;
+    assert!(body["databases"].as_array().unwrap().is_empty());
+    assert_eq!(ids(&body["notes"]), vec![trashed_earlier.as_str()]);
+
+    // Other accounts cannot restore
+    let other = app.create_account().await;
+    let (status, _) = app
+        .post(&other, "/hulunote/restore-from-trash", json!({ "note-id": trashed_earlier }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn restoring_a_nav_restores_its_subtree() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (note_id, root) = app.create_note(&owner, &database_id, "Outline").await;
+    let parent = app.create_nav(&owner, &note_id, &root, "parent", 1.0).await;
+    app.create_nav(&owner, &note_id, &parent, "child", 1.0).await;
+    let original = app.note_outline(&owner, &note_id).await;
+
+    let (status, _) = app
+        .post(
+            &owner,
+            "/hulunote/create-or-update-nav",
+            json!({ "note-id": note_id, "id": parent, "is-delete": true }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let body = trash(&app, &owner, &database_id).await;
+    assert_eq!(ids(&body["navs"]), vec![parent.as_str()]);
+    assert_eq!(body["navs"][0]["note-title"], "Outline");
+    assert!(body["navs"][0]["deleted-at"].is_string());
+
+    let (status, body) = app
+        .post(&owner, "/hulunote/restore-from-trash", json!({ "nav-id": parent }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["restored-navs"], 1);
+    assert_eq!(app.note_outline(&owner, &note_id).await, original);
+    assert!(trash(&app, &owner, &database_id).await["navs"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn purge_removes_items_past_retention() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (old_note, root) = app.create_note(&owner, &database_id, "Old").await;
+    let nav = app.create_nav(&owner, &old_note, &root, "see [[Elsewhere]]", 1.0).await;
+    let (recent_note, _) = app.create_note(&owner, &database_id, "Recent").await;
+
+    for note_id in [&old_note, &recent_note] {
+        let (status, _) = app
+            .post(&owner, "/hulunote/update-hulunote-note", json!({ "note-id": note_id, "is-delete": true }))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    sqlx::query("UPDATE hulunote_notes SET deleted_at = NOW() - INTERVAL '31 days' WHERE id::text = $1")
+        .bind(&old_note)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let stats = hulunote_server::handlers::purge_trash(&app.pool, chrono::Duration::days(30))
+        .await
+        .unwrap();
+    assert!(stats.notes >= 1 && stats.navs >= 2, "{:?}", stats);
+
+    let body = trash(&app, &owner, &database_id).await;
+    assert_eq!(ids(&body["notes"]), vec![recent_note.as_str()]);
+
+    for (table, column) in [
+        ("hulunote_navs", "id::text"),
+        ("hulunote_links", "source_nav_id::text"),
+        ("hulunote_nav_revisions", "nav_id::text"),
+    ] {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {} WHERE {} = $1", table, column))
+            .bind(&nav)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0, "{} still has rows for the purged nav", table);
+    }
+}
+
+#[tokio::test]
+async fn purging_a_database_drops_its_members_and_share_links() {
+    let Some(app) = common::setup().await else { return };
+    let owner = app.create_account().await;
+    let member = app.create_account().await;
+    let database_id = app.create_database(&owner).await;
+    let (status, _) = app
+        .post(&owner, "/hulunote/invite-database-member", json!({ "database-id": database_id, "account-id": member.id, "role": "viewer" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = app.post(&owner, "/hulunote/create-share-link", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, _) = app.post(&owner, "/hulunote/delete-database", json!({ "database-id": database_id })).await;
+    assert_eq!(status, StatusCode::OK);
+    sqlx::query("UPDATE hulunote_databases SET deleted_at = NOW() - INTERVAL '31 days' WHERE id::text = $1")
+        .bind(&database_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let stats = hulunote_server::handlers::purge_trash(&app.pool, chrono::Duration::days(30))
+        .await
+        .unwrap();
+    assert!(stats.databases >= 1, "{:?}", stats);
+
+    for (table, column) in [
+        ("database_members", "database_id::text"),
+        ("hulunote_share_links", "target_id"),
+    ] {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {} WHERE {} = $1", table, column))
+            .bind(&database_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0, "{} still has rows for the purged database", table);
+    }
+}